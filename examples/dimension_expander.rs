@@ -118,12 +118,13 @@ impl Plugin for DimensionExpander {
     }
 
     fn process(&mut self, buffer: &mut AudioBuffer<f32>) {
-        let (inputs, outputs) = buffer.split();
+        let (inputs, mut outputs) = buffer.split();
 
         // Assume 2 channels
-        if inputs.len() < 2 || outputs.len() < 2 {
-            return;
-        }
+        let ((in_l, in_r), (out_l, out_r)) = match (inputs.stereo(), outputs.stereo_mut()) {
+            (Some(stereo_in), Some(stereo_out)) => (stereo_in, stereo_out),
+            _ => return,
+        };
 
         // Resize if size changed
         let size = self.params.size.get();
@@ -132,12 +133,10 @@ impl Plugin for DimensionExpander {
         }
 
         // Iterate over inputs as (&f32, &f32)
-        let (l, r) = inputs.split_at(1);
-        let stereo_in = l[0].iter().zip(r[0].iter());
+        let stereo_in = in_l.iter().zip(in_r.iter());
 
         // Iterate over outputs as (&mut f32, &mut f32)
-        let (mut l, mut r) = outputs.split_at_mut(1);
-        let stereo_out = l[0].iter_mut().zip(r[0].iter_mut());
+        let stereo_out = out_l.iter_mut().zip(out_r.iter_mut());
 
         // Zip and process
         for ((left_in, right_in), (left_out, right_out)) in stereo_in.zip(stereo_out) {
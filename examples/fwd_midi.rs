@@ -16,7 +16,7 @@ struct MyPlugin {
 impl MyPlugin {
     fn send_midi(&mut self) {
         self.send_buffer
-            .send_events(self.recv_buffer.events().events(), &mut self.host);
+            .send_events(self.recv_buffer.events().events(), &self.host);
         self.recv_buffer.clear();
     }
 }
@@ -82,6 +82,7 @@ impl Plugin for SineSynth {
             outputs: 2,
             parameters: 0,
             initial_delay: 0,
+            receive_midi: true,
             ..Info::default()
         }
     }
@@ -135,13 +136,6 @@ impl Plugin for SineSynth {
             }
         }
     }
-
-    fn can_do(&self, can_do: CanDo) -> Supported {
-        match can_do {
-            CanDo::ReceiveMidiEvent => Supported::Yes,
-            _ => Supported::Maybe,
-        }
-    }
 }
 
 plugin_main!(SineSynth);
@@ -55,7 +55,13 @@ impl Plugin for MyPlugin {
                 host,
                 transfer: ParameterTransfer::new(PARAMETER_COUNT),
             }),
-            states: vec![Smoothed::default(); PARAMETER_COUNT],
+            states: (0..PARAMETER_COUNT)
+                .map(|_| {
+                    let mut state = Smoothed::default();
+                    state.set_coefficient(FILTER_FACTOR);
+                    state
+                })
+                .collect(),
             sample_rate: 44100.0,
             phase: 0.0,
         }
@@ -99,6 +105,10 @@ impl Plugin for MyPlugin {
         // Example: Dummy synth adding together a bunch of sines.
         let samples = buffer.samples();
         let mut outputs = buffer.split().1;
+        let (out_l, out_r) = match outputs.stereo_mut() {
+            Some(stereo_out) => stereo_out,
+            None => return,
+        };
         for i in 0..samples {
             let mut sum = 0.0;
             for p in 0..PARAMETER_COUNT {
@@ -107,30 +117,11 @@ impl Plugin for MyPlugin {
                     sum += (self.phase * p as f32 * TWO_PI).sin() * amp;
                 }
             }
-            outputs[0][i] = sum;
-            outputs[1][i] = sum;
+            out_l[i] = sum;
+            out_r[i] = sum;
             self.phase = (self.phase + BASE_FREQUENCY / self.sample_rate).fract();
         }
     }
 }
 
-// Example: Parameter smoothing as an example of non-trivial parameter handling
-// that has to happen when a parameter changes.
-#[derive(Clone, Default)]
-struct Smoothed {
-    state: f32,
-    target: f32,
-}
-
-impl Smoothed {
-    fn set(&mut self, value: f32) {
-        self.target = value;
-    }
-
-    fn get(&mut self) -> f32 {
-        self.state += (self.target - self.state) * FILTER_FACTOR;
-        self.state
-    }
-}
-
 plugin_main!(MyPlugin);
@@ -1,7 +1,12 @@
 //! Structures and types for interfacing with the VST 2.4 API.
 
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use std::fmt;
+use std::ops::Deref;
 use std::os::raw::c_void;
 use std::sync::Arc;
+use std::{mem, ptr};
 
 use self::consts::*;
 use crate::{
@@ -17,11 +22,19 @@ pub mod consts {
     pub const MAX_PARAM_STR_LEN: usize = 32;
     pub const MAX_LABEL: usize = 64;
     pub const MAX_SHORT_LABEL: usize = 8;
+    pub const MAX_CATEGORY_LABEL: usize = 24;
     pub const MAX_PRODUCT_STR_LEN: usize = 64;
     pub const MAX_VENDOR_STR_LEN: usize = 64;
+    pub const MAX_EFFECT_NAME_LEN: usize = 32;
 
     /// VST plugins are identified by a magic number. This corresponds to 0x56737450.
     pub const VST_MAGIC: i32 = ('V' as i32) << 24 | ('s' as i32) << 16 | ('t' as i32) << 8 | ('P' as i32);
+
+    /// `index` of the vendor specific host opcode used to query the position of a plugin in the
+    /// host's plugin chain. `ptr` points to two `usize` values which the host fills with the
+    /// position and the total length of the chain, returning `1` if it did so.
+    pub const CHAIN_POSITION_VENDOR_INDEX: i32 =
+        ('V' as i32) << 24 | ('r' as i32) << 16 | ('C' as i32) << 8 | ('p' as i32);
 }
 
 /// `VSTPluginMain` function signature.
@@ -156,7 +169,18 @@ impl AEffect {
     /// Return handle to Editor object. Only works for plugins created using this library.
     /// Caller is responsible for not calling this function concurrently.
     pub unsafe fn get_editor(&self) -> &mut Option<Box<dyn Editor>> {
-        &mut (*(self.user as *mut super::PluginCache)).editor
+        &mut *(*(self.user as *const super::PluginCache)).editor.get()
+    }
+
+    /// Return handle to the plugin cache. Only works for plugins created using this library.
+    pub(crate) unsafe fn get_cache(&self) -> &super::PluginCache {
+        &*(self.user as *const super::PluginCache)
+    }
+
+    /// Return handle to the part of the plugin cache used while processing. Only works for plugins
+    /// created using this library.
+    pub(crate) unsafe fn get_process_cache(&self) -> &crate::cache::ProcessCache {
+        &(*(self.user as *const super::PluginCache)).process
     }
 
     /// Drop the Plugin object. Only works for plugins created using this library.
@@ -185,10 +209,55 @@ pub struct ChannelProperties {
     pub future: [u8; 48],
 }
 
+/// Properties of a parameter, filled in by the plugin on `GetParamInfo`.
+#[repr(C)]
+pub struct ParameterProperties {
+    /// Float step.
+    pub step_float: f32,
+    /// Small float step.
+    pub small_step_float: f32,
+    /// Large float step.
+    pub large_step_float: f32,
+
+    /// Alternative label for the parameter.
+    pub label: [u8; MAX_LABEL],
+
+    /// Flags found in `ParameterFlags`.
+    pub flags: i32,
+
+    /// Minimum value when `USES_INTEGER_MIN_MAX` is set.
+    pub min_integer: i32,
+    /// Maximum value when `USES_INTEGER_MIN_MAX` is set.
+    pub max_integer: i32,
+    /// Integer step when `USES_INT_STEP` is set.
+    pub step_integer: i32,
+    /// Large integer step when `USES_INT_STEP` is set.
+    pub large_step_integer: i32,
+
+    /// Short label for the parameter (recommended: 6 characters + delimiter).
+    pub short_label: [u8; MAX_SHORT_LABEL],
+
+    /// Index where the parameter should be displayed, starting at 0.
+    pub display_index: i16,
+
+    /// Category of the parameter, starting at 1. 0 means no category.
+    pub category: i16,
+    /// Number of parameters in the category.
+    pub num_parameters_in_category: i16,
+    /// Reserved for future use.
+    pub _reserved: i16,
+
+    /// Label of the category, e.g. "Osc 1".
+    pub category_label: [u8; MAX_CATEGORY_LABEL],
+
+    /// Reserved for future use.
+    pub future: [u8; 16],
+}
+
 /// Tells the host how the channels are intended to be used in the plugin. Only useful for some
 /// hosts.
 #[repr(i32)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SpeakerArrangementType {
     /// User defined arrangement.
     Custom = -2,
@@ -268,9 +337,159 @@ pub enum SpeakerArrangementType {
     Surround102,
 }
 
+/// Describes a complete speaker arrangement. Used with `plugin::OpCode::SetSpeakerArrangement`
+/// and `plugin::OpCode::GetSpeakerArrangement`.
+#[repr(C)]
+pub struct SpeakerArrangement {
+    /// Type of this arrangement. See `SpeakerArrangementType`.
+    pub arrangement_type: i32,
+
+    /// Number of channels in this arrangement.
+    pub num_channels: i32,
+
+    /// Variable-length array of speakers, one for each channel.
+    ///
+    /// The VST standard specifies a variable length array of initial size 8. If there are more
+    /// than 8 channels a larger array must be stored in this structure.
+    pub speakers: [SpeakerProperties; 8],
+}
+
+/// Information about a single speaker in a `SpeakerArrangement`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SpeakerProperties {
+    /// Azimuth in radians, between -pi and pi. 0 is straight ahead.
+    pub azimuth: f32,
+
+    /// Elevation in radians, between -pi/2 and pi/2. 0 is level with the listener.
+    pub elevation: f32,
+
+    /// Distance from the listener in meters, between 0 and +inf.
+    pub radius: f32,
+
+    /// Reserved for future use, must be 0.
+    pub _reserved: f32,
+
+    /// Speaker name.
+    pub name: [u8; MAX_LABEL],
+
+    /// Type of this speaker. See `SpeakerType`.
+    pub speaker_type: i32,
+
+    /// Reserved for future use.
+    pub future: [u8; 28],
+}
+
+/// The position of a speaker within a speaker arrangement.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
+pub enum SpeakerType {
+    /// Undefined speaker.
+    Undefined = 0x7fff_ffff,
+
+    /// Mono (M).
+    Mono = 0,
+    /// Left (L).
+    Left,
+    /// Right (R).
+    Right,
+    /// Center (C).
+    Center,
+    /// Subbass (Lfe).
+    Lfe,
+    /// Left surround (Ls).
+    LeftSurround,
+    /// Right surround (Rs).
+    RightSurround,
+    /// Left of center (Lc).
+    LeftCenter,
+    /// Right of center (Rc).
+    RightCenter,
+    /// Surround (S), also used for center of surround (Cs).
+    Surround,
+    /// Side left (Sl).
+    SideLeft,
+    /// Side right (Sr).
+    SideRight,
+    /// Top middle (Tm).
+    TopMiddle,
+    /// Top front left (Tfl).
+    TopFrontLeft,
+    /// Top front center (Tfc).
+    TopFrontCenter,
+    /// Top front right (Tfr).
+    TopFrontRight,
+    /// Top rear left (Trl).
+    TopRearLeft,
+    /// Top rear center (Trc).
+    TopRearCenter,
+    /// Top rear right (Trr).
+    TopRearRight,
+    /// Subbass 2 (Lfe2).
+    Lfe2,
+}
+
+impl SpeakerType {
+    /// Short name of this speaker, as used in the arrangement descriptions (e.g. "Ls").
+    pub fn short_name(self) -> &'static str {
+        use self::SpeakerType::*;
+
+        match self {
+            Undefined => "",
+            Mono => "M",
+            Left => "L",
+            Right => "R",
+            Center => "C",
+            Lfe => "Lfe",
+            LeftSurround => "Ls",
+            RightSurround => "Rs",
+            LeftCenter => "Lc",
+            RightCenter => "Rc",
+            Surround => "S",
+            SideLeft => "Sl",
+            SideRight => "Sr",
+            TopMiddle => "Tm",
+            TopFrontLeft => "Tfl",
+            TopFrontCenter => "Tfc",
+            TopFrontRight => "Tfr",
+            TopRearLeft => "Trl",
+            TopRearCenter => "Trc",
+            TopRearRight => "Trr",
+            Lfe2 => "Lfe2",
+        }
+    }
+}
+
 /// Used to specify whether functionality is supported.
+///
+/// More variants may be added in the future, so matches outside of this crate need a wildcard
+/// arm:
+///
+/// ```
+/// # use vst::api::Supported;
+/// fn is_yes(supported: Supported) -> bool {
+///     match supported {
+///         Supported::Yes => true,
+///         Supported::Maybe | Supported::No | Supported::Custom(_) => false,
+///         _ => false,
+///     }
+/// }
+/// ```
+///
+/// Without it, the match does not compile:
+///
+/// ```compile_fail,E0004
+/// # use vst::api::Supported;
+/// fn is_yes(supported: Supported) -> bool {
+///     match supported {
+///         Supported::Yes => true,
+///         Supported::Maybe | Supported::No | Supported::Custom(_) => false,
+///     }
+/// }
+/// ```
 #[allow(missing_docs)]
-#[derive(PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum Supported {
     Yes,
     Maybe,
@@ -290,6 +509,21 @@ impl Supported {
             _ => None,
         }
     }
+
+    /// Whether this is `Supported::Yes`.
+    pub fn is_supported(&self) -> bool {
+        matches!(self, Supported::Yes)
+    }
+
+    /// Convert to a `bool` if the answer is definite: `Some(true)` for `Yes`, `Some(false)` for
+    /// `No` and `None` for `Maybe` and custom values.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Supported::Yes => Some(true),
+            Supported::No => Some(false),
+            Supported::Maybe | Supported::Custom(_) => None,
+        }
+    }
 }
 
 impl Into<isize> for Supported {
@@ -306,6 +540,43 @@ impl Into<isize> for Supported {
     }
 }
 
+/// The length of the tail a plugin produces after its input has stopped.
+///
+/// This is how the raw return value of `GetTailSize` is interpreted: `0` means the plugin does
+/// not report a tail size, `1` means it has no tail and any larger value is a number of samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TailSize {
+    /// The plugin does not report a tail size. Hosts should use their default.
+    Default,
+    /// The plugin stops producing output as soon as its input stops.
+    NoTail,
+    /// The plugin keeps producing output for this many samples.
+    Samples(usize),
+}
+
+impl TailSize {
+    /// Interpret the raw value returned for `GetTailSize`.
+    ///
+    /// Negative values are not defined by the VST API and are treated as `Default`.
+    pub fn from_raw(value: isize) -> TailSize {
+        match value {
+            1 => TailSize::NoTail,
+            value if value > 1 => TailSize::Samples(value as usize),
+            _ => TailSize::Default,
+        }
+    }
+}
+
+impl From<TailSize> for isize {
+    fn from(tail_size: TailSize) -> isize {
+        match tail_size {
+            TailSize::Default => 0,
+            TailSize::NoTail => 1,
+            TailSize::Samples(samples) => samples as isize,
+        }
+    }
+}
+
 /// Denotes in which thread the host is in.
 #[repr(i32)]
 pub enum ProcessLevel {
@@ -349,6 +620,7 @@ pub enum FileSelectCommand {
 
 // TODO: investigate removing this.
 /// Format to select files.
+#[repr(i32)]
 pub enum FileSelectType {
     /// Regular file selector.
     Regular,
@@ -424,6 +696,29 @@ pub struct Events {
 }
 
 impl Events {
+    /// Wrap an array of event pointers in an `Events` structure without copying the events.
+    ///
+    /// Only the pointers are copied into the returned `OwnedEvents`, which dereferences to
+    /// `Events` and can be passed to `process_events`.
+    ///
+    /// # Safety
+    ///
+    /// Every pointer must point to a valid event (e.g. a `MidiEvent` or `SysExEvent` cast to
+    /// `Event`) which stays valid and is not modified for as long as the returned `OwnedEvents` is
+    /// used.
+    pub unsafe fn from_ptrs(ptrs: &[*mut Event]) -> OwnedEvents {
+        // The header fields take up the space of a whole number of pointers on all platforms.
+        let header = mem::offset_of!(Events, events) / mem::size_of::<*mut Event>();
+        let mut storage = vec![ptr::null_mut(); header + ptrs.len().max(2)];
+        storage[header..header + ptrs.len()].copy_from_slice(ptrs);
+
+        let events = &mut *(storage.as_mut_ptr() as *mut Events);
+        events.num_events = ptrs.len() as i32;
+        events._reserved = 0;
+
+        OwnedEvents { storage }
+    }
+
     #[inline]
     pub(crate) fn events_raw(&self) -> &[*const Event] {
         use std::slice;
@@ -482,9 +777,28 @@ impl Events {
     }
 }
 
+/// An `Events` structure with room for any number of event pointers, created by
+/// `Events::from_ptrs`.
+pub struct OwnedEvents {
+    /// The `Events` header followed by the event pointers, stored as pointers for alignment.
+    storage: Vec<*mut Event>,
+}
+
+impl Deref for OwnedEvents {
+    type Target = Events;
+
+    fn deref(&self) -> &Events {
+        unsafe { &*(self.storage.as_ptr() as *const Events) }
+    }
+}
+
 /// The type of event that has occurred. See `api::Event.event_type`.
+///
+/// More event types may be added in the future, so matches outside of this crate need a
+/// wildcard arm.
 #[repr(i32)]
 #[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
 pub enum EventType {
     /// Value used for uninitialized placeholder events.
     _Placeholder = 0,
@@ -687,7 +1001,8 @@ pub struct TimeInfo {
     pub time_sig_denominator: i32,
 
     /// SMPTE offset in SMPTE subframes (bits; 1/80 of a frame).
-    /// The current SMPTE position can be calculated using `sample_pos`, `sample_rate`, and `smpte_frame_rate`.
+    /// The current SMPTE position can be calculated using `sample_pos`, `sample_rate`, and `smpte_frame_rate`,
+    /// see `smpte_position`.
     pub smpte_offset: i32,
 
     /// See `SmpteFrameRate`
@@ -700,6 +1015,149 @@ pub struct TimeInfo {
     pub flags: i32,
 }
 
+impl fmt::Debug for TimeInfo {
+    /// Only the fields which are always valid and those marked as valid in `flags` are printed.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags = TimeInfoFlags::from_bits_truncate(self.flags);
+        let mut s = f.debug_struct("TimeInfo");
+        s.field("sample_pos", &self.sample_pos);
+        s.field("sample_rate", &self.sample_rate);
+        if flags.contains(TimeInfoFlags::NANOSECONDS_VALID) {
+            s.field("nanoseconds", &self.nanoseconds);
+        }
+        if flags.contains(TimeInfoFlags::PPQ_POS_VALID) {
+            s.field("ppq_pos", &self.ppq_pos);
+        }
+        if flags.contains(TimeInfoFlags::TEMPO_VALID) {
+            s.field("tempo", &self.tempo);
+        }
+        if flags.contains(TimeInfoFlags::BARS_VALID) {
+            s.field("bar_start_pos", &self.bar_start_pos);
+        }
+        if flags.contains(TimeInfoFlags::CYCLE_POS_VALID) {
+            s.field("cycle_start_pos", &self.cycle_start_pos);
+            s.field("cycle_end_pos", &self.cycle_end_pos);
+        }
+        if flags.contains(TimeInfoFlags::TIME_SIG_VALID) {
+            s.field("time_sig_numerator", &self.time_sig_numerator);
+            s.field("time_sig_denominator", &self.time_sig_denominator);
+        }
+        if flags.contains(TimeInfoFlags::SMPTE_VALID) {
+            s.field("smpte_offset", &self.smpte_offset);
+            s.field("smpte_frame_rate", &self.smpte_frame_rate);
+        }
+        if flags.contains(TimeInfoFlags::VST_CLOCK_VALID) {
+            s.field("samples_to_next_clock", &self.samples_to_next_clock);
+        }
+        s.field("flags", &flags);
+        s.finish()
+    }
+}
+
+impl TimeInfo {
+    /// Number of ticks per beat used by `musical_position`.
+    pub const TICKS_PER_BEAT: i32 = 960;
+
+    /// Calculate the current position in bars, beats and ticks from `ppq_pos`, `bar_start_pos`
+    /// and the time signature.
+    ///
+    /// Returns `None` unless the host set `TimeInfoFlags::PPQ_POS_VALID`,
+    /// `TimeInfoFlags::TIME_SIG_VALID` and `TimeInfoFlags::BARS_VALID`. The bar number assumes
+    /// that the time signature did not change since the start of the song.
+    pub fn musical_position(&self) -> Option<MusicalPosition> {
+        let required = TimeInfoFlags::PPQ_POS_VALID | TimeInfoFlags::TIME_SIG_VALID | TimeInfoFlags::BARS_VALID;
+        if !TimeInfoFlags::from_bits_truncate(self.flags).contains(required)
+            || self.time_sig_numerator <= 0
+            || self.time_sig_denominator <= 0
+        {
+            return None;
+        }
+
+        // Length of a beat and a bar in quarter notes.
+        let beat_length = 4.0 / f64::from(self.time_sig_denominator);
+        let bar_length = beat_length * f64::from(self.time_sig_numerator);
+
+        let beats = (self.ppq_pos - self.bar_start_pos) / beat_length;
+        let ticks = ((beats - beats.floor()) * f64::from(Self::TICKS_PER_BEAT)).floor();
+
+        Some(MusicalPosition {
+            bar: (self.bar_start_pos / bar_length).round() as i32 + 1,
+            beat: beats.floor() as i32 + 1,
+            tick: ticks as i32,
+            ticks_per_beat: Self::TICKS_PER_BEAT,
+        })
+    }
+
+    /// Calculate the current SMPTE position from `sample_pos`, `sample_rate`, `smpte_offset` and
+    /// `smpte_frame_rate`.
+    ///
+    /// Returns `None` if the host did not set `TimeInfoFlags::SMPTE_VALID`, or if the position
+    /// is before zero. For the drop-frame rates, frame numbers are skipped as in SMPTE drop-frame
+    /// timecode.
+    pub fn smpte_position(&self) -> Option<SmptePosition> {
+        let flags = TimeInfoFlags::from_bits_truncate(self.flags);
+        if !flags.contains(TimeInfoFlags::SMPTE_VALID) || self.sample_rate <= 0.0 {
+            return None;
+        }
+
+        let (rate, rate_divisor) = self.smpte_frame_rate.rate();
+        let frames = self.sample_pos * rate / (self.sample_rate * rate_divisor) + f64::from(self.smpte_offset) / 80.0;
+        if frames < 0.0 {
+            return None;
+        }
+
+        let nominal_rate = self.smpte_frame_rate.nominal_rate();
+        // Allow for rounding errors in positions at exact frame boundaries.
+        let mut frames = (frames + 1e-6).floor() as u64;
+        if self.smpte_frame_rate.is_drop_frame() {
+            // Frame numbers 0 and 1 are skipped at the start of every minute, except every tenth.
+            let frames_per_minute = nominal_rate * 60 - 2;
+            let frames_per_ten_minutes = frames_per_minute * 10 + 2;
+            let ten_minutes = frames / frames_per_ten_minutes;
+            let remainder = frames % frames_per_ten_minutes;
+            frames += 18 * ten_minutes;
+            if remainder > 1 {
+                frames += 2 * ((remainder - 2) / frames_per_minute);
+            }
+        }
+
+        let seconds = frames / nominal_rate;
+        Some(SmptePosition {
+            hours: (seconds / 3600) as u32,
+            minutes: (seconds / 60 % 60) as u32,
+            seconds: (seconds % 60) as u32,
+            frames: (frames % nominal_rate) as u32,
+        })
+    }
+}
+
+/// A position in bars, beats and ticks. See `TimeInfo::musical_position`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MusicalPosition {
+    /// Bar number, starting at 1.
+    pub bar: i32,
+    /// Beat within the bar, starting at 1. Beats are counted in the unit of the time signature
+    /// denominator, e.g. eighth notes in 6/8.
+    pub beat: i32,
+    /// Tick within the beat, starting at 0.
+    pub tick: i32,
+    /// Number of ticks per beat.
+    pub ticks_per_beat: i32,
+}
+
+/// A position in SMPTE timecode. See `TimeInfo::smpte_position`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SmptePosition {
+    /// Hours.
+    pub hours: u32,
+    /// Minutes, between 0 and 59.
+    pub minutes: u32,
+    /// Seconds, between 0 and 59.
+    pub seconds: u32,
+    /// Frames, between 0 and the nominal frame rate minus one.
+    pub frames: u32,
+}
+
 #[repr(i32)]
 #[derive(Copy, Clone, Debug)]
 /// SMPTE Frame Rates.
@@ -738,6 +1196,39 @@ impl Default for SmpteFrameRate {
     }
 }
 
+impl SmpteFrameRate {
+    /// The actual frame rate as a fraction `(frames, seconds)`.
+    fn rate(self) -> (f64, f64) {
+        use self::SmpteFrameRate::*;
+        match self {
+            Smpte24fps | SmpteFilm16mm | SmpteFilm35mm => (24.0, 1.0),
+            Smpte25fps => (25.0, 1.0),
+            Smpte2997fps | Smpte2997dfps => (30000.0, 1001.0),
+            Smpte30fps | Smpte30dfps => (30.0, 1.0),
+            Smpte239fps => (24000.0, 1001.0),
+            Smpte249fps => (25000.0, 1001.0),
+            Smpte599fps => (60000.0, 1001.0),
+            Smpte60fps => (60.0, 1.0),
+        }
+    }
+
+    /// The number of frames counted per second of timecode.
+    fn nominal_rate(self) -> u64 {
+        use self::SmpteFrameRate::*;
+        match self {
+            Smpte24fps | SmpteFilm16mm | SmpteFilm35mm | Smpte239fps => 24,
+            Smpte25fps | Smpte249fps => 25,
+            Smpte2997fps | Smpte2997dfps | Smpte30fps | Smpte30dfps => 30,
+            Smpte599fps | Smpte60fps => 60,
+        }
+    }
+
+    /// Whether this is a drop-frame rate.
+    fn is_drop_frame(self) -> bool {
+        matches!(self, SmpteFrameRate::Smpte2997dfps | SmpteFrameRate::Smpte30dfps)
+    }
+}
+
 bitflags! {
     /// Flags for VST channels.
     pub struct ChannelFlags: i32 {
@@ -750,6 +1241,26 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Flags for `ParameterProperties`.
+    pub struct ParameterFlags: i32 {
+        /// Parameter is an on/off switch.
+        const IS_SWITCH = 1;
+        /// `min_integer` and `max_integer` are valid.
+        const USES_INTEGER_MIN_MAX = 1 << 1;
+        /// `step_float`, `small_step_float` and `large_step_float` are valid.
+        const USES_FLOAT_STEP = 1 << 2;
+        /// `step_integer` and `large_step_integer` are valid.
+        const USES_INT_STEP = 1 << 3;
+        /// `display_index` is valid.
+        const SUPPORTS_DISPLAY_INDEX = 1 << 4;
+        /// `category`, `num_parameters_in_category` and `category_label` are valid.
+        const SUPPORTS_DISPLAY_CATEGORY = 1 << 5;
+        /// Parameter value can be ramped up or down.
+        const CAN_RAMP = 1 << 6;
+    }
+}
+
 bitflags! {
     /// Flags for VST plugins.
     pub struct PluginFlags: i32 {
@@ -924,4 +1435,162 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn events_from_ptrs() {
+        let first = encode_midi_message_as_events([0x90, 60, 100]);
+        let second = encode_midi_message_as_events([0x80, 60, 0]);
+        let ptrs = [first.events.events[0], second.events.events[0], first.events.events[0]];
+
+        let events = unsafe { Events::from_ptrs(&ptrs[..2]) };
+        assert_eq!(events.num_events, 2);
+        let data: Vec<[u8; 3]> = events
+            .events()
+            .map(|event| match event {
+                event::Event::Midi(midi) => midi.data,
+                _ => panic!("Not a midi event!"),
+            })
+            .collect();
+        assert_eq!(data, vec![[0x90, 60, 100], [0x80, 60, 0]]);
+
+        // More pointers than fit in the fixed size array of `Events`.
+        let events = unsafe { Events::from_ptrs(&ptrs) };
+        assert_eq!(events.events().count(), 3);
+
+        let events = unsafe { Events::from_ptrs(&[]) };
+        assert_eq!(events.events().count(), 0);
+    }
+
+    fn smpte_time_info(sample_pos: f64, smpte_frame_rate: SmpteFrameRate) -> TimeInfo {
+        TimeInfo {
+            sample_pos,
+            sample_rate: 48000.0,
+            smpte_frame_rate,
+            flags: TimeInfoFlags::SMPTE_VALID.bits(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn smpte_position_24fps() {
+        // 01:02:03 plus 5 frames of 2000 samples each.
+        let time_info = smpte_time_info(3723.0 * 48000.0 + 5.0 * 2000.0, SmpteFrameRate::Smpte24fps);
+        assert_eq!(
+            time_info.smpte_position(),
+            Some(SmptePosition {
+                hours: 1,
+                minutes: 2,
+                seconds: 3,
+                frames: 5,
+            })
+        );
+
+        let time_info = TimeInfo { flags: 0, ..time_info };
+        assert_eq!(time_info.smpte_position(), None);
+    }
+
+    #[test]
+    fn smpte_position_2997_drop_frame() {
+        // At 29.97 fps, each frame lasts 1601.6 samples at 48kHz.
+        let at_frame = |frame: f64| smpte_time_info(frame * 1601.6, SmpteFrameRate::Smpte2997dfps).smpte_position();
+
+        // Frames 00:01:00;00 and 00:01:00;01 are dropped.
+        let position = |minutes, seconds, frames| {
+            Some(SmptePosition {
+                hours: 0,
+                minutes,
+                seconds,
+                frames,
+            })
+        };
+        assert_eq!(at_frame(1799.0), position(0, 59, 29));
+        assert_eq!(at_frame(1800.0), position(1, 0, 2));
+        // No frames are dropped at the start of every tenth minute.
+        assert_eq!(at_frame(17982.0), position(10, 0, 0));
+        assert_eq!(at_frame(17983.0), position(10, 0, 1));
+    }
+
+    fn musical_time_info(ppq_pos: f64, bar_start_pos: f64, numerator: i32, denominator: i32) -> TimeInfo {
+        TimeInfo {
+            // At 120 BPM, a quarter note lasts 22050 samples at 44.1kHz.
+            sample_pos: ppq_pos * 22050.0,
+            sample_rate: 44100.0,
+            ppq_pos,
+            tempo: 120.0,
+            bar_start_pos,
+            time_sig_numerator: numerator,
+            time_sig_denominator: denominator,
+            flags: (TimeInfoFlags::PPQ_POS_VALID | TimeInfoFlags::TIME_SIG_VALID | TimeInfoFlags::BARS_VALID).bits(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn musical_position_4_4() {
+        let time_info = musical_time_info(9.5, 8.0, 4, 4);
+        assert_eq!(
+            time_info.musical_position(),
+            Some(MusicalPosition {
+                bar: 3,
+                beat: 2,
+                tick: 480,
+                ticks_per_beat: 960,
+            })
+        );
+
+        let time_info = TimeInfo {
+            flags: TimeInfoFlags::PPQ_POS_VALID.bits(),
+            ..time_info
+        };
+        assert_eq!(time_info.musical_position(), None);
+    }
+
+    #[test]
+    fn musical_position_6_8() {
+        // A bar of 6/8 lasts three quarter notes, and a beat is an eighth note.
+        let time_info = musical_time_info(7.75, 6.0, 6, 8);
+        assert_eq!(
+            time_info.musical_position(),
+            Some(MusicalPosition {
+                bar: 3,
+                beat: 4,
+                tick: 480,
+                ticks_per_beat: 960,
+            })
+        );
+    }
+
+    #[test]
+    fn time_info_debug_only_prints_valid_fields() {
+        let time_info = TimeInfo {
+            sample_pos: 512.0,
+            sample_rate: 44100.0,
+            nanoseconds: 99.0,
+            ppq_pos: 1.5,
+            tempo: 120.0,
+            time_sig_numerator: 3,
+            flags: (TimeInfoFlags::TRANSPORT_PLAYING | TimeInfoFlags::PPQ_POS_VALID | TimeInfoFlags::TEMPO_VALID)
+                .bits(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format!("{:?}", time_info),
+            "TimeInfo { sample_pos: 512.0, sample_rate: 44100.0, ppq_pos: 1.5, tempo: 120.0, \
+             flags: TRANSPORT_PLAYING | PPQ_POS_VALID | TEMPO_VALID }"
+        );
+    }
+
+    #[test]
+    fn supported_as_bool() {
+        assert!(Supported::Yes.is_supported());
+        assert!(!Supported::Maybe.is_supported());
+        assert!(!Supported::No.is_supported());
+        assert!(!Supported::Custom(5).is_supported());
+
+        assert_eq!(Supported::Yes.as_bool(), Some(true));
+        assert_eq!(Supported::No.as_bool(), Some(false));
+        assert_eq!(Supported::Maybe.as_bool(), None);
+        assert_eq!(Supported::Custom(5).as_bool(), None);
+    }
 }
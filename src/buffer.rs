@@ -2,7 +2,28 @@
 
 use num_traits::Float;
 
-use std::slice;
+use std::marker::PhantomData;
+use std::{ptr, slice};
+
+/// Create a slice from a pointer provided by the host, which may be null if `len` is 0.
+#[inline]
+unsafe fn raw_slice<'a, T>(ptr: *const T, len: usize) -> &'a [T] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(ptr, len)
+    }
+}
+
+/// Mutable version of `raw_slice`.
+#[inline]
+unsafe fn raw_slice_mut<'a, T>(ptr: *mut T, len: usize) -> &'a mut [T] {
+    if len == 0 {
+        &mut []
+    } else {
+        slice::from_raw_parts_mut(ptr, len)
+    }
+}
 
 /// `AudioBuffer` contains references to the audio buffers for all input and output channels.
 ///
@@ -16,6 +37,9 @@ pub struct AudioBuffer<'a, T: 'a + Float> {
 impl<'a, T: 'a + Float> AudioBuffer<'a, T> {
     /// Create an `AudioBuffer` from raw pointers.
     /// Only really useful for interacting with the VST API.
+    ///
+    /// Every channel pointer must point to at least `samples` samples. As the lengths of the
+    /// channels are unknown, only null pointers are caught, and only in debug builds.
     #[inline]
     pub unsafe fn from_raw(
         input_count: usize,
@@ -24,13 +48,80 @@ impl<'a, T: 'a + Float> AudioBuffer<'a, T> {
         outputs_raw: *mut *mut T,
         samples: usize,
     ) -> Self {
+        #[cfg(debug_assertions)]
+        {
+            assert!(input_count == 0 || !inputs_raw.is_null(), "Null input channel array");
+            assert!(output_count == 0 || !outputs_raw.is_null(), "Null output channel array");
+            if samples > 0 {
+                for i in 0..input_count {
+                    assert!(!(*inputs_raw.add(i)).is_null(), "Null pointer for input channel {}", i);
+                }
+                for i in 0..output_count {
+                    assert!(
+                        !(*outputs_raw.add(i)).is_null(),
+                        "Null pointer for output channel {}",
+                        i
+                    );
+                }
+            }
+        }
+
         Self {
-            inputs: slice::from_raw_parts(inputs_raw, input_count),
-            outputs: slice::from_raw_parts_mut(outputs_raw, output_count),
+            inputs: raw_slice(inputs_raw, input_count),
+            outputs: raw_slice_mut(outputs_raw, output_count),
             samples,
         }
     }
 
+    /// Create an `AudioBuffer` from slices of samples without any `unsafe` code.
+    ///
+    /// As an `AudioBuffer` only refers to the per-channel pointer arrays, these are kept in the
+    /// returned [`SliceBuffer`], from which the actual `AudioBuffer` can be obtained.
+    ///
+    /// ```rust
+    /// # use vst::buffer::AudioBuffer;
+    /// let input = vec![0.5f32; 64];
+    /// let mut output = vec![0.0f32; 64];
+    /// let inputs = [&input[..]];
+    /// let mut outputs = [&mut output[..]];
+    /// let mut slices = AudioBuffer::from_slices(&inputs, &mut outputs);
+    /// let mut buffer = slices.buffer();
+    /// assert_eq!(buffer.samples(), 64);
+    /// ```
+    ///
+    /// # Panics
+    /// This function will panic if the sample arrays do not all have the same length.
+    pub fn from_slices(inputs: &'a [&'a [T]], outputs: &'a mut [&'a mut [T]]) -> SliceBuffer<'a, T> {
+        let mut length = None;
+        for input in inputs.iter() {
+            match length {
+                None => length = Some(input.len()),
+                Some(old_length) => {
+                    if input.len() != old_length {
+                        panic!("Mismatching lengths of input arrays");
+                    }
+                }
+            }
+        }
+        for output in outputs.iter() {
+            match length {
+                None => length = Some(output.len()),
+                Some(old_length) => {
+                    if output.len() != old_length {
+                        panic!("Mismatching lengths of output arrays");
+                    }
+                }
+            }
+        }
+
+        SliceBuffer {
+            inputs: inputs.iter().map(|input| input.as_ptr()).collect(),
+            outputs: outputs.iter_mut().map(|output| output.as_mut_ptr()).collect(),
+            samples: length.unwrap_or(0),
+            _marker: PhantomData,
+        }
+    }
+
     /// The number of input channels that this buffer was created for
     #[inline]
     pub fn input_count(&self) -> usize {
@@ -49,6 +140,51 @@ impl<'a, T: 'a + Float> AudioBuffer<'a, T> {
         self.samples
     }
 
+    /// Whether this buffer contains no samples.
+    ///
+    /// Some hosts call `process` with zero samples, for example during transport changes. All
+    /// channels are empty slices then, so code indexing into a channel (like `inputs.get(0)[0]`)
+    /// will panic. Plugins should return early from `process` in this case:
+    ///
+    /// ```
+    /// # use vst::buffer::AudioBuffer;
+    /// fn process(buffer: &mut AudioBuffer<f32>) {
+    ///     if buffer.is_empty() {
+    ///         return;
+    ///     }
+    ///     let (inputs, mut outputs) = buffer.split();
+    ///     outputs.get_mut(0)[0] = inputs.get(0)[0];
+    /// }
+    /// # let (inputs, mut outputs) = ([&[][..]], [&mut [][..]]);
+    /// # let mut buffers = AudioBuffer::from_slices(&inputs, &mut outputs);
+    /// # process(&mut buffers.buffer());
+    /// ```
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.samples == 0
+    }
+
+    /// Access the input channel at `index`.
+    ///
+    /// Unlike `zip`, this also reaches input channels without a matching output channel, such as
+    /// the sidechain inputs of a compressor.
+    ///
+    /// # Panics
+    /// Panics if `index` is not less than `input_count()`.
+    #[inline]
+    pub fn input(&self, index: usize) -> &[T] {
+        unsafe { raw_slice(self.inputs[index], self.samples) }
+    }
+
+    /// Mutably access the output channel at `index`.
+    ///
+    /// # Panics
+    /// Panics if `index` is not less than `output_count()`.
+    #[inline]
+    pub fn output(&mut self, index: usize) -> &mut [T] {
+        unsafe { raw_slice_mut(self.outputs[index], self.samples) }
+    }
+
     /// The raw inputs to pass to processReplacing
     #[inline]
     pub(crate) fn raw_inputs(&self) -> &[*const T] {
@@ -79,7 +215,19 @@ impl<'a, T: 'a + Float> AudioBuffer<'a, T> {
         )
     }
 
+    /// Get the input and output channels of this buffer. Same as [`split`](#method.split).
+    #[inline]
+    pub fn channels<'b>(&'b mut self) -> (Inputs<'b, T>, Outputs<'b, T>)
+    where
+        'a: 'b,
+    {
+        self.split()
+    }
+
     /// Create an iterator over pairs of input buffers and output buffers.
+    ///
+    /// The iterator stops at the smaller of the input and output channel counts, so any extra
+    /// channels are skipped. Use [`zip_longest`](#method.zip_longest) to visit all of them.
     #[inline]
     pub fn zip<'b>(&'b mut self) -> AudioBufferIterator<'a, 'b, T> {
         AudioBufferIterator {
@@ -87,6 +235,188 @@ impl<'a, T: 'a + Float> AudioBuffer<'a, T> {
             index: 0,
         }
     }
+
+    /// Create an iterator over pairs of input buffers and output buffers which continues up to
+    /// the larger of the input and output channel counts.
+    ///
+    /// Channels missing on the shorter side are returned as `None`.
+    #[inline]
+    pub fn zip_longest<'b>(&'b mut self) -> AudioBufferLongestIterator<'a, 'b, T> {
+        AudioBufferLongestIterator {
+            audio_buffer: self,
+            index: 0,
+        }
+    }
+
+    /// Process each channel in place.
+    ///
+    /// Every input channel is copied to the output channel with the same index, and `f` is then
+    /// called with that output channel. Hosts may pass the same array for an input and an output
+    /// channel, which is handled correctly. Only paired channels are processed, like with
+    /// [`zip`](#method.zip); extra output channels are left unchanged.
+    ///
+    /// ```
+    /// # use vst::buffer::AudioBuffer;
+    /// fn process(buffer: &mut AudioBuffer<f32>) {
+    ///     // Halve the volume
+    ///     buffer.process_in_place(|channel| {
+    ///         for sample in channel {
+    ///             *sample *= 0.5;
+    ///         }
+    ///     });
+    /// }
+    /// # let (inputs, mut outputs) = ([&[1.0, -2.0][..]], [&mut [0.0, 0.0][..]]);
+    /// # let mut buffers = AudioBuffer::from_slices(&inputs, &mut outputs);
+    /// # process(&mut buffers.buffer());
+    /// ```
+    pub fn process_in_place<F: FnMut(&mut [T])>(&mut self, mut f: F) {
+        let samples = self.samples;
+        for (&input, &output) in self.inputs.iter().zip(self.outputs.iter()) {
+            if samples > 0 && !ptr::eq(input, output) {
+                // `copy` allows the arrays to overlap.
+                unsafe { ptr::copy(input, output, samples) };
+            }
+            f(unsafe { raw_slice_mut(output, samples) });
+        }
+    }
+}
+
+/// Owns the channel pointer arrays for an `AudioBuffer` created from sample slices.
+///
+/// Returned by [`AudioBuffer::from_slices`].
+pub struct SliceBuffer<'a, T: 'a + Float> {
+    inputs: Vec<*const T>,
+    outputs: Vec<*mut T>,
+    samples: usize,
+    _marker: PhantomData<&'a mut [T]>,
+}
+
+impl<'a, T: 'a + Float> SliceBuffer<'a, T> {
+    /// Get an `AudioBuffer` referring to the sample slices.
+    #[inline]
+    pub fn buffer(&mut self) -> AudioBuffer<'_, T> {
+        unsafe {
+            AudioBuffer::from_raw(
+                self.inputs.len(),
+                self.outputs.len(),
+                self.inputs.as_ptr(),
+                self.outputs.as_mut_ptr(),
+                self.samples,
+            )
+        }
+    }
+}
+
+impl<'a> AudioBuffer<'a, f32> {
+    /// Convert this buffer to `f64` for plugins which process internally in double precision.
+    ///
+    /// The input samples are copied into `f64` scratch channels, from which an `f64`
+    /// `AudioBuffer` can be obtained with [`ConvertingBuffer::buffer`]. The output channels are
+    /// converted back and written to this buffer when the returned `ConvertingBuffer` is dropped.
+    ///
+    /// This allocates one `Vec<f64>` per input and output channel on every call, so it is only a
+    /// convenience. Plugins which care about allocation in the audio thread should keep their own
+    /// scratch buffers.
+    ///
+    /// ```rust
+    /// # use vst::buffer::AudioBuffer;
+    /// # let input = vec![0.5f32; 64];
+    /// # let mut output = vec![0.0f32; 64];
+    /// # let inputs = [&input[..]];
+    /// # let mut outputs = [&mut output[..]];
+    /// # let mut slices = AudioBuffer::from_slices(&inputs, &mut outputs);
+    /// # let mut buffer = slices.buffer();
+    /// fn process_f64(buffer: &mut AudioBuffer<f64>) {
+    ///     for (input, output) in buffer.zip() {
+    ///         output.copy_from_slice(input);
+    ///     }
+    /// }
+    ///
+    /// let mut converted = buffer.convert_to_f64();
+    /// process_f64(&mut converted.buffer());
+    /// ```
+    pub fn convert_to_f64<'b>(&'b mut self) -> ConvertingBuffer<'a, 'b> {
+        let samples = self.samples;
+        let (inputs, outputs) = self.split();
+        let inputs: Vec<Vec<f64>> = inputs
+            .into_iter()
+            .map(|input| input.iter().map(|&sample| f64::from(sample)).collect())
+            .collect();
+        let outputs = vec![vec![0.0; samples]; outputs.len()];
+
+        ConvertingBuffer {
+            input_ptrs: Vec::with_capacity(inputs.len()),
+            output_ptrs: Vec::with_capacity(outputs.len()),
+            inputs,
+            outputs,
+            target: self,
+        }
+    }
+}
+
+#[cfg(feature = "ndarray")]
+impl<'a, T: 'a + Float> AudioBuffer<'a, T> {
+    /// View each input channel as a 1D array of samples, without copying.
+    ///
+    /// The channels of an `AudioBuffer` are separate allocations of the host, so they are viewed
+    /// one by one rather than as a single 2D array.
+    pub fn input_views(&self) -> impl ExactSizeIterator<Item = ndarray::ArrayView1<'_, T>> + '_ {
+        let samples = self.samples;
+        self.inputs
+            .iter()
+            .map(move |&channel| ndarray::ArrayView1::from(unsafe { raw_slice(channel, samples) }))
+    }
+
+    /// View each output channel as a mutable 1D array of samples, without copying.
+    pub fn output_views_mut(&mut self) -> impl ExactSizeIterator<Item = ndarray::ArrayViewMut1<'_, T>> + '_ {
+        let samples = self.samples;
+        self.outputs
+            .iter()
+            .map(move |&channel| ndarray::ArrayViewMut1::from(unsafe { raw_slice_mut(channel, samples) }))
+    }
+}
+
+/// Presents an `f32` `AudioBuffer` as `f64` scratch channels.
+///
+/// Returned by [`AudioBuffer::convert_to_f64`]. The `f64` outputs are copied back to the `f32`
+/// buffer on drop.
+pub struct ConvertingBuffer<'a, 'b> {
+    inputs: Vec<Vec<f64>>,
+    outputs: Vec<Vec<f64>>,
+    input_ptrs: Vec<*const f64>,
+    output_ptrs: Vec<*mut f64>,
+    target: &'b mut AudioBuffer<'a, f32>,
+}
+
+impl<'a, 'b> ConvertingBuffer<'a, 'b> {
+    /// Get an `f64` `AudioBuffer` referring to the scratch channels.
+    pub fn buffer(&mut self) -> AudioBuffer<'_, f64> {
+        self.input_ptrs.clear();
+        self.input_ptrs.extend(self.inputs.iter().map(|input| input.as_ptr()));
+        self.output_ptrs.clear();
+        self.output_ptrs
+            .extend(self.outputs.iter_mut().map(|output| output.as_mut_ptr()));
+
+        unsafe {
+            AudioBuffer::from_raw(
+                self.input_ptrs.len(),
+                self.output_ptrs.len(),
+                self.input_ptrs.as_ptr(),
+                self.output_ptrs.as_mut_ptr(),
+                self.target.samples(),
+            )
+        }
+    }
+}
+
+impl<'a, 'b> Drop for ConvertingBuffer<'a, 'b> {
+    fn drop(&mut self) {
+        for (output, scratch) in self.target.split().1.into_iter().zip(&self.outputs) {
+            for (sample, &value) in output.iter_mut().zip(scratch) {
+                *sample = value as f32;
+            }
+        }
+    }
 }
 
 /// Iterator over pairs of buffers of input channels and output channels.
@@ -107,10 +437,8 @@ where
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.index < self.audio_buffer.inputs.len() && self.index < self.audio_buffer.outputs.len() {
-            let input =
-                unsafe { slice::from_raw_parts(self.audio_buffer.inputs[self.index], self.audio_buffer.samples) };
-            let output =
-                unsafe { slice::from_raw_parts_mut(self.audio_buffer.outputs[self.index], self.audio_buffer.samples) };
+            let input = unsafe { raw_slice(self.audio_buffer.inputs[self.index], self.audio_buffer.samples) };
+            let output = unsafe { raw_slice_mut(self.audio_buffer.outputs[self.index], self.audio_buffer.samples) };
             let val = (input, output);
             self.index += 1;
             Some(val)
@@ -120,6 +448,45 @@ where
     }
 }
 
+/// Iterator over pairs of buffers of input channels and output channels, continuing until both
+/// are exhausted.
+pub struct AudioBufferLongestIterator<'a, 'b, T>
+where
+    T: 'a + Float,
+    'a: 'b,
+{
+    audio_buffer: &'b mut AudioBuffer<'a, T>,
+    index: usize,
+}
+
+impl<'a, 'b, T> Iterator for AudioBufferLongestIterator<'a, 'b, T>
+where
+    T: 'b + Float,
+{
+    type Item = (Option<&'b [T]>, Option<&'b mut [T]>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let samples = self.audio_buffer.samples;
+        let input = self
+            .audio_buffer
+            .inputs
+            .get(self.index)
+            .map(|&ptr| unsafe { raw_slice(ptr, samples) });
+        let output = self
+            .audio_buffer
+            .outputs
+            .get(self.index)
+            .map(|&ptr| unsafe { raw_slice_mut(ptr, samples) });
+
+        if input.is_none() && output.is_none() {
+            return None;
+        }
+
+        self.index += 1;
+        Some((input, output))
+    }
+}
+
 use std::ops::{Index, IndexMut};
 
 /// Wrapper type to access the buffers for the input channels of an `AudioBuffer` in a safe way.
@@ -143,7 +510,37 @@ impl<'a, T> Inputs<'a, T> {
 
     /// Access channel at the given index
     pub fn get(&self, i: usize) -> &'a [T] {
-        unsafe { slice::from_raw_parts(self.bufs[i], self.samples) }
+        unsafe { raw_slice(self.bufs[i], self.samples) }
+    }
+
+    /// Access the first two channels as a stereo pair, if there are at least two channels.
+    pub fn stereo(&self) -> Option<(&'a [T], &'a [T])> {
+        if self.len() < 2 {
+            return None;
+        }
+        Some((self.get(0), self.get(1)))
+    }
+
+    /// Iterate over the channels without allocating.
+    ///
+    /// Prefer this over `to_channel_refs` in `process`, unless the channels have to be passed on
+    /// as a slice.
+    pub fn channels(&self) -> InputIterator<'a, T> {
+        InputIterator {
+            data: Inputs {
+                bufs: self.bufs,
+                samples: self.samples,
+            },
+            i: 0,
+        }
+    }
+
+    /// Collect the channels into a `Vec`, for libraries which expect a `&[&[T]]`.
+    ///
+    /// This allocates on every call, so avoid calling it for every block on the audio thread. If
+    /// possible, keep a `Vec` around and refill it from `channels` instead.
+    pub fn to_channel_refs(&self) -> Vec<&'a [T]> {
+        self.channels().collect()
     }
 
     /// Split borrowing at the given index, like for slices
@@ -219,12 +616,26 @@ impl<'a, T> Outputs<'a, T> {
 
     /// Access channel at the given index
     pub fn get(&self, i: usize) -> &'a [T] {
-        unsafe { slice::from_raw_parts(self.bufs[i], self.samples) }
+        unsafe { raw_slice(self.bufs[i], self.samples) }
     }
 
     /// Mutably access channel at the given index
     pub fn get_mut(&mut self, i: usize) -> &'a mut [T] {
-        unsafe { slice::from_raw_parts_mut(self.bufs[i], self.samples) }
+        unsafe { raw_slice_mut(self.bufs[i], self.samples) }
+    }
+
+    /// Mutably access the first two channels as a stereo pair, if there are at least two
+    /// channels.
+    pub fn stereo_mut(&mut self) -> Option<(&mut [T], &mut [T])> {
+        if self.len() < 2 {
+            return None;
+        }
+        let outputs = Outputs {
+            bufs: self.bufs,
+            samples: self.samples,
+        };
+        let (mut left, mut right) = outputs.split_at_mut(1);
+        Some((left.get_mut(0), right.get_mut(0)))
     }
 
     /// Split borrowing at the given index, like for slices
@@ -319,10 +730,14 @@ impl WriteIntoPlaceholder for MidiEvent {
             event_type: api::EventType::Midi,
             byte_size: mem::size_of::<api::MidiEvent>() as i32,
             delta_frames: self.delta_frames,
-            flags: if self.live {
-                api::MidiEventFlags::REALTIME_EVENT.bits()
-            } else {
-                0
+            flags: {
+                let realtime = api::MidiEventFlags::REALTIME_EVENT.bits();
+                let flags = self.raw_flags & !realtime;
+                if self.live {
+                    flags | realtime
+                } else {
+                    flags
+                }
             },
             note_length: self.note_length.unwrap_or(0),
             note_offset: self.note_offset.unwrap_or(0),
@@ -409,6 +824,10 @@ impl SendEventBuffer {
 
     /// Sends events to the host. See the `fwd_midi` example.
     ///
+    /// The events are only valid until the next call that modifies this buffer, so keep the
+    /// buffer around (e.g. as a field of the plugin) and reuse it in every call to `process`.
+    /// Events beyond the capacity given to [`new`](#method.new) are dropped.
+    ///
     /// # Example
     /// ```no_run
     /// # use vst::plugin::{Info, Plugin, HostCallback};
@@ -425,12 +844,12 @@ impl SendEventBuffer {
     ///     let events: Vec<MidiEvent> = vec![
     ///         // ...
     ///     ];
-    ///     self.send_buffer.send_events(&events, &mut self.host);
+    ///     self.send_buffer.send_events(&events, &self.host);
     /// }
     /// # }
     /// ```
     #[inline(always)]
-    pub fn send_events<T: IntoIterator<Item = U>, U: WriteIntoPlaceholder>(&mut self, events: T, host: &mut dyn Host) {
+    pub fn send_events<T: IntoIterator<Item = U>, U: WriteIntoPlaceholder>(&mut self, events: T, host: &dyn Host) {
         self.store_events(events);
         host.process_events(self.events());
     }
@@ -457,6 +876,12 @@ impl SendEventBuffer {
         }
     }
 
+    /// Iterate over the stored events.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = Event<'_>> {
+        self.events().events()
+    }
+
     /// Clears the buffer
     #[inline(always)]
     pub fn clear(&mut self) {
@@ -481,7 +906,13 @@ impl SendEventBuffer {
 
 #[cfg(test)]
 mod tests {
-    use crate::buffer::AudioBuffer;
+    use std::cell::RefCell;
+    use std::ptr;
+
+    use crate::api;
+    use crate::buffer::{AudioBuffer, SendEventBuffer};
+    use crate::event::{Event, MidiEvent, SysExEvent};
+    use crate::host::Host;
 
     /// Size of buffers used in tests.
     const SIZE: usize = 1024;
@@ -515,6 +946,104 @@ mod tests {
         }
     }
 
+    #[test]
+    fn sidechain_input() {
+        let main_left = vec![1.0; SIZE];
+        let main_right = vec![2.0; SIZE];
+        let sidechain = vec![0.5; SIZE];
+        let mut out_left = vec![0.0; SIZE];
+        let mut out_right = vec![0.0; SIZE];
+
+        let inputs = vec![main_left.as_ptr(), main_right.as_ptr(), sidechain.as_ptr()];
+        let mut outputs = vec![out_left.as_mut_ptr(), out_right.as_mut_ptr()];
+        let mut buffer = unsafe { AudioBuffer::from_raw(3, 2, inputs.as_ptr(), outputs.as_mut_ptr(), SIZE) };
+
+        for channel in 0..2 {
+            for sample in 0..SIZE {
+                let value = buffer.input(channel)[sample] * buffer.input(2)[sample];
+                buffer.output(channel)[sample] = value;
+            }
+        }
+        assert_eq!(out_left, vec![0.5; SIZE]);
+        assert_eq!(out_right, vec![1.0; SIZE]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn output_out_of_bounds() {
+        let input = vec![0.0; SIZE];
+        let mut output = vec![0.0; SIZE];
+        let inputs = vec![input.as_ptr()];
+        let mut outputs = vec![output.as_mut_ptr()];
+        let mut buffer = unsafe { AudioBuffer::from_raw(1, 1, inputs.as_ptr(), outputs.as_mut_ptr(), SIZE) };
+        buffer.output(1);
+    }
+
+    #[test]
+    fn process_in_place() {
+        let input = vec![1.0; SIZE];
+        let mut out1 = vec![0.0; SIZE];
+        let mut out2 = vec![3.0; SIZE];
+
+        let inputs = vec![input.as_ptr()];
+        let mut outputs = vec![out1.as_mut_ptr(), out2.as_mut_ptr()];
+        let mut buffer = unsafe { AudioBuffer::from_raw(1, 2, inputs.as_ptr(), outputs.as_mut_ptr(), SIZE) };
+
+        let mut channels = 0;
+        buffer.process_in_place(|channel| {
+            channel.iter_mut().for_each(|sample| *sample *= 2.0);
+            channels += 1;
+        });
+        assert_eq!(channels, 1);
+        assert_eq!(out1, vec![2.0; SIZE]);
+        assert_eq!(out2, vec![3.0; SIZE]);
+
+        // The host may use the same array for input and output.
+        let mut shared = vec![1.0; SIZE];
+        let inputs = vec![shared.as_ptr()];
+        let mut outputs = vec![shared.as_mut_ptr()];
+        let mut buffer = unsafe { AudioBuffer::from_raw(1, 1, inputs.as_ptr(), outputs.as_mut_ptr(), SIZE) };
+        buffer.process_in_place(|channel| channel.iter_mut().for_each(|sample| *sample += 1.0));
+        assert_eq!(shared, vec![2.0; SIZE]);
+    }
+
+    #[test]
+    fn zero_sample_passthrough() {
+        // Hosts may pass null channel pointers when there are no samples to process.
+        let inputs = [ptr::null::<f32>(); 2];
+        let mut outputs = [ptr::null_mut::<f32>(); 2];
+        let mut buffer = unsafe { AudioBuffer::from_raw(2, 2, inputs.as_ptr(), outputs.as_mut_ptr(), 0) };
+        assert!(buffer.is_empty());
+
+        let mut channels = 0;
+        for (input, output) in buffer.zip() {
+            output.copy_from_slice(input);
+            assert!(output.is_empty());
+            channels += 1;
+        }
+        assert_eq!(channels, 2);
+
+        let (inputs, mut outputs) = buffer.split();
+        assert!(inputs.get(1).is_empty());
+        assert!(outputs.get_mut(1).is_empty());
+    }
+
+    #[test]
+    fn input_channel_refs() {
+        let in1 = vec![1.0; SIZE];
+        let in2 = vec![2.0; SIZE];
+        let mut out1 = vec![0.0; SIZE];
+
+        let inputs = vec![in1.as_ptr(), in2.as_ptr()];
+        let mut outputs = vec![out1.as_mut_ptr()];
+        let mut buffer = unsafe { AudioBuffer::from_raw(2, 1, inputs.as_ptr(), outputs.as_mut_ptr(), SIZE) };
+        let (inputs, _) = buffer.split();
+
+        let refs: &[&[f32]] = &inputs.to_channel_refs();
+        assert_eq!(refs, &[&in1[..], &in2[..]]);
+        assert!(inputs.channels().eq(refs.iter().copied()));
+    }
+
     // Test that the `zip()` method returns an iterator that gives `n` elements
     // where n is the number of inputs when this is lower than the number of outputs.
     #[test]
@@ -582,6 +1111,234 @@ mod tests {
         assert_eq!(None, iter.next());
     }
 
+    // Test that `zip_longest()` visits every output channel when there are fewer inputs.
+    #[test]
+    fn buffer_zip_longest_fewer_inputs_than_outputs() {
+        let in1 = vec![1.0; SIZE];
+        let in2 = vec![2.0; SIZE];
+
+        let mut out1 = vec![3.0; SIZE];
+        let mut out2 = vec![4.0; SIZE];
+        let mut out3 = vec![5.0; SIZE];
+        let mut out4 = vec![6.0; SIZE];
+
+        let inputs = vec![in1.as_ptr(), in2.as_ptr()];
+        let mut outputs = vec![
+            out1.as_mut_ptr(),
+            out2.as_mut_ptr(),
+            out3.as_mut_ptr(),
+            out4.as_mut_ptr(),
+        ];
+        let mut buffer = unsafe { AudioBuffer::from_raw(2, 4, inputs.as_ptr(), outputs.as_mut_ptr(), SIZE) };
+
+        let mut count = 0;
+        for (index, (input, output)) in buffer.zip_longest().enumerate() {
+            let output = output.unwrap();
+            match input {
+                Some(input) => output.copy_from_slice(input),
+                None => {
+                    assert!(index >= 2);
+                    output.iter_mut().for_each(|sample| *sample = 0.0);
+                }
+            }
+            count += 1;
+        }
+
+        assert_eq!(count, 4);
+        assert_eq!(out1, in1);
+        assert_eq!(out2, in2);
+        assert!(out3.iter().chain(out4.iter()).all(|&sample| sample == 0.0));
+    }
+
+    // Test that `zip_longest()` yields the extra inputs when there are fewer outputs.
+    #[test]
+    fn buffer_zip_longest_more_inputs_than_outputs() {
+        let in1 = vec![1.0; SIZE];
+        let in2 = vec![2.0; SIZE];
+
+        let mut out1 = vec![3.0; SIZE];
+
+        let inputs = vec![in1.as_ptr(), in2.as_ptr()];
+        let mut outputs = vec![out1.as_mut_ptr()];
+        let mut buffer = unsafe { AudioBuffer::from_raw(2, 1, inputs.as_ptr(), outputs.as_mut_ptr(), SIZE) };
+
+        let mut iter = buffer.zip_longest();
+        let (input, output) = iter.next().unwrap();
+        assert_eq!(input.unwrap()[0], 1.0);
+        assert_eq!(output.unwrap()[0], 3.0);
+
+        let (input, output) = iter.next().unwrap();
+        assert_eq!(input.unwrap()[0], 2.0);
+        assert!(output.is_none());
+
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn buffer_channels() {
+        let in1 = vec![1.0; SIZE];
+        let in2 = vec![2.0; SIZE];
+
+        let mut out1 = vec![0.0; SIZE];
+        let mut out2 = vec![0.0; SIZE];
+        let mut out3 = vec![0.0; SIZE];
+        let mut out4 = vec![0.0; SIZE];
+
+        let inputs = vec![in1.as_ptr(), in2.as_ptr()];
+        let mut outputs = vec![
+            out1.as_mut_ptr(),
+            out2.as_mut_ptr(),
+            out3.as_mut_ptr(),
+            out4.as_mut_ptr(),
+        ];
+        let mut buffer = unsafe { AudioBuffer::from_raw(2, 4, inputs.as_ptr(), outputs.as_mut_ptr(), SIZE) };
+
+        let (inputs, outputs) = buffer.channels();
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(outputs.len(), 4);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Null pointer for output channel 1")]
+    fn from_raw_null_channel() {
+        let input = vec![0.0f32; SIZE];
+        let mut output = vec![0.0f32; SIZE];
+
+        let inputs = vec![input.as_ptr()];
+        let mut outputs = vec![output.as_mut_ptr(), std::ptr::null_mut()];
+        unsafe { AudioBuffer::from_raw(1, 2, inputs.as_ptr(), outputs.as_mut_ptr(), SIZE) };
+    }
+
+    /// Test that buffers created from slices pass samples through.
+    #[test]
+    fn from_slices() {
+        let in1: Vec<f32> = (0..SIZE).map(|x| x as f32).collect();
+        let in2 = in1.clone();
+
+        let mut out1 = vec![0.0; SIZE];
+        let mut out2 = out1.clone();
+
+        {
+            let inputs = [&in1[..], &in2[..]];
+            let mut outputs = [&mut out1[..], &mut out2[..]];
+            let mut slices = AudioBuffer::from_slices(&inputs, &mut outputs);
+            let mut buffer = slices.buffer();
+
+            assert_eq!(buffer.input_count(), 2);
+            assert_eq!(buffer.output_count(), 2);
+            assert_eq!(buffer.samples(), SIZE);
+
+            for (input, output) in buffer.zip() {
+                output.copy_from_slice(input);
+            }
+        }
+
+        assert_eq!(out1, in1);
+        assert_eq!(out2, in2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Mismatching lengths of output arrays")]
+    fn from_slices_mismatching_lengths() {
+        let input = vec![0.0f32; SIZE];
+        let mut output = vec![0.0f32; SIZE - 1];
+
+        let inputs = [&input[..]];
+        let mut outputs = [&mut output[..]];
+        AudioBuffer::from_slices(&inputs, &mut outputs);
+    }
+
+    /// Test swapping the channels of a stereo pair.
+    #[test]
+    fn stereo_pair() {
+        let left_in = vec![1.0; SIZE];
+        let right_in = vec![2.0; SIZE];
+
+        let mut left_out = vec![0.0; SIZE];
+        let mut right_out = vec![0.0; SIZE];
+
+        let inputs = vec![left_in.as_ptr(), right_in.as_ptr()];
+        let mut outputs = vec![left_out.as_mut_ptr(), right_out.as_mut_ptr()];
+        let mut buffer = unsafe { AudioBuffer::from_raw(2, 2, inputs.as_ptr(), outputs.as_mut_ptr(), SIZE) };
+
+        let (inputs, mut outputs) = buffer.split();
+        let (input_left, input_right) = inputs.stereo().unwrap();
+        let (output_left, output_right) = outputs.stereo_mut().unwrap();
+        output_left.copy_from_slice(input_right);
+        output_right.copy_from_slice(input_left);
+
+        assert_eq!(left_out, right_in);
+        assert_eq!(right_out, left_in);
+    }
+
+    #[test]
+    fn stereo_pair_mono() {
+        let input = vec![1.0; SIZE];
+        let mut output = vec![0.0; SIZE];
+
+        let inputs = vec![input.as_ptr()];
+        let mut outputs = vec![output.as_mut_ptr()];
+        let mut buffer = unsafe { AudioBuffer::from_raw(1, 1, inputs.as_ptr(), outputs.as_mut_ptr(), SIZE) };
+
+        let (inputs, mut outputs) = buffer.split();
+        assert!(inputs.stereo().is_none());
+        assert!(outputs.stereo_mut().is_none());
+    }
+
+    #[test]
+    fn send_events() {
+        struct RecordingHost {
+            received: RefCell<Vec<i32>>,
+        }
+
+        impl Host for RecordingHost {
+            fn process_events(&self, events: &api::Events) {
+                self.received.borrow_mut().push(events.num_events);
+            }
+        }
+
+        let host = RecordingHost {
+            received: RefCell::new(Vec::new()),
+        };
+        let mut send_buffer = SendEventBuffer::new(2);
+
+        let events = vec![MidiEvent::simple([0x90, 60, 100]); 3];
+        send_buffer.send_events(&events[..1], &host);
+        send_buffer.send_events(&events, &host);
+
+        assert_eq!(*host.received.borrow(), vec![1, 2]);
+    }
+
+    /// Test that stored events can be iterated.
+    #[test]
+    fn send_event_buffer_iter() {
+        let mut send_buffer = SendEventBuffer::new(2);
+        let payload = [0xf0, 0x7e, 0xf7];
+        let sysex = SysExEvent {
+            payload: &payload,
+            delta_frames: 8,
+        };
+        send_buffer.store_events(vec![
+            Event::Midi(MidiEvent::simple([0x90, 60, 100])),
+            Event::SysEx(sysex),
+        ]);
+
+        let mut events = send_buffer.iter();
+        match events.next() {
+            Some(Event::Midi(event)) => assert_eq!(event.data, [0x90, 60, 100]),
+            _ => panic!("Expected a midi event"),
+        }
+        match events.next() {
+            Some(Event::SysEx(event)) => {
+                assert_eq!(event.payload, &payload);
+                assert_eq!(event.delta_frames, 8);
+            }
+            _ => panic!("Expected a SysEx event"),
+        }
+        assert!(events.next().is_none());
+    }
+
     /// Test that creating buffers from raw pointers works.
     #[test]
     fn from_raw() {
@@ -603,4 +1360,66 @@ mod tests {
             });
         }
     }
+
+    /// Test that converting an `f32` buffer to `f64` and back is lossless.
+    #[test]
+    fn convert_to_f64() {
+        let input: Vec<f32> = (0..SIZE).map(|x| (x as f32 * 0.1).sin() / 3.0).collect();
+        let mut out1 = vec![1.0f32; SIZE];
+        let mut out2 = vec![1.0f32; SIZE];
+
+        {
+            let inputs = [&input[..]];
+            let mut outputs = [&mut out1[..], &mut out2[..]];
+            let mut slices = AudioBuffer::from_slices(&inputs, &mut outputs);
+            let mut buffer = slices.buffer();
+
+            let mut converted = buffer.convert_to_f64();
+            let mut buffer = converted.buffer();
+            assert_eq!(buffer.input_count(), 1);
+            assert_eq!(buffer.output_count(), 2);
+            assert_eq!(buffer.samples(), SIZE);
+
+            for (input, output) in buffer.zip() {
+                for (&sample, converted) in input.iter().zip(output) {
+                    *converted = sample;
+                }
+            }
+        }
+
+        assert_eq!(out1, input);
+        assert_eq!(out2, vec![0.0; SIZE]);
+    }
+
+    /// Test that ndarray views of the channels refer to the underlying samples.
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn ndarray_views() {
+        const LEN: usize = 16;
+        let input: Vec<f32> = (0..2 * LEN).map(|x| x as f32).collect();
+        let mut out1 = vec![0.0f32; LEN];
+        let mut out2 = vec![0.0f32; LEN];
+
+        {
+            let inputs = [&input[..LEN], &input[LEN..]];
+            let mut outputs = [&mut out1[..], &mut out2[..]];
+            let mut slices = AudioBuffer::from_slices(&inputs, &mut outputs);
+            let mut buffer = slices.buffer();
+
+            let views: Vec<_> = buffer.input_views().collect();
+            assert_eq!(views.len(), 2);
+            assert_eq!(views[0].len(), LEN);
+            assert_eq!(views[0][3], 3.0);
+            assert_eq!(views[1][3], (LEN + 3) as f32);
+
+            let mut views = buffer.output_views_mut();
+            assert_eq!(views.len(), 2);
+            views.next().unwrap()[1] = 1.0;
+            views.next().unwrap().fill(0.5);
+        }
+
+        assert_eq!(out1[1], 1.0);
+        assert_eq!(out1.iter().filter(|&&sample| sample != 0.0).count(), 1);
+        assert!(out2.iter().all(|&sample| sample == 0.5));
+    }
 }
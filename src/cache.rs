@@ -1,19 +1,244 @@
-use std::sync::Arc;
+#[cfg(all(test, debug_assertions))]
+use std::cell::RefCell;
+use std::cell::{Cell, UnsafeCell};
+#[cfg(debug_assertions)]
+use std::fmt;
+use std::ptr;
+#[cfg(debug_assertions)]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 
-use crate::{editor::Editor, prelude::*};
+use crate::{api::AEffect, editor::Editor, prelude::*};
 
+/// State of a plugin created using this library, stored in `AEffect::user`.
+///
+/// The cache is shared between the host's threads, so it is only ever accessed through shared
+/// references. The editor and its idle state are only used by the dispatcher on the UI thread, the
+/// processing functions only use `process`.
 pub(crate) struct PluginCache {
     pub info: Info,
     pub params: Arc<dyn PluginParameters>,
-    pub editor: Option<Box<dyn Editor>>,
+    /// The editor, only accessed by the dispatcher through `AEffect::get_editor`.
+    pub editor: UnsafeCell<Option<Box<dyn Editor>>>,
+    /// Whether `PluginParameters::create_editor` still has to be called for a lazily created editor.
+    editor_pending: Cell<bool>,
+    host: HostCallback,
+    /// The plugin id reported by the host while the plugin was being created.
+    pub plugin_id: i32,
+    /// The `(version, vendor, product)` information of the host, queried on first use.
+    pub host_info: OnceLock<(isize, String, String)>,
+    last_idle: Cell<Option<Instant>>,
+    /// The state used by the processing functions.
+    pub process: ProcessCache,
 }
 
+/// The part of the `PluginCache` used by the processing functions.
+///
+/// Processing runs concurrently with the dispatcher, so this only holds immutable or atomic values.
+pub(crate) struct ProcessCache {
+    /// The `(inputs, outputs)` declared in `Info`.
+    declared_channels: (i32, i32),
+    /// Whether the host has resumed the plugin, tracked to diagnose out of order calls.
+    #[cfg(debug_assertions)]
+    resumed: AtomicBool,
+    /// Whether processing while suspended has been reported since the last state change.
+    #[cfg(debug_assertions)]
+    warned_process: AtomicBool,
+    /// Whether differing channel counts have been reported.
+    #[cfg(debug_assertions)]
+    warned_channels: AtomicBool,
+}
+
+// The processing thread shares the `ProcessCache` with the dispatcher.
+const _: fn() = || {
+    fn assert_sync<T: Sync>() {}
+    assert_sync::<ProcessCache>();
+};
+
 impl PluginCache {
-    pub fn new(info: &Info, params: Arc<dyn PluginParameters>, editor: Option<Box<dyn Editor>>) -> Self {
+    pub fn new(
+        info: &Info,
+        params: Arc<dyn PluginParameters>,
+        editor: Option<Box<dyn Editor>>,
+        host: HostCallback,
+        plugin_id: i32,
+    ) -> Self {
         Self {
             info: info.clone(),
             params,
-            editor,
+            editor_pending: Cell::new(info.lazy_editor),
+            editor: UnsafeCell::new(editor),
+            host,
+            plugin_id,
+            host_info: OnceLock::new(),
+            last_idle: Cell::new(None),
+            process: ProcessCache {
+                declared_channels: (info.inputs, info.outputs),
+                #[cfg(debug_assertions)]
+                resumed: AtomicBool::new(false),
+                #[cfg(debug_assertions)]
+                warned_process: AtomicBool::new(false),
+                #[cfg(debug_assertions)]
+                warned_channels: AtomicBool::new(false),
+            },
+        }
+    }
+
+    /// Report the latency from `PluginParameters::get_initial_delay` to the host if it changed.
+    ///
+    /// Hosts only pick up the latency while the plugin is suspended, so this must only be called
+    /// then. Otherwise `initialDelay` would already hold the new value the next time the plugin is
+    /// suspended, and the host would never be notified.
+    pub fn update_initial_delay(&self) {
+        if let Some(delay) = self.params.get_initial_delay() {
+            let effect = self.host.raw_effect();
+            if unsafe { (*effect).initialDelay } != delay {
+                self.host.set_initial_delay(delay);
+            }
+        }
+    }
+
+    /// Create the editor of a plugin with `Info::lazy_editor` set, unless this already happened.
+    ///
+    /// This runs on the UI thread while the plugin may be processing, so the editor is created
+    /// through the shared parameter object instead of `Plugin::get_editor`.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called by the dispatcher.
+    pub unsafe fn create_lazy_editor(&self) {
+        if self.editor_pending.replace(false) {
+            *self.editor.get() = Arc::clone(&self.params).create_editor();
+        }
+    }
+
+    /// Call `Editor::idle`, unless the last call was less than the editor's idle interval ago.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called by the dispatcher.
+    pub unsafe fn idle_editor(&self) {
+        if let Some(ref mut editor) = *self.editor.get() {
+            let now = Instant::now();
+            if let (Some(interval), Some(last_idle)) = (editor.idle_interval_ms(), self.last_idle.get()) {
+                if now.duration_since(last_idle) < Duration::from_millis(interval.into()) {
+                    return;
+                }
+            }
+
+            self.last_idle.set(Some(now));
+            editor.idle();
+        }
+    }
+}
+
+impl ProcessCache {
+    /// Record that the host resumed or suspended the plugin. Only tracked in debug builds.
+    pub fn set_resumed(&self, resumed: bool) {
+        #[cfg(debug_assertions)]
+        {
+            self.resumed.store(resumed, Ordering::Relaxed);
+            self.warned_process.store(false, Ordering::Relaxed);
+        }
+        #[cfg(not(debug_assertions))]
+        let _ = resumed;
+    }
+
+    /// Warn if the host processes audio while the plugin is suspended. Only checked in debug
+    /// builds, and reported once per state change to keep the processing thread quiet.
+    pub fn check_processing(&self) {
+        #[cfg(debug_assertions)]
+        if !self.resumed.load(Ordering::Relaxed) && !self.warned_process.swap(true, Ordering::Relaxed) {
+            warn_host(format_args!(
+                "VST: Host is processing audio while the plugin is suspended"
+            ));
         }
     }
+
+    /// Warn if the host calls `operation`, which is only allowed while the plugin is suspended,
+    /// while it is resumed. Only checked in debug builds.
+    pub fn check_suspended(&self, operation: &str) {
+        #[cfg(debug_assertions)]
+        if self.resumed.load(Ordering::Relaxed) {
+            warn_host(format_args!(
+                "VST: Host called {} while the plugin is resumed",
+                operation
+            ));
+        }
+        #[cfg(not(debug_assertions))]
+        let _ = operation;
+    }
+
+    /// The number of input and output channels to pass to the plugin when processing.
+    ///
+    /// The host allocates channels according to `numInputs` and `numOutputs` of the `AEffect`,
+    /// which are set from `Info` when the plugin is created. Should they have been changed since,
+    /// only the channels known to both are used, so the plugin never accesses channels the host did
+    /// not allocate. This is reported once in debug builds.
+    pub fn channel_counts(&self, effect: &AEffect) -> (usize, usize) {
+        let declared = self.declared_channels;
+        let allocated = (effect.numInputs, effect.numOutputs);
+        #[cfg(debug_assertions)]
+        if declared != allocated && !self.warned_channels.swap(true, Ordering::Relaxed) {
+            warn_host(format_args!(
+                "VST: Plugin declared {:?} (inputs, outputs) but the host was told {:?}, using the smaller counts",
+                declared, allocated
+            ));
+        }
+
+        (
+            declared.0.min(allocated.0).max(0) as usize,
+            declared.1.min(allocated.1).max(0) as usize,
+        )
+    }
+}
+
+/// Log a warning about host calls which don't match the plugin state. Only used in debug builds.
+#[cfg(debug_assertions)]
+fn warn_host(message: fmt::Arguments) {
+    warn!("{}", message);
+    #[cfg(test)]
+    WARNINGS.with(|warnings| warnings.borrow_mut().push(message.to_string()));
+}
+
+#[cfg(all(test, debug_assertions))]
+thread_local! {
+    /// The warnings passed to `warn_host` on this thread, so tests don't need a global logger.
+    static WARNINGS: RefCell<Vec<String>> = RefCell::new(Vec::new());
+}
+
+/// Take the warnings about host calls made on this thread so far.
+#[cfg(all(test, debug_assertions))]
+pub(crate) fn take_warnings() -> Vec<String> {
+    WARNINGS.with(|warnings| warnings.borrow_mut().split_off(0))
+}
+
+thread_local! {
+    /// The plugin the dispatcher is calling into on this thread, and its cached `Info`.
+    static DISPATCH_INFO: Cell<(*const (), *const Info)> = const { Cell::new((ptr::null(), ptr::null())) };
+}
+
+/// Call `f` with `plugin`, letting default `Plugin` methods read `info` through `with_cached_info`
+/// instead of rebuilding it with `Plugin::get_info`.
+pub(crate) fn dispatch_with_info<R>(plugin: &mut dyn Plugin, info: &Info, f: impl FnOnce(&mut dyn Plugin) -> R) -> R {
+    let current = (plugin as *const dyn Plugin as *const (), info as *const Info);
+    let previous = DISPATCH_INFO.with(|cell| cell.replace(current));
+    let result = f(plugin);
+    DISPATCH_INFO.with(|cell| cell.set(previous));
+    result
+}
+
+/// Call `f` with the `Info` of `plugin`.
+///
+/// This is the `Info` cached when the plugin was created if the dispatcher is calling into
+/// `plugin`, or the result of `plugin.get_info()` otherwise, e.g. when a plugin is used directly.
+pub(crate) fn with_cached_info<P: Plugin + ?Sized, R>(plugin: &P, f: impl FnOnce(&Info) -> R) -> R {
+    let (dispatched, info) = DISPATCH_INFO.with(Cell::get);
+    if !info.is_null() && ptr::eq(dispatched, plugin as *const P as *const ()) {
+        // SAFETY: `dispatch_with_info` only stores `info` for the duration of a call.
+        f(unsafe { &*info })
+    } else {
+        f(&plugin.get_info())
+    }
 }
@@ -1,11 +1,15 @@
 //! Meta data for dealing with input / output channels. Not all hosts use this so it is not
 //! necessary for plugin functionality.
 
+use std::error::Error;
+use std::fmt;
+
 use crate::api;
 use crate::api::consts::{MAX_LABEL, MAX_SHORT_LABEL};
 
 /// Information about an input / output channel. This isn't necessary for a channel to function but
 /// informs the host how the channel is meant to be used.
+#[derive(Debug)]
 pub struct ChannelInfo {
     name: String,
     short_name: String,
@@ -16,8 +20,9 @@ pub struct ChannelInfo {
 impl ChannelInfo {
     /// Construct a new `ChannelInfo` object.
     ///
-    /// `name` is a user friendly name for this channel limited to `MAX_LABEL` characters.
-    /// `short_name` is an optional field which provides a short name limited to `MAX_SHORT_LABEL`.
+    /// `name` is a user friendly name for this channel limited to `MAX_LABEL - 1` bytes.
+    /// `short_name` is an optional field which provides a short name limited to
+    /// `MAX_SHORT_LABEL - 1` bytes. Longer names are truncated on a character boundary.
     /// `active` determines whether this channel is active.
     /// `arrangement_type` describes the arrangement type for this channel.
     pub fn new(
@@ -40,19 +45,33 @@ impl ChannelInfo {
             arrangement_type: arrangement_type.unwrap_or(SpeakerArrangementType::Custom),
         }
     }
+
+    /// The user friendly name of this channel.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The short name of this channel, which is the same as `name` if none was given.
+    pub fn short_name(&self) -> &str {
+        &self.short_name
+    }
+
+    /// Whether this channel is active.
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// The arrangement type of this channel.
+    pub fn arrangement_type(&self) -> &SpeakerArrangementType {
+        &self.arrangement_type
+    }
 }
 
 impl Into<api::ChannelProperties> for ChannelInfo {
     /// Convert to the VST api equivalent of this structure.
     fn into(self) -> api::ChannelProperties {
         api::ChannelProperties {
-            name: {
-                let mut label = [0; MAX_LABEL as usize];
-                for (b, c) in self.name.bytes().zip(label.iter_mut()) {
-                    *c = b;
-                }
-                label
-            },
+            name: label::<MAX_LABEL>(&self.name),
             flags: {
                 let mut flag = api::ChannelFlags::empty();
                 if self.active {
@@ -67,18 +86,24 @@ impl Into<api::ChannelProperties> for ChannelInfo {
                 flag.bits()
             },
             arrangement_type: self.arrangement_type.into(),
-            short_name: {
-                let mut label = [0; MAX_SHORT_LABEL as usize];
-                for (b, c) in self.short_name.bytes().zip(label.iter_mut()) {
-                    *c = b;
-                }
-                label
-            },
+            short_name: label::<MAX_SHORT_LABEL>(&self.short_name),
             future: [0; 48],
         }
     }
 }
 
+/// Copy `name` into a null terminated label of `N` bytes, truncating it on a character boundary.
+fn label<const N: usize>(name: &str) -> [u8; N] {
+    let mut len = name.len().min(N - 1);
+    while !name.is_char_boundary(len) {
+        len -= 1;
+    }
+
+    let mut label = [0; N];
+    label[..len].copy_from_slice(&name.as_bytes()[..len]);
+    label
+}
+
 impl From<api::ChannelProperties> for ChannelInfo {
     fn from(api: api::ChannelProperties) -> ChannelInfo {
         ChannelInfo {
@@ -92,8 +117,244 @@ impl From<api::ChannelProperties> for ChannelInfo {
     }
 }
 
+/// A complete speaker arrangement, e.g. of all inputs or all outputs of a plugin.
+///
+/// This can be converted into the `api::SpeakerArrangement` used by the speaker arrangement
+/// opcodes with `TryFrom`, as long as it has no more than 8 speakers.
+pub struct SpeakerArrangement {
+    arrangement_type: api::SpeakerArrangementType,
+    speakers: Vec<api::SpeakerType>,
+}
+
+impl SpeakerArrangement {
+    /// Construct a new `SpeakerArrangement` containing the standard speakers of
+    /// `arrangement_type`, in channel order.
+    ///
+    /// `Custom` and `Empty` arrangements contain no speakers; use `custom` to describe the
+    /// speakers of a user defined arrangement.
+    pub fn new(arrangement_type: SpeakerArrangementType) -> SpeakerArrangement {
+        let arrangement_type: api::SpeakerArrangementType = arrangement_type.into();
+
+        SpeakerArrangement {
+            arrangement_type,
+            speakers: standard_speakers(arrangement_type).to_vec(),
+        }
+    }
+
+    /// Construct a new user defined `SpeakerArrangement` from the given speakers.
+    pub fn custom(speakers: Vec<api::SpeakerType>) -> SpeakerArrangement {
+        SpeakerArrangement {
+            arrangement_type: api::SpeakerArrangementType::Custom,
+            speakers,
+        }
+    }
+
+    /// Get the VST API arrangement type of this arrangement.
+    pub fn arrangement_type(&self) -> api::SpeakerArrangementType {
+        self.arrangement_type
+    }
+
+    /// Get the speakers in this arrangement, in channel order.
+    pub fn speakers(&self) -> &[api::SpeakerType] {
+        &self.speakers
+    }
+
+    /// Get the number of channels in this arrangement.
+    pub fn num_channels(&self) -> usize {
+        self.speakers.len()
+    }
+}
+
+/// An error converting a `SpeakerArrangement` into an `api::SpeakerArrangement`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SpeakerArrangementError {
+    /// The arrangement has more speakers than the 8 which fit into an `api::SpeakerArrangement`,
+    /// such as 8.1 or 10.2 surround. Contains the number of speakers in the arrangement.
+    TooManySpeakers(usize),
+}
+
+impl fmt::Display for SpeakerArrangementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpeakerArrangementError::TooManySpeakers(speakers) => {
+                write!(f, "Too many speakers for api::SpeakerArrangement: {}", speakers)
+            }
+        }
+    }
+}
+
+impl Error for SpeakerArrangementError {}
+
+impl TryFrom<SpeakerArrangement> for api::SpeakerArrangement {
+    type Error = SpeakerArrangementError;
+
+    /// Convert to the VST api equivalent of this structure.
+    ///
+    /// `api::SpeakerArrangement` only has room for 8 speakers, so this fails for arrangements
+    /// with more channels.
+    fn try_from(arrangement: SpeakerArrangement) -> Result<api::SpeakerArrangement, SpeakerArrangementError> {
+        let empty = api::SpeakerProperties {
+            azimuth: 0.0,
+            elevation: 0.0,
+            radius: 0.0,
+            _reserved: 0.0,
+            name: [0; MAX_LABEL],
+            speaker_type: api::SpeakerType::Undefined.into(),
+            future: [0; 28],
+        };
+        let mut speakers = [empty; 8];
+
+        if arrangement.speakers.len() > speakers.len() {
+            return Err(SpeakerArrangementError::TooManySpeakers(arrangement.speakers.len()));
+        }
+
+        for (speaker, properties) in arrangement.speakers.iter().zip(speakers.iter_mut()) {
+            for (b, c) in speaker.short_name().bytes().zip(properties.name.iter_mut()) {
+                *c = b;
+            }
+            properties.speaker_type = (*speaker).into();
+        }
+
+        Ok(api::SpeakerArrangement {
+            arrangement_type: arrangement.arrangement_type as i32,
+            num_channels: arrangement.speakers.len() as i32,
+            speakers,
+        })
+    }
+}
+
+/// The speakers of a standard arrangement type, in channel order.
+fn standard_speakers(arrangement_type: api::SpeakerArrangementType) -> &'static [api::SpeakerType] {
+    use api::SpeakerArrangementType as Raw;
+    use api::SpeakerType::*;
+
+    match arrangement_type {
+        Raw::Custom | Raw::Empty => &[],
+        Raw::Mono => &[Mono],
+
+        Raw::Stereo => &[Left, Right],
+        Raw::StereoSurround => &[LeftSurround, RightSurround],
+        Raw::StereoCenter => &[LeftCenter, RightCenter],
+        Raw::StereoSide => &[SideLeft, SideRight],
+        Raw::StereoCLfe => &[Center, Lfe],
+
+        Raw::Cinema30 => &[Left, Right, Center],
+        Raw::Music30 => &[Left, Right, Surround],
+
+        Raw::Cinema31 => &[Left, Right, Center, Lfe],
+        Raw::Music31 => &[Left, Right, Lfe, Surround],
+
+        Raw::Cinema40 => &[Left, Right, Center, Surround],
+        Raw::Music40 => &[Left, Right, LeftSurround, RightSurround],
+
+        Raw::Cinema41 => &[Left, Right, Center, Lfe, Surround],
+        Raw::Music41 => &[Left, Right, Lfe, LeftSurround, RightSurround],
+
+        Raw::Surround50 => &[Left, Right, Center, LeftSurround, RightSurround],
+        Raw::Surround51 => &[Left, Right, Center, Lfe, LeftSurround, RightSurround],
+
+        Raw::Cinema60 => &[Left, Right, Center, LeftSurround, RightSurround, Surround],
+        Raw::Music60 => &[Left, Right, LeftSurround, RightSurround, SideLeft, SideRight],
+
+        Raw::Cinema61 => &[Left, Right, Center, Lfe, LeftSurround, RightSurround, Surround],
+        Raw::Music61 => &[Left, Right, Lfe, LeftSurround, RightSurround, SideLeft, SideRight],
+
+        Raw::Cinema70 => &[
+            Left,
+            Right,
+            Center,
+            LeftSurround,
+            RightSurround,
+            LeftCenter,
+            RightCenter,
+        ],
+        Raw::Music70 => &[Left, Right, Center, LeftSurround, RightSurround, SideLeft, SideRight],
+
+        Raw::Cinema71 => &[
+            Left,
+            Right,
+            Center,
+            Lfe,
+            LeftSurround,
+            RightSurround,
+            LeftCenter,
+            RightCenter,
+        ],
+        Raw::Music71 => &[
+            Left,
+            Right,
+            Center,
+            Lfe,
+            LeftSurround,
+            RightSurround,
+            SideLeft,
+            SideRight,
+        ],
+
+        Raw::Cinema80 => &[
+            Left,
+            Right,
+            Center,
+            LeftSurround,
+            RightSurround,
+            LeftCenter,
+            RightCenter,
+            Surround,
+        ],
+        Raw::Music80 => &[
+            Left,
+            Right,
+            Center,
+            LeftSurround,
+            RightSurround,
+            Surround,
+            SideLeft,
+            SideRight,
+        ],
+
+        Raw::Cinema81 => &[
+            Left,
+            Right,
+            Center,
+            Lfe,
+            LeftSurround,
+            RightSurround,
+            LeftCenter,
+            RightCenter,
+            Surround,
+        ],
+        Raw::Music81 => &[
+            Left,
+            Right,
+            Center,
+            Lfe,
+            LeftSurround,
+            RightSurround,
+            Surround,
+            SideLeft,
+            SideRight,
+        ],
+
+        Raw::Surround102 => &[
+            Left,
+            Right,
+            Center,
+            Lfe,
+            LeftSurround,
+            RightSurround,
+            TopFrontLeft,
+            TopFrontCenter,
+            TopFrontRight,
+            TopRearLeft,
+            TopRearRight,
+            Lfe2,
+        ],
+    }
+}
+
 /// Target for Speaker arrangement type. Can be a cinema configuration or music configuration. Both
 /// are technically identical but this provides extra information to the host.
+#[derive(Debug)]
 pub enum ArrangementTarget {
     /// Music arrangement. Technically identical to Cinema.
     Music,
@@ -102,6 +363,7 @@ pub enum ArrangementTarget {
 }
 
 /// An enum for all channels in a stereo configuration.
+#[derive(Debug)]
 pub enum StereoChannel {
     /// Left channel.
     Left,
@@ -110,6 +372,7 @@ pub enum StereoChannel {
 }
 
 /// Possible stereo speaker configurations.
+#[derive(Debug)]
 #[allow(non_camel_case_types)]
 pub enum StereoConfig {
     /// Regular.
@@ -125,6 +388,7 @@ pub enum StereoConfig {
 }
 
 /// Possible surround speaker configurations.
+#[derive(Debug)]
 #[allow(non_camel_case_types)]
 pub enum SurroundConfig {
     /// 3.0 surround sound.
@@ -179,6 +443,7 @@ pub enum SurroundConfig {
 }
 
 /// Type representing how a channel is used. Only useful for some hosts.
+#[derive(Debug)]
 pub enum SpeakerArrangementType {
     /// Custom arrangement not specified to host.
     Custom,
@@ -350,3 +615,187 @@ impl From<api::ChannelProperties> for SpeakerArrangementType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::api;
+    use crate::api::SpeakerType::*;
+    use crate::channels::{
+        ArrangementTarget, ChannelInfo, SpeakerArrangement, SpeakerArrangementError, SpeakerArrangementType,
+        StereoChannel, StereoConfig, SurroundConfig,
+    };
+
+    #[test]
+    fn channel_info_accessors() {
+        let arrangement = SpeakerArrangementType::Stereo(StereoConfig::L_R, StereoChannel::Left);
+        let info = ChannelInfo::new(
+            "Left input".to_string(),
+            Some("L in".to_string()),
+            true,
+            Some(arrangement),
+        );
+        assert_eq!(info.name(), "Left input");
+        assert_eq!(info.short_name(), "L in");
+        assert!(info.is_active());
+        assert!(info.arrangement_type().is_left_stereo());
+
+        let info = ChannelInfo::new("Aux".to_string(), None, false, None);
+        assert_eq!(info.short_name(), "Aux");
+        assert!(!info.is_active());
+        assert!(matches!(info.arrangement_type(), SpeakerArrangementType::Custom));
+    }
+
+    #[test]
+    fn channel_info_debug() {
+        let arrangement = SpeakerArrangementType::Stereo(StereoConfig::L_R, StereoChannel::Right);
+        let info = ChannelInfo::new("Right".to_string(), None, true, Some(arrangement));
+        assert_eq!(
+            format!("{:?}", info),
+            "ChannelInfo { name: \"Right\", short_name: \"Right\", active: true, \
+             arrangement_type: Stereo(L_R, Right) }"
+        );
+    }
+
+    #[test]
+    fn stereo_arrangement() {
+        let arrangement =
+            SpeakerArrangement::new(SpeakerArrangementType::Stereo(StereoConfig::L_R, StereoChannel::Left));
+        assert_eq!(arrangement.arrangement_type(), api::SpeakerArrangementType::Stereo);
+        assert_eq!(arrangement.speakers(), &[Left, Right]);
+
+        let raw = api::SpeakerArrangement::try_from(arrangement).unwrap();
+        assert_eq!(raw.arrangement_type, api::SpeakerArrangementType::Stereo as i32);
+        assert_eq!(raw.num_channels, 2);
+        assert_eq!(raw.speakers[0].speaker_type, Left.into());
+        assert_eq!(&raw.speakers[0].name[..2], b"L\0");
+        assert_eq!(raw.speakers[1].speaker_type, Right.into());
+        assert_eq!(raw.speakers[2].speaker_type, Undefined.into());
+    }
+
+    #[test]
+    fn surround_51_arrangement() {
+        let arrangement = SpeakerArrangement::new(SpeakerArrangementType::Surround(SurroundConfig::S5_1));
+        assert_eq!(arrangement.num_channels(), 6);
+
+        let raw = api::SpeakerArrangement::try_from(arrangement).unwrap();
+        assert_eq!(raw.arrangement_type, api::SpeakerArrangementType::Surround51 as i32);
+        assert_eq!(raw.num_channels, 6);
+
+        let types: Vec<i32> = raw.speakers[..6].iter().map(|s| s.speaker_type).collect();
+        let expected: Vec<i32> = [Left, Right, Center, Lfe, LeftSurround, RightSurround]
+            .iter()
+            .map(|&s| s.into())
+            .collect();
+        assert_eq!(types, expected);
+        assert_eq!(&raw.speakers[3].name[..4], b"Lfe\0");
+    }
+
+    #[test]
+    fn surround_102_does_not_fit() {
+        let arrangement = SpeakerArrangement::new(SpeakerArrangementType::Surround(SurroundConfig::S10_2));
+        assert_eq!(arrangement.num_channels(), 12);
+
+        let result = api::SpeakerArrangement::try_from(arrangement);
+        assert_eq!(result.err(), Some(SpeakerArrangementError::TooManySpeakers(12)));
+    }
+
+    /// Every arrangement type, with the number of speakers it contains.
+    fn all_arrangement_types() -> Vec<(SpeakerArrangementType, usize)> {
+        use self::ArrangementTarget::{Cinema, Music};
+        use self::SpeakerArrangementType::*;
+        use self::SurroundConfig::*;
+
+        let stereo = |config| Stereo(config, StereoChannel::Left);
+        vec![
+            (Custom, 0),
+            (Empty, 0),
+            (Mono, 1),
+            (stereo(StereoConfig::L_R), 2),
+            (stereo(StereoConfig::Ls_Rs), 2),
+            (stereo(StereoConfig::Lc_Rc), 2),
+            (stereo(StereoConfig::Sl_Sr), 2),
+            (stereo(StereoConfig::C_Lfe), 2),
+            (Surround(S3_0(Cinema)), 3),
+            (Surround(S3_0(Music)), 3),
+            (Surround(S3_1(Cinema)), 4),
+            (Surround(S3_1(Music)), 4),
+            (Surround(S4_0(Cinema)), 4),
+            (Surround(S4_0(Music)), 4),
+            (Surround(S4_1(Cinema)), 5),
+            (Surround(S4_1(Music)), 5),
+            (Surround(S5_0), 5),
+            (Surround(S5_1), 6),
+            (Surround(S6_0(Cinema)), 6),
+            (Surround(S6_0(Music)), 6),
+            (Surround(S6_1(Cinema)), 7),
+            (Surround(S6_1(Music)), 7),
+            (Surround(S7_0(Cinema)), 7),
+            (Surround(S7_0(Music)), 7),
+            (Surround(S7_1(Cinema)), 8),
+            (Surround(S7_1(Music)), 8),
+            (Surround(S8_0(Cinema)), 8),
+            (Surround(S8_0(Music)), 8),
+            (Surround(S8_1(Cinema)), 9),
+            (Surround(S8_1(Music)), 9),
+            (Surround(S10_2), 12),
+        ]
+    }
+
+    #[test]
+    fn convert_every_arrangement() {
+        for (arrangement_type, count) in all_arrangement_types() {
+            let description = format!("{:?}", arrangement_type);
+            let arrangement = SpeakerArrangement::new(arrangement_type);
+            assert_eq!(arrangement.num_channels(), count, "{}", description);
+
+            let raw_type = arrangement.arrangement_type() as i32;
+            let speakers: Vec<i32> = arrangement.speakers().iter().map(|&s| s.into()).collect();
+
+            match api::SpeakerArrangement::try_from(arrangement) {
+                Ok(raw) => {
+                    assert!(count <= 8, "{}", description);
+                    assert_eq!(raw.arrangement_type, raw_type, "{}", description);
+                    assert_eq!(raw.num_channels as usize, count, "{}", description);
+                    let types: Vec<i32> = raw.speakers[..count].iter().map(|s| s.speaker_type).collect();
+                    assert_eq!(types, speakers, "{}", description);
+                }
+                Err(error) => {
+                    assert!(count > 8, "{}", description);
+                    assert_eq!(
+                        error,
+                        SpeakerArrangementError::TooManySpeakers(count),
+                        "{}",
+                        description
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn custom_arrangement_too_large() {
+        let arrangement = SpeakerArrangement::custom(vec![Mono; 9]);
+        let error = api::SpeakerArrangement::try_from(arrangement).err().unwrap();
+        assert_eq!(error, SpeakerArrangementError::TooManySpeakers(9));
+        assert_eq!(error.to_string(), "Too many speakers for api::SpeakerArrangement: 9");
+    }
+
+    #[test]
+    fn channel_label_truncation() {
+        // 62 ASCII bytes followed by a 3 byte character, which only fits partially into 63 bytes.
+        let name = format!("{}€", "a".repeat(62));
+        let info = ChannelInfo::new(name, Some("Gärtnä".to_string()), true, None);
+
+        let raw: api::ChannelProperties = info.into();
+        assert_eq!(&raw.name[..62], "a".repeat(62).as_bytes());
+        assert_eq!(raw.name[62..], [0, 0]);
+        // "Gärtn" is 6 bytes, the second 'ä' would overlap the null terminator.
+        assert_eq!(&raw.short_name, b"G\xc3\xa4rtn\0\0");
+
+        let info = ChannelInfo::new("€".repeat(30), Some("äää".to_string()), true, None);
+        let raw: api::ChannelProperties = info.into();
+        assert_eq!(&raw.name[..63], "€".repeat(21).as_bytes());
+        assert_eq!(raw.name[63], 0);
+        assert_eq!(&raw.short_name[..7], b"\xc3\xa4\xc3\xa4\xc3\xa4\0");
+    }
+}
@@ -2,8 +2,12 @@
 
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 
+use std::error::Error;
+use std::fmt;
 use std::os::raw::c_void;
 
+use crate::api::ModifierKey;
+
 /// Implemented by plugin editors.
 #[allow(unused_variables)]
 pub trait Editor {
@@ -16,6 +20,15 @@ pub trait Editor {
     /// Editor idle call. Called by host.
     fn idle(&mut self) {}
 
+    /// Minimum time in milliseconds between two calls to `idle`.
+    ///
+    /// Hosts send idle calls at their own, often high, rate. If this returns an interval, idle
+    /// calls arriving sooner than that after the previous call to `idle` are skipped. Returns
+    /// `None` by default, which forwards every idle call.
+    fn idle_interval_ms(&self) -> Option<u32> {
+        None
+    }
+
     /// Called when the editor window is closed.
     fn close(&mut self) {}
 
@@ -33,9 +46,40 @@ pub trait Editor {
     /// Return `true` if the window opened successfully, `false` otherwise.
     fn open(&mut self, parent: *mut c_void) -> bool;
 
+    /// Called when the editor window is opened, reporting why opening failed.
+    ///
+    /// The host calls this instead of `open`, and logs the error if opening failed. Editors
+    /// which can tell why opening failed should override this; the default calls `open` and
+    /// reports `EditorOpenError::Failed` if it returns `false`.
+    fn open_result(&mut self, parent: *mut c_void) -> Result<(), EditorOpenError> {
+        if self.open(parent) {
+            Ok(())
+        } else {
+            Err(EditorOpenError::Failed)
+        }
+    }
+
     /// Return whether the window is currently open.
     fn is_open(&mut self) -> bool;
 
+    /// Get the handle of the window created in `open`, if the editor exposes it.
+    ///
+    /// Like the `parent` pointer passed to `open`, the meaning of the handle depends on the
+    /// platform (`HWND` on Windows, `NSView*` on Mac OS X, the window ID on X11). VST has no
+    /// standard opcode for this query, so editors loaded by a host return `None`.
+    fn window_handle(&self) -> Option<*mut c_void> {
+        None
+    }
+
+    /// Whether the editor wants to receive keyboard events.
+    ///
+    /// Hosts which still ask for this can use it to decide whether to route keyboard focus to the
+    /// editor, and may stop sending `key_down` and `key_up` to editors returning `false`. Editors
+    /// handling keys should therefore return `true`. Returns `false` by default.
+    fn wants_keyboard(&self) -> bool {
+        false
+    }
+
     /// Set the knob mode for this editor (if supported by host).
     ///
     /// Return `true` if the knob mode was set.
@@ -54,6 +98,35 @@ pub trait Editor {
     }
 }
 
+/// The reason an editor window could not be opened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditorOpenError {
+    /// The parent window handle passed by the host is invalid, e.g. null.
+    InvalidParent,
+
+    /// The editor does not support the platform or windowing system it is running on.
+    UnsupportedPlatform,
+
+    /// Opening failed for another reason, described by the message.
+    Other(String),
+
+    /// Opening failed without a reason, as reported by `Editor::open`.
+    Failed,
+}
+
+impl fmt::Display for EditorOpenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditorOpenError::InvalidParent => write!(f, "Invalid parent window handle"),
+            EditorOpenError::UnsupportedPlatform => write!(f, "Unsupported platform"),
+            EditorOpenError::Other(message) => write!(f, "{}", message),
+            EditorOpenError::Failed => write!(f, "Editor could not be opened"),
+        }
+    }
+}
+
+impl Error for EditorOpenError {}
+
 /// Rectangle used to specify dimensions of editor window.
 #[doc(hidden)]
 #[derive(Copy, Clone, Debug)]
@@ -68,27 +141,103 @@ pub struct Rect {
     pub right: i16,
 }
 
+/// Size of an editor window in pixels.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct EditorSize {
+    /// Width of the window in pixels.
+    pub width: u32,
+    /// Height of the window in pixels.
+    pub height: u32,
+}
+
+impl From<Rect> for EditorSize {
+    fn from(rect: Rect) -> EditorSize {
+        // Assuming coordinate origins from top-left
+        EditorSize {
+            width: (rect.right as i32 - rect.left as i32).max(0) as u32,
+            height: (rect.bottom as i32 - rect.top as i32).max(0) as u32,
+        }
+    }
+}
+
+impl From<EditorSize> for (i32, i32) {
+    fn from(size: EditorSize) -> (i32, i32) {
+        (size.width as i32, size.height as i32)
+    }
+}
+
+/// Position of the top-left corner of an editor window in pixels.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct EditorPosition {
+    /// X coordinate in pixels.
+    pub x: i32,
+    /// Y coordinate in pixels.
+    pub y: i32,
+}
+
+impl From<Rect> for EditorPosition {
+    fn from(rect: Rect) -> EditorPosition {
+        EditorPosition {
+            x: rect.left as i32,
+            y: rect.top as i32,
+        }
+    }
+}
+
+impl From<EditorPosition> for (i32, i32) {
+    fn from(position: EditorPosition) -> (i32, i32) {
+        (position.x, position.y)
+    }
+}
+
 /// A platform independent key code. Includes modifier keys.
 #[derive(Copy, Clone, Debug)]
 pub struct KeyCode {
     /// ASCII character for key pressed (if applicable).
     pub character: char,
-    /// Key pressed. See `enums::Key`.
+    /// Key pressed. See `Key`.
     pub key: Key,
-    /// Modifier key bitflags. See `enums::flags::modifier_key`.
+    /// Modifier key bitflags. See `api::ModifierKey` and `modifiers`.
     pub modifier: u8,
 }
 
+impl KeyCode {
+    /// Create a key code from a character, key and modifier key flags.
+    pub fn new(character: char, key: Key, modifiers: ModifierKey) -> KeyCode {
+        KeyCode {
+            character,
+            key,
+            modifier: modifiers.bits(),
+        }
+    }
+
+    /// Get the modifier keys held down for this key code. Unknown bits are ignored.
+    pub fn modifiers(&self) -> ModifierKey {
+        ModifierKey::from_bits_truncate(self.modifier)
+    }
+}
+
 /// Allows host to set how a parameter knob works.
 #[repr(isize)]
-#[derive(Copy, Clone, Debug, TryFromPrimitive, IntoPrimitive)]
+#[derive(Copy, Clone, Debug, Default, TryFromPrimitive, IntoPrimitive)]
 #[allow(missing_docs)]
 pub enum KnobMode {
+    #[default]
     Circular,
     CircularRelative,
     Linear,
 }
 
+impl KnobMode {
+    /// Convert a raw knob mode from the host, returning `KnobMode::Circular` for unknown values.
+    pub fn from_raw(value: isize) -> KnobMode {
+        KnobMode::try_from(value).unwrap_or_else(|_| {
+            trace!("VST: Got unknown knob mode ({}), using circular", value);
+            KnobMode::default()
+        })
+    }
+}
+
 /// Platform independent key codes.
 #[allow(missing_docs)]
 #[repr(isize)]
@@ -153,3 +302,154 @@ pub enum Key {
     Alt,
     Equals,
 }
+
+impl Key {
+    /// Convert a raw key code from the host, returning `Key::None` for unknown codes.
+    pub fn from_raw_lossy(value: isize) -> Key {
+        Key::try_from(value).unwrap_or(Key::None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::ModifierKey;
+    use crate::editor::{Editor, EditorOpenError, EditorPosition, EditorSize, Key, KeyCode, KnobMode, Rect};
+
+    use std::os::raw::c_void;
+
+    struct TestEditor;
+
+    impl Editor for TestEditor {
+        fn size(&self) -> (i32, i32) {
+            (0, 0)
+        }
+
+        fn position(&self) -> (i32, i32) {
+            (0, 0)
+        }
+
+        fn open(&mut self, _parent: *mut c_void) -> bool {
+            true
+        }
+
+        fn is_open(&mut self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn key_code_modifiers() {
+        let keycode = KeyCode::new('a', Key::None, ModifierKey::SHIFT | ModifierKey::ALT);
+
+        assert_eq!(keycode.modifier, 0b11);
+        assert_eq!(keycode.modifiers(), ModifierKey::SHIFT | ModifierKey::ALT);
+        assert!(!keycode.modifiers().contains(ModifierKey::CONTROL));
+
+        let raw = KeyCode {
+            character: 'a',
+            key: Key::None,
+            modifier: 0xf0 | ModifierKey::SHIFT.bits(),
+        };
+        assert_eq!(raw.modifiers(), ModifierKey::SHIFT);
+    }
+
+    #[test]
+    fn window_handle_defaults_to_none() {
+        assert!(TestEditor.window_handle().is_none());
+    }
+
+    #[test]
+    fn rect_conversions() {
+        let rect = Rect {
+            top: 20,
+            left: 10,
+            bottom: 220,
+            right: 410,
+        };
+
+        assert_eq!(
+            EditorSize::from(rect),
+            EditorSize {
+                width: 400,
+                height: 200
+            }
+        );
+        assert_eq!(EditorPosition::from(rect), EditorPosition { x: 10, y: 20 });
+        assert_eq!(<(i32, i32)>::from(EditorSize::from(rect)), (400, 200));
+        assert_eq!(<(i32, i32)>::from(EditorPosition::from(rect)), (10, 20));
+    }
+
+    #[test]
+    fn inverted_rect_has_empty_size() {
+        let rect = Rect {
+            top: 50,
+            left: 50,
+            bottom: 0,
+            right: 0,
+        };
+
+        assert_eq!(EditorSize::from(rect), EditorSize::default());
+    }
+
+    #[test]
+    fn key_from_raw_lossy() {
+        assert!(matches!(Key::from_raw_lossy(Key::Escape.into()), Key::Escape));
+        assert!(matches!(Key::from_raw_lossy(Key::Equals.into()), Key::Equals));
+        assert!(matches!(Key::from_raw_lossy(1000), Key::None));
+        assert!(matches!(Key::from_raw_lossy(-1), Key::None));
+    }
+
+    #[test]
+    fn knob_mode_from_raw() {
+        assert!(matches!(KnobMode::from_raw(1), KnobMode::CircularRelative));
+        assert!(matches!(KnobMode::from_raw(2), KnobMode::Linear));
+        assert!(matches!(KnobMode::from_raw(3), KnobMode::Circular));
+        assert!(matches!(KnobMode::from_raw(-1), KnobMode::Circular));
+    }
+
+    #[test]
+    fn open_result() {
+        struct HeadlessEditor;
+
+        impl Editor for HeadlessEditor {
+            fn size(&self) -> (i32, i32) {
+                (0, 0)
+            }
+
+            fn position(&self) -> (i32, i32) {
+                (0, 0)
+            }
+
+            fn open(&mut self, _parent: *mut c_void) -> bool {
+                false
+            }
+
+            fn open_result(&mut self, parent: *mut c_void) -> Result<(), EditorOpenError> {
+                if parent.is_null() {
+                    Err(EditorOpenError::InvalidParent)
+                } else {
+                    Err(EditorOpenError::UnsupportedPlatform)
+                }
+            }
+
+            fn is_open(&mut self) -> bool {
+                false
+            }
+        }
+
+        let mut parent = 0u8;
+        let parent = &mut parent as *mut u8 as *mut c_void;
+        assert_eq!(
+            HeadlessEditor.open_result(std::ptr::null_mut()),
+            Err(EditorOpenError::InvalidParent)
+        );
+        assert_eq!(
+            HeadlessEditor.open_result(parent),
+            Err(EditorOpenError::UnsupportedPlatform)
+        );
+        assert_eq!(EditorOpenError::UnsupportedPlatform.to_string(), "Unsupported platform");
+
+        // The default implementation delegates to `open`
+        assert_eq!(TestEditor.open_result(parent), Ok(()));
+    }
+}
@@ -59,6 +59,43 @@ pub struct MidiEvent {
 
     /// Note off velocity between 0 and 127.
     pub note_off_velocity: u8,
+
+    /// The raw `api::MidiEventFlags` bits of this event, including any host specific bits.
+    ///
+    /// These are written back when the event is sent, except for `REALTIME_EVENT`, which is
+    /// always taken from `live`.
+    pub raw_flags: i32,
+}
+
+impl MidiEvent {
+    /// Create a midi event from its raw data, occurring at the start of the processing block.
+    ///
+    /// The event is not marked as live and has no note length, offset, detuning or note off
+    /// velocity.
+    pub fn simple(data: [u8; 3]) -> MidiEvent {
+        MidiEvent {
+            data,
+            delta_frames: 0,
+            live: false,
+            note_length: None,
+            note_offset: None,
+            detune: 0,
+            note_off_velocity: 0,
+            raw_flags: 0,
+        }
+    }
+}
+
+impl From<[u8; 3]> for MidiEvent {
+    fn from(data: [u8; 3]) -> MidiEvent {
+        MidiEvent::simple(data)
+    }
+}
+
+impl From<(u8, u8, u8)> for MidiEvent {
+    fn from((status, data1, data2): (u8, u8, u8)) -> MidiEvent {
+        MidiEvent::simple([status, data1, data2])
+    }
 }
 
 /// A system exclusive event.
@@ -77,6 +114,9 @@ pub struct SysExEvent<'a> {
     pub delta_frames: i32,
 }
 
+/// Largest SysEx payload accepted from a raw event. Larger sizes are taken to be corrupt.
+const MAX_SYSEX_SIZE: usize = 1 << 24;
+
 impl<'a> Event<'a> {
     /// Creates a high-level event from the given low-level API event.
     ///
@@ -85,6 +125,8 @@ impl<'a> Event<'a> {
     /// You must ensure that the given pointer refers to a valid event of the correct event type.
     /// For example, if the event type is [`api::EventType::SysEx`], it should point to a
     /// [`SysExEvent`]. In case of a [`SysExEvent`], `system_data` and `data_size` must be correct.
+    /// A null `system_data` or a negative or implausibly large `data_size` results in an empty
+    /// payload.
     pub unsafe fn from_raw_event(event: *const api::Event) -> Event<'a> {
         use api::EventType::*;
         let event = &*event;
@@ -102,7 +144,7 @@ impl<'a> Event<'a> {
                 } else {
                     None
                 };
-                let flags = api::MidiEventFlags::from_bits(event.flags).unwrap();
+                let flags = api::MidiEventFlags::from_bits_truncate(event.flags);
 
                 Event::Midi(MidiEvent {
                     data: event.midi_data,
@@ -112,6 +154,7 @@ impl<'a> Event<'a> {
                     note_offset: offset,
                     detune: event.detune,
                     note_off_velocity: event.note_off_velocity,
+                    raw_flags: event.flags,
                 })
             }
 
@@ -121,7 +164,15 @@ impl<'a> Event<'a> {
                     // event_type refers to a `SysEx` type.
                     #[allow(clippy::cast_ptr_alignment)]
                     let event: &api::SysExEvent = &*(event as *const api::Event as *const api::SysExEvent);
-                    slice::from_raw_parts(event.system_data, event.data_size as usize)
+                    match usize::try_from(event.data_size) {
+                        Ok(size) if size <= MAX_SYSEX_SIZE && !event.system_data.is_null() => {
+                            slice::from_raw_parts(event.system_data, size)
+                        }
+                        _ => {
+                            warn!("VST: Ignoring SysEx payload with invalid size {}", event.data_size);
+                            &[]
+                        }
+                    }
                 },
 
                 delta_frames: event.delta_frames,
@@ -130,4 +181,129 @@ impl<'a> Event<'a> {
             _ => Event::Deprecated(*event),
         }
     }
+
+    /// Number of samples into the current processing block that this event occurs on, whatever
+    /// the type of event.
+    pub fn delta_frames(&self) -> i32 {
+        match self {
+            Event::Midi(event) => event.delta_frames,
+            Event::SysEx(event) => event.delta_frames,
+            Event::Deprecated(event) => event.delta_frames,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+
+    use crate::api;
+    use crate::buffer::{PlaceholderEvent, WriteIntoPlaceholder};
+    use crate::event::{Event, MidiEvent, SysExEvent};
+
+    #[test]
+    fn simple_midi_event() {
+        let event = MidiEvent::simple([0x90, 60, 100]);
+
+        let mut placeholder: PlaceholderEvent = unsafe { mem::zeroed() };
+        event.write_into(&mut placeholder);
+        let raw = unsafe { &*(&placeholder as *const PlaceholderEvent as *const api::MidiEvent) };
+
+        assert!(matches!(raw.event_type, api::EventType::Midi));
+        assert_eq!(raw.byte_size, mem::size_of::<api::MidiEvent>() as i32);
+        assert_eq!(raw.midi_data, [0x90, 60, 100]);
+        assert_eq!(raw.delta_frames, 0);
+        assert_eq!(raw.flags, 0);
+        assert_eq!(raw.note_length, 0);
+        assert_eq!(raw.note_offset, 0);
+        assert_eq!(raw.detune, 0);
+        assert_eq!(raw.note_off_velocity, 0);
+    }
+
+    #[test]
+    fn midi_event_from_bytes() {
+        let from_array = MidiEvent::from([0x80, 64, 0]);
+        let from_tuple: MidiEvent = (0x80, 64, 0).into();
+
+        for event in [from_array, from_tuple].iter() {
+            assert_eq!(event.data, [0x80, 64, 0]);
+            assert_eq!(event.delta_frames, 0);
+            assert!(!event.live);
+            assert_eq!(event.note_length, None);
+            assert_eq!(event.note_offset, None);
+        }
+    }
+
+    #[test]
+    fn midi_event_raw_flags() {
+        const HOST_FLAG: i32 = 1 << 8;
+
+        let mut placeholder: PlaceholderEvent = unsafe { mem::zeroed() };
+        let mut event = MidiEvent::simple([0x90, 60, 100]);
+        event.live = true;
+        event.raw_flags = HOST_FLAG;
+        event.write_into(&mut placeholder);
+
+        let raw = &placeholder as *const PlaceholderEvent as *const api::Event;
+        let mut event = match unsafe { Event::from_raw_event(raw) } {
+            Event::Midi(event) => event,
+            _ => panic!("Expected a midi event"),
+        };
+        assert!(event.live);
+        assert_eq!(event.raw_flags, HOST_FLAG | api::MidiEventFlags::REALTIME_EVENT.bits());
+
+        event.live = false;
+        event.write_into(&mut placeholder);
+        let raw = unsafe { &*(&placeholder as *const PlaceholderEvent as *const api::MidiEvent) };
+        assert_eq!(raw.flags, HOST_FLAG);
+    }
+
+    #[test]
+    fn delta_frames() {
+        let midi = Event::Midi(MidiEvent {
+            delta_frames: 12,
+            ..MidiEvent::simple([0x90, 60, 100])
+        });
+        let sysex = Event::SysEx(SysExEvent {
+            payload: &[0xF0, 0xF7],
+            delta_frames: 34,
+        });
+        let deprecated = Event::Deprecated(api::Event {
+            event_type: api::EventType::_Trigger,
+            byte_size: mem::size_of::<api::Event>() as i32,
+            delta_frames: 56,
+            _flags: 0,
+            _reserved: [0; 16],
+        });
+
+        assert_eq!(midi.delta_frames(), 12);
+        assert_eq!(sysex.delta_frames(), 34);
+        assert_eq!(deprecated.delta_frames(), 56);
+    }
+
+    #[test]
+    fn malformed_sysex_event() {
+        let mut data = [0xF0, 0x7E, 0xF7];
+        let raw = |system_data: *mut u8, data_size: i32| api::SysExEvent {
+            event_type: api::EventType::SysEx,
+            byte_size: mem::size_of::<api::SysExEvent>() as i32,
+            delta_frames: 0,
+            _flags: 0,
+            data_size,
+            _reserved1: 0,
+            system_data,
+            _reserved2: 0,
+        };
+        let payload = |event: &api::SysExEvent| match unsafe {
+            Event::from_raw_event(event as *const api::SysExEvent as *const api::Event)
+        } {
+            Event::SysEx(event) => event.payload.to_vec(),
+            _ => panic!("Expected a SysEx event"),
+        };
+
+        assert_eq!(payload(&raw(data.as_mut_ptr(), 3)), data);
+        assert!(payload(&raw(data.as_mut_ptr(), -1)).is_empty());
+        assert!(payload(&raw(data.as_mut_ptr(), i32::MAX)).is_empty());
+        assert!(payload(&raw(std::ptr::null_mut(), 3)).is_empty());
+    }
 }
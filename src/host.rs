@@ -4,21 +4,25 @@ use num_enum::{IntoPrimitive, TryFromPrimitive};
 use num_traits::Float;
 
 use libloading::Library;
-use std::cell::UnsafeCell;
+use std::cell::{Cell, UnsafeCell};
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::error::Error;
 use std::ffi::CString;
+use std::marker::PhantomData;
 use std::mem::MaybeUninit;
+use std::ops::{Deref, DerefMut};
 use std::os::raw::c_void;
 use std::path::Path;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
 use std::{fmt, ptr, slice};
 
 use crate::{
-    api::{self, consts::*, AEffect, PluginFlags, PluginMain, Supported, TimeInfo},
-    buffer::AudioBuffer,
+    api::{self, consts::*, AEffect, PluginFlags, PluginMain, Supported, TailSize, TimeInfo},
+    buffer::{AudioBuffer, SendEventBuffer, WriteIntoPlaceholder},
     channels::ChannelInfo,
-    editor::{Editor, Rect},
+    editor::{Editor, EditorPosition, EditorSize, KeyCode, KnobMode, Rect},
     interfaces,
     plugin::{self, Category, HostCallback, Info, Plugin, PluginParameters},
 };
@@ -192,6 +196,9 @@ pub enum OpCode {
 #[allow(unused_variables)]
 pub trait Host {
     /// Automate a parameter; the value has been changed.
+    ///
+    /// Parameter values are normalized to `0.0..=1.0`, but plugins are not guaranteed to stay
+    /// within that range, so hosts which store automation should validate the value.
     fn automate(&self, index: i32, value: f32) {}
 
     /// Signal that automation of a parameter started (the knob has been touched / mouse button down).
@@ -203,9 +210,9 @@ pub trait Host {
     /// Get the plugin ID of the currently loading plugin.
     ///
     /// This is only useful for shell plugins where this value will change the plugin returned.
-    /// `TODO: implement shell plugins`
+    /// While a plugin is loaded through `PluginLoader::instance_with_id`, the given ID is
+    /// reported instead.
     fn get_plugin_id(&self) -> i32 {
-        // TODO: Handle this properly
         0
     }
 
@@ -225,6 +232,8 @@ pub trait Host {
     fn process_events(&self, events: &api::Events) {}
 
     /// Get time information.
+    ///
+    /// Plugins may call this many times per block, see `CachedTimeInfo` to avoid recomputing it.
     fn get_time_info(&self, mask: i32) -> Option<TimeInfo> {
         None
     }
@@ -234,12 +243,92 @@ pub trait Host {
         0
     }
 
+    /// Get the current sample rate in Hz, or `0.0` if unknown.
+    fn get_sample_rate(&self) -> f32 {
+        0.0
+    }
+
     /// Refresh UI after the plugin's parameters changed.
     ///
     /// Note: some hosts will call some `PluginParameters` methods from within the `update_display`
     /// call, including `get_parameter`, `get_parameter_label`, `get_parameter_name`
     /// and `get_parameter_text`.
     fn update_display(&self) {}
+
+    /// Resize the plugin's editor window to `width` by `height` pixels.
+    ///
+    /// Called by plugins with resizable editors. Return `true` if the window was resized, after
+    /// which the host can expect `Editor::size` of the plugin to report the new size.
+    fn size_window(&self, width: i32, height: i32) -> bool {
+        false
+    }
+
+    /// Get the position of the plugin in the host's plugin chain as `(index, total)`, e.g.
+    /// `(2, 3)` for the last of three plugins.
+    ///
+    /// This is not part of the VST API, so it is passed through a vendor specific opcode with
+    /// `api::consts::CHAIN_POSITION_VENDOR_INDEX`. Hosts which don't implement it return `None`.
+    fn get_plugin_chain_position(&self) -> Option<(usize, usize)> {
+        None
+    }
+}
+
+/// Memoizes the time information of the current processing block for `Host::get_time_info`.
+///
+/// Plugins may ask for the time information several times per block with different masks. With
+/// this helper, the host computes it once per sample position, and answers repeated queries from
+/// the cache. The cached value is used for all masks, so it should contain all fields the host
+/// supports.
+///
+/// ```
+/// # use std::cell::Cell;
+/// # use vst::api::TimeInfo;
+/// # use vst::host::{CachedTimeInfo, Host};
+/// struct MyHost {
+///     sample_pos: Cell<f64>,
+///     time_info: CachedTimeInfo,
+/// }
+///
+/// impl Host for MyHost {
+///     fn get_time_info(&self, _mask: i32) -> Option<TimeInfo> {
+///         let sample_pos = self.sample_pos.get();
+///         self.time_info.get_or_compute(sample_pos, || {
+///             Some(TimeInfo {
+///                 sample_pos,
+///                 sample_rate: 44100.0,
+///                 ..Default::default()
+///             })
+///         })
+///     }
+/// }
+/// ```
+#[derive(Default)]
+pub struct CachedTimeInfo {
+    cached: Cell<Option<(f64, Option<TimeInfo>)>>,
+}
+
+impl CachedTimeInfo {
+    /// Create an empty cache.
+    pub fn new() -> CachedTimeInfo {
+        Default::default()
+    }
+
+    /// Get the time information for `sample_pos`, calling `compute` only if it is not cached.
+    pub fn get_or_compute<F: FnOnce() -> Option<TimeInfo>>(&self, sample_pos: f64, compute: F) -> Option<TimeInfo> {
+        match self.cached.get() {
+            Some((cached_pos, time_info)) if cached_pos == sample_pos => time_info,
+            _ => {
+                let time_info = compute();
+                self.cached.set(Some((sample_pos, time_info)));
+                time_info
+            }
+        }
+    }
+
+    /// Clear the cache, e.g. when the transport is moved without changing the sample position.
+    pub fn invalidate(&self) {
+        self.cached.set(None);
+    }
 }
 
 /// All possible errors that can occur when loading a VST plugin.
@@ -276,6 +365,117 @@ impl fmt::Display for PluginLoadError {
 
 impl Error for PluginLoadError {}
 
+/// All possible errors that can occur when loading a `.fxp` or `.fxb` file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FxFileError {
+    /// The data ends before the end of the file structure.
+    Truncated,
+
+    /// The data is not a preset file (for `load_fxp`) or bank file (for `load_fxb`).
+    InvalidMagic,
+
+    /// The file was written for a plugin with a different unique ID.
+    WrongPlugin,
+
+    /// The file stores parameter values, but the plugin uses chunks, or the other way around.
+    FormatMismatch,
+}
+
+impl fmt::Display for FxFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::FxFileError::*;
+        let description = match self {
+            Truncated => "The file data is truncated",
+            InvalidMagic => "The file is not a VST preset or bank file",
+            WrongPlugin => "The file was saved by a different plugin",
+            FormatMismatch => "The file format does not match the plugin's preset chunk support",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+impl Error for FxFileError {}
+
+/// Magic numbers of the Steinberg `.fxp`/`.fxb` file format.
+mod fx_magic {
+    pub const CHUNK: &[u8; 4] = b"CcnK";
+    pub const PRESET_PARAMS: &[u8; 4] = b"FxCk";
+    pub const PRESET_CHUNK: &[u8; 4] = b"FPCh";
+    pub const BANK_PARAMS: &[u8; 4] = b"FxBk";
+    pub const BANK_CHUNK: &[u8; 4] = b"FBCh";
+}
+
+/// Length of the preset name stored in a `.fxp` file.
+const FX_PRESET_NAME_LEN: usize = 28;
+
+/// Encode a preset name for a `.fxp` file.
+///
+/// The name is stored as UTF-8, cut at a character boundary to leave room for the null terminator.
+fn fx_preset_name(name: &str) -> [u8; FX_PRESET_NAME_LEN] {
+    let mut len = name.len().min(FX_PRESET_NAME_LEN - 1);
+    while !name.is_char_boundary(len) {
+        len -= 1;
+    }
+    let mut bytes = [0u8; FX_PRESET_NAME_LEN];
+    bytes[..len].copy_from_slice(&name.as_bytes()[..len]);
+    bytes
+}
+
+/// Length of the reserved space in the header of a `.fxb` file.
+const FX_BANK_RESERVED_LEN: usize = 128;
+
+/// Reads the big-endian fields of a `.fxp`/`.fxb` file.
+struct FxReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> FxReader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], FxFileError> {
+        if self.data.len() < len {
+            return Err(FxFileError::Truncated);
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn i32(&mut self) -> Result<i32, FxFileError> {
+        let bytes = self.bytes(4)?;
+        Ok(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn f32(&mut self) -> Result<f32, FxFileError> {
+        Ok(f32::from_bits(self.i32()? as u32))
+    }
+
+    /// Read the common header and return the format magic.
+    fn header(&mut self, unique_id: i32) -> Result<&'a [u8], FxFileError> {
+        if self.bytes(4)? != fx_magic::CHUNK {
+            return Err(FxFileError::InvalidMagic);
+        }
+        let _byte_size = self.i32()?;
+        let magic = self.bytes(4)?;
+        let _format_version = self.i32()?;
+        if self.i32()? != unique_id {
+            return Err(FxFileError::WrongPlugin);
+        }
+        let _plugin_version = self.i32()?;
+        Ok(magic)
+    }
+}
+
+/// A preset read from a `.fxp` file, or from a bank in a `.fxb` file.
+struct FxPreset<'a> {
+    name: String,
+    data: FxPresetData<'a>,
+}
+
+/// The contents of an `FxPreset`.
+enum FxPresetData<'a> {
+    Chunk(&'a [u8]),
+    Parameters(Vec<f32>),
+}
+
 /// Wrapper for an externally loaded VST plugin.
 ///
 /// The only functionality this struct provides is loading plugins, which can be done via the
@@ -287,14 +487,29 @@ pub struct PluginLoader<T: Host> {
 }
 
 /// An instance of an externally loaded VST plugin.
+///
+/// A `PluginInstance` is `Send`, so it can be moved to a dedicated audio thread after loading. It
+/// is not `Sync`: the VST API only allows the processing methods of a plugin to be called from
+/// one thread at a time. Parameters can be shared with other threads through
+/// `get_parameter_object`.
 #[allow(dead_code)] // To keep `lib` around.
 pub struct PluginInstance {
     params: Arc<PluginParametersInstance>,
+    /// Keeps the plugin library loaded until the plugin has been shut down in `drop`.
     lib: Arc<Library>,
     info: Info,
-    is_editor_active: bool,
+    api_version: i32,
+    /// The editor handed out by `get_editor`, shared with the returned box.
+    editor: Option<EditorInstance>,
+    is_resumed: bool,
+    _effect: PhantomData<*mut AEffect>,
 }
 
+// SAFETY: The plugin does not rely on being called from the thread it was created on, and as
+// `PluginInstance` is not `Sync`, its dispatcher and processing functions are never called
+// concurrently through it.
+unsafe impl Send for PluginInstance {}
+
 struct PluginParametersInstance {
     effect: UnsafeCell<*mut AEffect>,
 }
@@ -303,54 +518,44 @@ unsafe impl Send for PluginParametersInstance {}
 unsafe impl Sync for PluginParametersInstance {}
 
 impl Drop for PluginInstance {
+    /// Shut down the plugin.
+    ///
+    /// `Drop::drop` runs before the fields are dropped, so the instance's reference to the
+    /// library keeps it loaded while `Shutdown` is dispatched, even if the `PluginLoader` and all
+    /// other instances are already gone.
     fn drop(&mut self) {
         self.dispatch(plugin::OpCode::Shutdown, 0, 0, ptr::null_mut(), 0.0);
     }
 }
 
 /// The editor of an externally loaded VST plugin.
+///
+/// All editor state other than whether it is open lives in the plugin, so clones of an
+/// `EditorInstance` share that flag and otherwise just dispatch to the same plugin.
+#[derive(Clone)]
 struct EditorInstance {
     params: Arc<PluginParametersInstance>,
-    is_open: bool,
-}
-
-impl EditorInstance {
-    fn get_rect(&self) -> Option<Rect> {
-        let mut rect: *mut Rect = std::ptr::null_mut();
-        let rect_ptr: *mut *mut Rect = &mut rect;
-
-        let result = self
-            .params
-            .dispatch(plugin::OpCode::EditorGetRect, 0, 0, rect_ptr as *mut c_void, 0.0);
-
-        if result == 0 || rect.is_null() {
-            return None;
-        }
-        Some(unsafe { *rect }) // TODO: Who owns rect? Who should free the memory?
-    }
+    is_open: Arc<AtomicBool>,
 }
 
 impl Editor for EditorInstance {
     fn size(&self) -> (i32, i32) {
-        // Assuming coordinate origins from top-left
-        match self.get_rect() {
-            None => (0, 0),
-            Some(rect) => ((rect.right - rect.left) as i32, (rect.bottom - rect.top) as i32),
-        }
+        self.params.editor_size().unwrap_or_default().into()
     }
 
     fn position(&self) -> (i32, i32) {
-        // Assuming coordinate origins from top-left
-        match self.get_rect() {
-            None => (0, 0),
-            Some(rect) => (rect.left as i32, rect.top as i32),
-        }
+        self.params.editor_position().unwrap_or_default().into()
+    }
+
+    fn idle(&mut self) {
+        self.params
+            .dispatch(plugin::OpCode::EditorIdle, 0, 0, ptr::null_mut(), 0.0);
     }
 
     fn close(&mut self) {
         self.params
             .dispatch(plugin::OpCode::EditorClose, 0, 0, ptr::null_mut(), 0.0);
-        self.is_open = false;
+        self.is_open.store(false, Ordering::Relaxed);
     }
 
     fn open(&mut self, parent: *mut c_void) -> bool {
@@ -358,14 +563,14 @@ impl Editor for EditorInstance {
 
         let opened = result == 1;
         if opened {
-            self.is_open = true;
+            self.is_open.store(true, Ordering::Relaxed);
         }
 
         opened
     }
 
     fn is_open(&mut self) -> bool {
-        self.is_open
+        self.is_open.load(Ordering::Relaxed)
     }
 }
 
@@ -435,8 +640,27 @@ impl<T: Host> PluginLoader<T> {
 
     /// Call the VST entry point and retrieve a (possibly null) pointer.
     unsafe fn call_main(&mut self) -> *mut AEffect {
-        LOAD_POINTER = Box::into_raw(Box::new(Arc::clone(&self.host))) as *mut c_void;
-        (self.main)(callback_wrapper::<T>)
+        let host = Box::into_raw(Box::new(Arc::clone(&self.host)));
+        LOAD_POINTER.with(|load_pointer| load_pointer.set(host as *mut c_void));
+
+        let effect = (self.main)(callback_wrapper::<T>);
+
+        LOAD_POINTER.with(|load_pointer| load_pointer.set(ptr::null_mut()));
+        drop(Box::from_raw(host));
+
+        effect
+    }
+
+    /// Try to create an instance of the sub-plugin with the given unique ID from a shell plugin.
+    ///
+    /// While the plugin is created, `id` is reported to the plugin as the current plugin ID
+    /// instead of the value returned by `Host::get_plugin_id`. Otherwise this is the same as
+    /// [`instance`](#method.instance).
+    pub fn instance_with_id(&mut self, id: i32) -> Result<PluginInstance, PluginLoadError> {
+        LOAD_ID.with(|load_id| load_id.set(Some(id)));
+        let instance = self.instance();
+        LOAD_ID.with(|load_id| load_id.set(None));
+        instance
     }
 
     /// Try to create an instance of this VST plugin.
@@ -459,7 +683,7 @@ impl<T: Host> PluginLoader<T> {
 
         let instance = PluginInstance::new(effect, Arc::clone(&self.lib));
 
-        let api_ver = instance.dispatch(plugin::OpCode::GetApiVersion, 0, 0, ptr::null_mut(), 0.0);
+        let api_ver = instance.api_version();
         if api_ver >= 2400 {
             Ok(instance)
         } else {
@@ -469,6 +693,37 @@ impl<T: Host> PluginLoader<T> {
     }
 }
 
+/// Load the plugin at the given path and create an instance of it.
+///
+/// This is the same as calling [`PluginLoader::load`] followed by
+/// [`PluginLoader::instance`]. The loader is returned too, so more instances of the plugin can be
+/// created later.
+///
+/// Every `PluginInstance` keeps a reference to the plugin library and to the host, so the library
+/// stays loaded and the host stays alive until the last instance is dropped, even if the loader is
+/// dropped first.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::path::Path;
+/// # use std::sync::{Arc, Mutex};
+/// # use vst::host::{self, Host};
+/// # struct MyHost;
+/// # impl Host for MyHost {}
+/// let host = Arc::new(Mutex::new(MyHost));
+/// let (_loader, instance) = host::load_plugin(Path::new("plugin.so"), host).unwrap();
+/// // ...
+/// ```
+pub fn load_plugin<T: Host>(
+    path: &Path,
+    host: Arc<Mutex<T>>,
+) -> Result<(PluginLoader<T>, PluginInstance), PluginLoadError> {
+    let mut loader = PluginLoader::load(path, host)?;
+    let instance = loader.instance()?;
+    Ok((loader, instance))
+}
+
 impl PluginInstance {
     fn new(effect: *mut AEffect, lib: Arc<Library>) -> PluginInstance {
         use plugin::OpCode as op;
@@ -480,7 +735,10 @@ impl PluginInstance {
             params,
             lib,
             info: Default::default(),
-            is_editor_active: false,
+            api_version: 0,
+            editor: None,
+            is_resumed: false,
+            _effect: PhantomData,
         };
 
         unsafe {
@@ -501,6 +759,10 @@ impl PluginInstance {
 
                 unique_id: effect.uniqueId,
                 version: effect.version,
+                vendor_version: match plug.opcode(op::GetVendorVersion) as i32 {
+                    0 => effect.version,
+                    vendor_version => vendor_version,
+                },
 
                 category: Category::try_from(plug.opcode(op::GetCategory)).unwrap_or(Category::Unknown),
 
@@ -509,11 +771,395 @@ impl PluginInstance {
                 preset_chunks: flags.intersects(PluginFlags::PROGRAM_CHUNKS),
                 f64_precision: flags.intersects(PluginFlags::CAN_DOUBLE_REPLACING),
                 silent_when_stopped: flags.intersects(PluginFlags::NO_SOUND_IN_STOP),
+
+                // Capabilities are not queried on load, use `can_do` instead.
+                receive_midi: false,
+                send_midi: false,
+                receive_sysex: false,
+                receive_time_info: false,
+                offline: false,
+                midi_program_names: false,
+                bypass: false,
+                // Whether the editor is created lazily is not visible to the host.
+                lazy_editor: false,
             };
+            plug.api_version = plug.opcode(op::GetApiVersion) as i32;
         }
 
         plug
     }
+
+    /// Get the VST API version reported by the plugin, e.g. `2400` for VST 2.4.
+    ///
+    /// The version is queried once when the plugin is loaded.
+    pub fn api_version(&self) -> i32 {
+        self.api_version
+    }
+
+    /// Get the number of presets of the plugin.
+    pub fn preset_count(&self) -> i32 {
+        self.info.presets
+    }
+
+    /// Get the names of all presets, e.g. for a preset browser.
+    ///
+    /// The names are read by index, without changing the current preset.
+    pub fn preset_names(&self) -> Vec<String> {
+        (0..self.info.presets)
+            .map(|preset| self.params.get_preset_name(preset))
+            .collect()
+    }
+
+    /// Get the number of audio inputs, as read on load or by the last call to `refresh_io`.
+    pub fn num_inputs(&self) -> i32 {
+        self.info.inputs
+    }
+
+    /// Get the number of audio outputs, as read on load or by the last call to `refresh_io`.
+    pub fn num_outputs(&self) -> i32 {
+        self.info.outputs
+    }
+
+    /// Re-read the number of inputs and outputs and the initial delay from the plugin.
+    ///
+    /// These are read once when the plugin is loaded. Plugins which change them send `IOChanged`
+    /// to the host callback, upon which the host should call this method, after which
+    /// `get_info`, `num_inputs` and `num_outputs` reflect the new values.
+    pub fn refresh_io(&mut self) {
+        let effect = unsafe { &*self.get_effect() };
+        self.info.inputs = effect.numInputs;
+        self.info.outputs = effect.numOutputs;
+        self.info.initial_delay = effect.initialDelay;
+    }
+
+    /// Set a parameter as if the user had moved its control, reporting the change to `host` as
+    /// an edit gesture.
+    ///
+    /// VST has no opcodes for the host to send gestures to a plugin, so automation recording is
+    /// up to the host. This calls `host.begin_edit`, sets the parameter, calls `host.automate`
+    /// with the new value and finally `host.end_edit`, which is the same sequence the host
+    /// receives when the plugin's editor changes a parameter. Passing the host's own `Host`
+    /// implementation lets scripted changes be recorded like any other.
+    pub fn set_parameter_as_gesture(&self, host: &dyn Host, index: i32, value: f32) {
+        host.begin_edit(index);
+        self.params.set_parameter(index, value);
+        host.automate(index, value);
+        host.end_edit(index);
+    }
+
+    /// Get a copy of the bank or preset chunk of the plugin.
+    ///
+    /// Returns an empty vector if the plugin does not provide the chunk.
+    pub fn get_chunk(&self, kind: ChunkKind) -> Vec<u8> {
+        self.read_chunk(kind)
+    }
+
+    /// Load a bank or preset chunk, as returned by `get_chunk`, into the plugin.
+    pub fn set_chunk(&self, kind: ChunkKind, data: &[u8]) {
+        self.write_chunk(kind, data)
+    }
+
+    /// Get the length of the tail the plugin produces after its input has stopped.
+    ///
+    /// Unlike `Plugin::get_tail_size`, which returns the raw value, this distinguishes a plugin
+    /// which does not report a tail size from one which has no tail.
+    pub fn tail_samples(&self) -> TailSize {
+        TailSize::from_raw(self.opcode(plugin::OpCode::GetTailSize))
+    }
+
+    /// Get the editor handed out by `get_editor`, or `None` if it has not been requested yet.
+    ///
+    /// The box returned by `get_editor` and the editor returned here are two handles to the same
+    /// plugin editor: they dispatch to the same plugin and share whether the editor is open. The
+    /// box can be owned by the host's GUI code, while the instance uses this handle, e.g. to
+    /// forward idle calls. Both stay valid until the instance is dropped.
+    pub fn editor_mut(&mut self) -> Option<&mut dyn Editor> {
+        self.editor.as_mut().map(|editor| editor as &mut dyn Editor)
+    }
+
+    /// Send `events` to the plugin and then process `buffer`.
+    ///
+    /// Plugins expect the events for a block to arrive before the block is processed, which this
+    /// guarantees. The events must stay valid until this method returns.
+    pub fn process_with_events(&mut self, buffer: &mut AudioBuffer<f32>, events: &api::Events) {
+        self.process_events(events);
+        self.process(buffer);
+    }
+
+    /// Check that the plugin processes audio, e.g. when scanning a plugin directory.
+    ///
+    /// The plugin is suspended if needed, set to a sample rate of 44.1 kHz and a block size of
+    /// 512 samples, resumed, given one block of silence to process and suspended again. The
+    /// outputs are then checked to be finite.
+    ///
+    /// Panics can not be detected: a plugin built with this crate catches them itself and outputs
+    /// silence, and a panic in any other plugin aborts the process.
+    ///
+    /// This is meant to be called right after loading the plugin, as it leaves the plugin
+    /// suspended and with the sample rate and block size above.
+    pub fn smoke_test(&mut self) -> Result<(), ProcessError> {
+        const SAMPLE_RATE: f32 = 44100.0;
+        const BLOCK_SIZE: usize = 512;
+
+        let inputs = vec![vec![0.0f32; BLOCK_SIZE]; self.info.inputs.max(0) as usize];
+        let mut outputs = vec![vec![0.0f32; BLOCK_SIZE]; self.info.outputs.max(0) as usize];
+        let mut host_buffer = HostBuffer::new(inputs.len(), outputs.len());
+
+        self.suspend();
+        self.set_sample_rate(SAMPLE_RATE);
+        self.set_block_size(BLOCK_SIZE as i64);
+        self.resume();
+        self.process(&mut host_buffer.bind(&inputs, &mut outputs));
+        self.suspend();
+
+        match outputs
+            .iter()
+            .position(|output| output.iter().any(|sample| !sample.is_finite()))
+        {
+            Some(channel) => Err(ProcessError::NonFiniteOutput { channel }),
+            None => Ok(()),
+        }
+    }
+
+    /// Save the current preset in the Steinberg `.fxp` file format.
+    ///
+    /// If the plugin uses preset chunks, the chunk is stored, otherwise all parameter values.
+    pub fn save_fxp(&self) -> Vec<u8> {
+        let name = self.read_string(plugin::OpCode::GetCurrentPresetName, MAX_PRESET_NAME_LEN);
+        self.fx_preset(&name, self.info.preset_chunks)
+    }
+
+    /// Save all presets in the Steinberg `.fxb` file format.
+    ///
+    /// If the plugin uses preset chunks, the bank chunk is stored. Otherwise every preset is
+    /// selected in turn to store its parameter values, after which the current preset is restored.
+    pub fn save_fxb(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&self.info.presets.to_be_bytes());
+        body.extend_from_slice(&[0; FX_BANK_RESERVED_LEN]);
+
+        let magic = if self.info.preset_chunks {
+            let chunk = self.params.get_bank_data();
+            body.extend_from_slice(&(chunk.len() as i32).to_be_bytes());
+            body.extend_from_slice(&chunk);
+            fx_magic::BANK_CHUNK
+        } else {
+            let current = self.params.get_preset_num();
+            for preset in 0..self.info.presets {
+                self.params.change_preset(preset);
+                body.extend_from_slice(&self.fx_preset(&self.params.get_preset_name(preset), false));
+            }
+            self.params.change_preset(current);
+            fx_magic::BANK_PARAMS
+        };
+
+        self.fx_file(magic, &body)
+    }
+
+    /// Load a preset saved in the Steinberg `.fxp` file format into the current preset.
+    pub fn load_fxp(&self, data: &[u8]) -> Result<(), FxFileError> {
+        let mut reader = FxReader { data };
+        let magic = reader.header(self.info.unique_id)?;
+        if magic != fx_magic::PRESET_PARAMS && magic != fx_magic::PRESET_CHUNK {
+            return Err(FxFileError::InvalidMagic);
+        }
+        let preset = self.read_fx_preset(magic, &mut reader)?;
+        self.apply_fx_preset(preset);
+        Ok(())
+    }
+
+    /// Load all presets from a file in the Steinberg `.fxb` file format.
+    ///
+    /// The whole file is read before any preset is changed, so nothing is loaded if it is invalid.
+    pub fn load_fxb(&self, data: &[u8]) -> Result<(), FxFileError> {
+        let mut reader = FxReader { data };
+        let magic = reader.header(self.info.unique_id)?;
+        let is_chunk = match magic {
+            m if m == fx_magic::BANK_PARAMS => false,
+            m if m == fx_magic::BANK_CHUNK => true,
+            _ => return Err(FxFileError::InvalidMagic),
+        };
+        if is_chunk != self.info.preset_chunks {
+            return Err(FxFileError::FormatMismatch);
+        }
+
+        let presets = reader.i32()?;
+        reader.bytes(FX_BANK_RESERVED_LEN)?;
+
+        if is_chunk {
+            let len = reader.i32()?;
+            self.params.load_bank_data(reader.bytes(len.max(0) as usize)?);
+        } else {
+            let bank = (0..presets.min(self.info.presets))
+                .map(|_| {
+                    let magic = reader.header(self.info.unique_id)?;
+                    self.read_fx_preset(magic, &mut reader)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let current = self.params.get_preset_num();
+            for (index, preset) in bank.into_iter().enumerate() {
+                self.params.change_preset(index as i32);
+                self.apply_fx_preset(preset);
+            }
+            self.params.change_preset(current);
+        }
+
+        Ok(())
+    }
+
+    /// Build a `.fxp` file of the current preset.
+    fn fx_preset(&self, name: &str, chunk: bool) -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&self.info.parameters.to_be_bytes());
+
+        body.extend_from_slice(&fx_preset_name(name));
+
+        let magic = if chunk {
+            let chunk = self.params.get_preset_data();
+            body.extend_from_slice(&(chunk.len() as i32).to_be_bytes());
+            body.extend_from_slice(&chunk);
+            fx_magic::PRESET_CHUNK
+        } else {
+            for index in 0..self.info.parameters {
+                body.extend_from_slice(&self.params.get_parameter(index).to_bits().to_be_bytes());
+            }
+            fx_magic::PRESET_PARAMS
+        };
+
+        self.fx_file(magic, &body)
+    }
+
+    /// Prefix `body` with the header common to `.fxp` and `.fxb` files.
+    fn fx_file(&self, magic: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        // The byte size counts everything after the size field itself.
+        let byte_size = 16 + body.len() as i32;
+
+        let mut data = Vec::with_capacity(24 + body.len());
+        data.extend_from_slice(fx_magic::CHUNK);
+        data.extend_from_slice(&byte_size.to_be_bytes());
+        data.extend_from_slice(magic);
+        data.extend_from_slice(&1i32.to_be_bytes());
+        data.extend_from_slice(&self.info.unique_id.to_be_bytes());
+        data.extend_from_slice(&self.info.version.to_be_bytes());
+        data.extend_from_slice(body);
+        data
+    }
+
+    /// Read the body of a `.fxp` file.
+    fn read_fx_preset<'a>(&self, magic: &[u8], reader: &mut FxReader<'a>) -> Result<FxPreset<'a>, FxFileError> {
+        let is_chunk = magic == fx_magic::PRESET_CHUNK;
+        if is_chunk != self.info.preset_chunks {
+            return Err(FxFileError::FormatMismatch);
+        }
+
+        let parameters = reader.i32()?;
+        let name = reader.bytes(FX_PRESET_NAME_LEN)?;
+        let name = name.split(|&byte| byte == 0).next().unwrap_or_default();
+        let name = String::from_utf8_lossy(name).into_owned();
+
+        let data = if is_chunk {
+            let len = reader.i32()?;
+            FxPresetData::Chunk(reader.bytes(len.max(0) as usize)?)
+        } else {
+            let values = (0..parameters).map(|_| reader.f32()).collect::<Result<Vec<_>, _>>()?;
+            FxPresetData::Parameters(values)
+        };
+
+        Ok(FxPreset { name, data })
+    }
+
+    /// Load a preset read by `read_fx_preset` into the current preset.
+    fn apply_fx_preset(&self, preset: FxPreset) {
+        match preset.data {
+            FxPresetData::Chunk(chunk) => self.params.load_preset_data(chunk),
+            FxPresetData::Parameters(values) => {
+                for (index, value) in values
+                    .into_iter()
+                    .enumerate()
+                    .take(self.info.parameters.max(0) as usize)
+                {
+                    self.params.set_parameter(index as i32, value);
+                }
+            }
+        }
+
+        self.params.set_preset_name(preset.name);
+    }
+
+    /// Return whether the plugin is in the *resumed* state.
+    ///
+    /// A plugin starts out *suspended* and is switched between the states with `resume` and
+    /// `suspend`.
+    pub fn is_resumed(&self) -> bool {
+        self.is_resumed
+    }
+
+    /// Tell the plugin editor how its parameter knobs should behave.
+    ///
+    /// Returns `true` if the plugin accepted the knob mode.
+    pub fn set_knob_mode(&self, mode: KnobMode) -> bool {
+        self.dispatch(plugin::OpCode::EditorSetKnobMode, 0, mode.into(), ptr::null_mut(), 0.0) > 0
+    }
+
+    /// Get the value of the parameter at `index`.
+    ///
+    /// Returns `None` without calling into the plugin if `index` is not below the number of
+    /// parameters reported in the plugin's `Info`.
+    pub fn get_parameter_checked(&self, index: i32) -> Option<f32> {
+        if index >= 0 && index < self.info.parameters {
+            Some(self.params.get_parameter(index))
+        } else {
+            None
+        }
+    }
+
+    /// Set the value of the parameter at `index`.
+    ///
+    /// Returns `false` without calling into the plugin if `index` is not below the number of
+    /// parameters reported in the plugin's `Info`.
+    pub fn set_parameter_checked(&self, index: i32, value: f32) -> bool {
+        if index >= 0 && index < self.info.parameters {
+            self.params.set_parameter(index, value);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Get the size of the plugin editor window, or `None` if the plugin has no editor or did not
+    /// report its size.
+    ///
+    /// This can be queried before the editor is opened, e.g. to size the parent window.
+    pub fn editor_size(&self) -> Option<EditorSize> {
+        Dispatch::editor_size(self)
+    }
+
+    /// Get the position of the plugin editor window, or `None` if the plugin has no editor or did
+    /// not report its position.
+    pub fn editor_position(&self) -> Option<EditorPosition> {
+        Dispatch::editor_position(self)
+    }
+
+    /// Forward a key press to the plugin editor.
+    ///
+    /// Returns `true` if the key was used by the plugin.
+    pub fn editor_key_down(&self, key: KeyCode) -> bool {
+        self.send_key(plugin::OpCode::EditorKeyDown, key)
+    }
+
+    /// Forward a key release to the plugin editor.
+    ///
+    /// Returns `true` if the key was used by the plugin.
+    pub fn editor_key_up(&self, key: KeyCode) -> bool {
+        self.send_key(plugin::OpCode::EditorKeyUp, key)
+    }
+
+    fn send_key(&self, opcode: plugin::OpCode, key: KeyCode) -> bool {
+        // The modifier bitmask is passed as the raw bits of `opt`.
+        let modifier = f32::from_bits(key.modifier as u32);
+        self.dispatch(opcode, key.character as i32, key.key.into(), ptr::null_mut(), modifier) > 0
+    }
 }
 
 trait Dispatch {
@@ -551,6 +1197,58 @@ trait Dispatch {
             .take_while(|c| *c != '\0')
             .collect()
     }
+
+    /// Get a copy of the bank or preset chunk of the plugin.
+    fn read_chunk(&self, kind: ChunkKind) -> Vec<u8> {
+        // Create a pointer that can be updated from the plugin.
+        let mut ptr: *mut u8 = ptr::null_mut();
+        let len = self.dispatch(
+            plugin::OpCode::GetData,
+            kind.index(),
+            0,
+            &mut ptr as *mut *mut u8 as *mut c_void,
+            0.0,
+        );
+        if ptr.is_null() || len <= 0 {
+            return Vec::new();
+        }
+        let slice = unsafe { slice::from_raw_parts(ptr, len as usize) };
+        slice.to_vec()
+    }
+
+    /// Get the rectangle of the plugin editor, or `None` if the plugin did not report it.
+    fn editor_rect(&self) -> Option<Rect> {
+        let mut rect: *mut Rect = std::ptr::null_mut();
+        let rect_ptr: *mut *mut Rect = &mut rect;
+
+        let result = self.dispatch(plugin::OpCode::EditorGetRect, 0, 0, rect_ptr as *mut c_void, 0.0);
+
+        if result == 0 || rect.is_null() {
+            return None;
+        }
+        Some(unsafe { *rect }) // TODO: Who owns rect? Who should free the memory?
+    }
+
+    /// Get the size of the plugin editor, or `None` if the plugin did not report it.
+    fn editor_size(&self) -> Option<EditorSize> {
+        self.editor_rect().map(EditorSize::from)
+    }
+
+    /// Get the position of the plugin editor, or `None` if the plugin did not report it.
+    fn editor_position(&self) -> Option<EditorPosition> {
+        self.editor_rect().map(EditorPosition::from)
+    }
+
+    /// Load a bank or preset chunk into the plugin.
+    fn write_chunk(&self, kind: ChunkKind, data: &[u8]) {
+        self.dispatch(
+            plugin::OpCode::SetData,
+            kind.index(),
+            data.len() as isize,
+            data.as_ptr() as *mut c_void,
+            0.0,
+        );
+    }
 }
 
 impl Dispatch for PluginInstance {
@@ -587,12 +1285,24 @@ impl Plugin for PluginInstance {
         self.dispatch(plugin::OpCode::SetBlockSize, 0, size as isize, ptr::null_mut(), 0.0);
     }
 
+    /// Transition the plugin into the *resumed* state.
+    ///
+    /// Does nothing if the plugin is already resumed.
     fn resume(&mut self) {
-        self.dispatch(plugin::OpCode::StateChanged, 0, 1, ptr::null_mut(), 0.0);
+        if !self.is_resumed {
+            self.dispatch(plugin::OpCode::StateChanged, 0, 1, ptr::null_mut(), 0.0);
+            self.is_resumed = true;
+        }
     }
 
+    /// Transition the plugin into the *suspended* state.
+    ///
+    /// Does nothing if the plugin is already suspended.
     fn suspend(&mut self) {
-        self.dispatch(plugin::OpCode::StateChanged, 0, 0, ptr::null_mut(), 0.0);
+        if self.is_resumed {
+            self.dispatch(plugin::OpCode::StateChanged, 0, 0, ptr::null_mut(), 0.0);
+            self.is_resumed = false;
+        }
     }
 
     fn vendor_specific(&mut self, index: i32, value: isize, ptr: *mut c_void, opt: f32) -> isize {
@@ -605,6 +1315,10 @@ impl Plugin for PluginInstance {
             .expect("Invalid response received when querying plugin CanDo")
     }
 
+    fn get_vst_version(&self) -> i32 {
+        self.api_version
+    }
+
     fn get_tail_size(&self) -> isize {
         self.opcode(plugin::OpCode::GetTailSize)
     }
@@ -647,6 +1361,14 @@ impl Plugin for PluginInstance {
         self.dispatch(plugin::OpCode::ProcessEvents, 0, 0, events as *const _ as *mut _, 0.0);
     }
 
+    fn get_num_midi_inputs(&self) -> i32 {
+        self.opcode(plugin::OpCode::GetNumMidiInputs) as i32
+    }
+
+    fn get_num_midi_outputs(&self) -> i32 {
+        self.opcode(plugin::OpCode::GetNumMidiOutputs) as i32
+    }
+
     fn get_input_info(&self, input: i32) -> ChannelInfo {
         let mut props: MaybeUninit<api::ChannelProperties> = MaybeUninit::uninit();
         let ptr = props.as_mut_ptr() as *mut c_void;
@@ -669,18 +1391,23 @@ impl Plugin for PluginInstance {
         Arc::clone(&self.params) as Arc<dyn PluginParameters>
     }
 
+    /// Get the plugin's editor.
+    ///
+    /// Only the first call returns an editor. The instance keeps its own handle to that editor,
+    /// which can be reached through [`editor_mut`](#method.editor_mut).
     fn get_editor(&mut self) -> Option<Box<dyn Editor>> {
-        if self.is_editor_active {
+        if self.editor.is_some() {
             // An editor is already active, the caller should be using the active editor instead of
             // requesting for a new one.
             return None;
         }
 
-        self.is_editor_active = true;
-        Some(Box::new(EditorInstance {
+        let editor = EditorInstance {
             params: self.params.clone(),
-            is_open: false,
-        }))
+            is_open: Arc::new(AtomicBool::new(false)),
+        };
+        self.editor = Some(editor.clone());
+        Some(Box::new(editor))
     }
 }
 
@@ -732,54 +1459,98 @@ impl PluginParameters for PluginParametersInstance {
     // TODO: Editor
 
     fn get_preset_data(&self) -> Vec<u8> {
-        // Create a pointer that can be updated from the plugin.
-        let mut ptr: *mut u8 = ptr::null_mut();
-        let len = self.dispatch(
-            plugin::OpCode::GetData,
-            1, /*preset*/
-            0,
-            &mut ptr as *mut *mut u8 as *mut c_void,
-            0.0,
-        );
-        let slice = unsafe { slice::from_raw_parts(ptr, len as usize) };
-        slice.to_vec()
+        self.read_chunk(ChunkKind::Preset)
     }
 
     fn get_bank_data(&self) -> Vec<u8> {
-        // Create a pointer that can be updated from the plugin.
-        let mut ptr: *mut u8 = ptr::null_mut();
-        let len = self.dispatch(
-            plugin::OpCode::GetData,
-            0, /*bank*/
-            0,
-            &mut ptr as *mut *mut u8 as *mut c_void,
-            0.0,
-        );
-        let slice = unsafe { slice::from_raw_parts(ptr, len as usize) };
-        slice.to_vec()
+        self.read_chunk(ChunkKind::Bank)
     }
 
     fn load_preset_data(&self, data: &[u8]) {
-        self.dispatch(
-            plugin::OpCode::SetData,
-            1,
-            data.len() as isize,
-            data.as_ptr() as *mut c_void,
-            0.0,
-        );
+        self.write_chunk(ChunkKind::Preset, data)
     }
 
     fn load_bank_data(&self, data: &[u8]) {
-        self.dispatch(
-            plugin::OpCode::SetData,
-            0,
-            data.len() as isize,
-            data.as_ptr() as *mut c_void,
-            0.0,
-        );
+        self.write_chunk(ChunkKind::Bank, data)
+    }
+}
+
+/// Whether a chunk of plugin state holds the whole bank or the current preset.
+///
+/// Used with `PluginInstance::get_chunk` and `PluginInstance::set_chunk`. Chunks are only
+/// supported by plugins which set `Info::preset_chunks`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChunkKind {
+    /// The state of all presets.
+    Bank,
+
+    /// The state of the current preset.
+    Preset,
+}
+
+impl ChunkKind {
+    /// The `index` passed with `GetData` and `SetData` for this kind of chunk.
+    fn index(self) -> i32 {
+        match self {
+            ChunkKind::Bank => 0,
+            ChunkKind::Preset => 1,
+        }
+    }
+}
+
+/// An error binding sample arrays to a `HostBuffer`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BindError {
+    /// More input arrays were supplied than the `HostBuffer` was created for.
+    TooManyInputs,
+
+    /// More output arrays were supplied than the `HostBuffer` was created for.
+    TooManyOutputs,
+
+    /// The input arrays do not all have the same length.
+    MismatchingInputLengths,
+
+    /// The output arrays do not all have the same length as the other arrays.
+    MismatchingOutputLengths,
+}
+
+impl fmt::Display for BindError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::BindError::*;
+        let description = match self {
+            TooManyInputs => "Too many inputs for HostBuffer",
+            TooManyOutputs => "Too many outputs for HostBuffer",
+            MismatchingInputLengths => "Mismatching lengths of input arrays",
+            MismatchingOutputLengths => "Mismatching lengths of output arrays",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+impl Error for BindError {}
+
+/// An error found by `PluginInstance::smoke_test`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessError {
+    /// The plugin output a NaN or infinite sample on the given output channel.
+    NonFiniteOutput {
+        /// Index of the output channel.
+        channel: usize,
+    },
+}
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProcessError::NonFiniteOutput { channel } => {
+                write!(f, "Non-finite sample on output channel {}", channel)
+            }
+        }
     }
 }
 
+impl Error for ProcessError {}
+
 /// Used for constructing `AudioBuffer` instances on the host.
 ///
 /// This struct contains all necessary allocations for an `AudioBuffer` apart
@@ -800,6 +1571,8 @@ impl PluginParameters for PluginParametersInstance {
 pub struct HostBuffer<T: Float> {
     inputs: Vec<*const T>,
     outputs: Vec<*mut T>,
+    input_scratch: Vec<Vec<T>>,
+    output_scratch: Vec<Vec<T>>,
 }
 
 impl<T: Float> HostBuffer<T> {
@@ -808,31 +1581,56 @@ impl<T: Float> HostBuffer<T> {
         HostBuffer {
             inputs: vec![ptr::null(); input_count],
             outputs: vec![ptr::null_mut(); output_count],
+            input_scratch: Vec::new(),
+            output_scratch: Vec::new(),
         }
     }
 
     /// Create a `HostBuffer` for the number of input and output channels
     /// specified in an `Info` struct.
+    ///
+    /// For a plugin without inputs, such as most instruments, the buffer has no input channels
+    /// and is bound with an empty slice of input arrays. Negative channel counts are treated as 0.
     pub fn from_info(info: &Info) -> HostBuffer<T> {
-        HostBuffer::new(info.inputs as usize, info.outputs as usize)
+        HostBuffer::new(info.inputs.max(0) as usize, info.outputs.max(0) as usize)
     }
 
     /// Bind sample arrays to the `HostBuffer` to create an `AudioBuffer` to pass to a plugin.
     ///
     /// # Panics
     /// This function will panic if more inputs or outputs are supplied than the `HostBuffer`
-    /// was created for, or if the sample arrays do not all have the same length.
+    /// was created for, or if the sample arrays do not all have the same length. Use
+    /// [`bind_checked`](#method.bind_checked) to handle these cases without panicking.
     pub fn bind<'a, I, O>(&'a mut self, input_arrays: &[I], output_arrays: &mut [O]) -> AudioBuffer<'a, T>
+    where
+        I: AsRef<[T]> + 'a,
+        O: AsMut<[T]> + 'a,
+    {
+        match self.bind_checked(input_arrays, output_arrays) {
+            Ok(buffer) => buffer,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Bind sample arrays to the `HostBuffer` to create an `AudioBuffer` to pass to a plugin.
+    ///
+    /// Returns an error if more inputs or outputs are supplied than the `HostBuffer` was created
+    /// for, or if the sample arrays do not all have the same length.
+    pub fn bind_checked<'a, I, O>(
+        &'a mut self,
+        input_arrays: &[I],
+        output_arrays: &mut [O],
+    ) -> Result<AudioBuffer<'a, T>, BindError>
     where
         I: AsRef<[T]> + 'a,
         O: AsMut<[T]> + 'a,
     {
         // Check that number of desired inputs and outputs fit in allocation
         if input_arrays.len() > self.inputs.len() {
-            panic!("Too many inputs for HostBuffer");
+            return Err(BindError::TooManyInputs);
         }
         if output_arrays.len() > self.outputs.len() {
-            panic!("Too many outputs for HostBuffer");
+            return Err(BindError::TooManyOutputs);
         }
 
         // Initialize raw pointers and find common length
@@ -843,7 +1641,7 @@ impl<T: Float> HostBuffer<T> {
                 None => length = Some(input.len()),
                 Some(old_length) => {
                     if input.len() != old_length {
-                        panic!("Mismatching lengths of input arrays");
+                        return Err(BindError::MismatchingInputLengths);
                     }
                 }
             }
@@ -854,7 +1652,7 @@ impl<T: Float> HostBuffer<T> {
                 None => length = Some(output.len()),
                 Some(old_length) => {
                     if output.len() != old_length {
-                        panic!("Mismatching lengths of output arrays");
+                        return Err(BindError::MismatchingOutputLengths);
                     }
                 }
             }
@@ -863,13 +1661,121 @@ impl<T: Float> HostBuffer<T> {
 
         // Construct AudioBuffer
         unsafe {
-            AudioBuffer::from_raw(
+            Ok(AudioBuffer::from_raw(
                 input_arrays.len(),
                 output_arrays.len(),
                 self.inputs.as_ptr(),
                 self.outputs.as_mut_ptr(),
                 length,
+            ))
+        }
+    }
+
+    /// Bind sample arrays which may differ in length to the `HostBuffer` to create an
+    /// `AudioBuffer` to pass to a plugin.
+    ///
+    /// Unlike `bind`, the arrays do not need to have the same length. The returned `AudioBuffer`
+    /// covers as many samples as the shortest array, and samples beyond that are neither read nor
+    /// written by the plugin. This is useful when some channels occasionally get a shorter block,
+    /// e.g. at the end of a file.
+    ///
+    /// # Panics
+    /// This function will panic if more inputs or outputs are supplied than the `HostBuffer` was
+    /// created for.
+    pub fn bind_ragged<'a, I, O>(&'a mut self, input_arrays: &[I], output_arrays: &mut [O]) -> AudioBuffer<'a, T>
+    where
+        I: AsRef<[T]> + 'a,
+        O: AsMut<[T]> + 'a,
+    {
+        if input_arrays.len() > self.inputs.len() {
+            panic!("{}", BindError::TooManyInputs);
+        }
+        if output_arrays.len() > self.outputs.len() {
+            panic!("{}", BindError::TooManyOutputs);
+        }
+
+        let mut length = None;
+        for (i, input) in input_arrays.iter().map(|r| r.as_ref()).enumerate() {
+            self.inputs[i] = input.as_ptr();
+            length = Some(length.map_or(input.len(), |length: usize| length.min(input.len())));
+        }
+        for (i, output) in output_arrays.iter_mut().map(|r| r.as_mut()).enumerate() {
+            self.outputs[i] = output.as_mut_ptr();
+            length = Some(length.map_or(output.len(), |length: usize| length.min(output.len())));
+        }
+
+        unsafe {
+            AudioBuffer::from_raw(
+                input_arrays.len(),
+                output_arrays.len(),
+                self.inputs.as_ptr(),
+                self.outputs.as_mut_ptr(),
+                length.unwrap_or(0),
+            )
+        }
+    }
+
+    /// Bind interleaved sample arrays (`[L, R, L, R, ...]`) to the `HostBuffer` to create an
+    /// `AudioBuffer` to pass to a plugin.
+    ///
+    /// VST plugins only process separate arrays per channel, so the input samples are copied into
+    /// scratch arrays owned by the `HostBuffer`, and the output samples are copied back into
+    /// `output_interleaved` when the returned `InterleavedAudioBuffer` is dropped. The scratch
+    /// arrays are reused, so this only allocates when the number of channels or samples grows,
+    /// but the copies cost an extra pass over all samples in each direction. An empty
+    /// `input_interleaved` binds no input channels, e.g. for synths.
+    ///
+    /// # Panics
+    /// This function will panic if `channels` is more than the `HostBuffer` was created for, or
+    /// if the arrays do not contain the same whole number of samples per channel.
+    pub fn bind_interleaved<'a>(
+        &'a mut self,
+        input_interleaved: &[T],
+        output_interleaved: &'a mut [T],
+        channels: usize,
+    ) -> InterleavedAudioBuffer<'a, T> {
+        let input_channels = if input_interleaved.is_empty() { 0 } else { channels };
+        if input_channels > self.inputs.len() {
+            panic!("Too many inputs for HostBuffer");
+        }
+        if channels > self.outputs.len() {
+            panic!("Too many outputs for HostBuffer");
+        }
+
+        let samples = output_interleaved.len().checked_div(channels).unwrap_or(0);
+        if samples * channels != output_interleaved.len()
+            || (input_channels > 0 && input_interleaved.len() != output_interleaved.len())
+        {
+            panic!("Mismatching lengths of interleaved arrays");
+        }
+
+        self.input_scratch.resize_with(input_channels, Vec::new);
+        for (channel, scratch) in self.input_scratch.iter_mut().enumerate() {
+            scratch.clear();
+            scratch.extend(input_interleaved.iter().skip(channel).step_by(channels));
+            self.inputs[channel] = scratch.as_ptr();
+        }
+
+        self.output_scratch.resize_with(channels, Vec::new);
+        for (channel, scratch) in self.output_scratch.iter_mut().enumerate() {
+            scratch.clear();
+            scratch.resize(samples, T::zero());
+            self.outputs[channel] = scratch.as_mut_ptr();
+        }
+
+        let buffer = unsafe {
+            AudioBuffer::from_raw(
+                input_channels,
+                channels,
+                self.inputs.as_ptr(),
+                self.outputs.as_mut_ptr(),
+                samples,
             )
+        };
+
+        InterleavedAudioBuffer {
+            buffer,
+            output: output_interleaved,
         }
     }
 
@@ -884,20 +1790,267 @@ impl<T: Float> HostBuffer<T> {
     }
 }
 
-/// HACK: a pointer to store the host so that it can be accessed from the `callback_wrapper`
-/// function passed to the plugin.
+// SAFETY: The pointers stored in a `HostBuffer` are only dereferenced through the `AudioBuffer`
+// returned by `bind`, which borrows both the `HostBuffer` and the sample arrays.
+unsafe impl<T: Float + Send> Send for HostBuffer<T> {}
+
+/// An `AudioBuffer` bound to interleaved sample arrays.
+///
+/// Returned by [`HostBuffer::bind_interleaved`](struct.HostBuffer.html#method.bind_interleaved).
+/// Derefs to the `AudioBuffer` to pass to the plugin, and copies the output samples into the
+/// interleaved output array when dropped.
+pub struct InterleavedAudioBuffer<'a, T: Float> {
+    buffer: AudioBuffer<'a, T>,
+    output: &'a mut [T],
+}
+
+impl<'a, T: Float> Deref for InterleavedAudioBuffer<'a, T> {
+    type Target = AudioBuffer<'a, T>;
+
+    fn deref(&self) -> &AudioBuffer<'a, T> {
+        &self.buffer
+    }
+}
+
+impl<'a, T: Float> DerefMut for InterleavedAudioBuffer<'a, T> {
+    fn deref_mut(&mut self) -> &mut AudioBuffer<'a, T> {
+        &mut self.buffer
+    }
+}
+
+impl<'a, T: Float> Drop for InterleavedAudioBuffer<'a, T> {
+    fn drop(&mut self) {
+        let channels = self.buffer.output_count();
+        for (channel, samples) in self.buffer.split().1.into_iter().enumerate() {
+            for (out, sample) in self.output.iter_mut().skip(channel).step_by(channels).zip(samples) {
+                *out = *sample;
+            }
+        }
+    }
+}
+
+/// A pool of `HostBuffer` instances, for hosts running many plugin instances.
+///
+/// Buffers are kept per combination of input and output channel count and are returned to the
+/// pool when the `PooledHostBuffer` handed out by [`get`](#method.get) is dropped.
+///
+/// ```rust
+/// # use vst::host::HostBufferPool;
+/// # use vst::plugin::Plugin;
+/// # fn test<P: Plugin>(plugin: &mut P, pool: &HostBufferPool<f32>) {
+/// let mut host_buffer = pool.get(2, 2);
+/// let inputs = vec![vec![0.0; 1000]; 2];
+/// let mut outputs = vec![vec![0.0; 1000]; 2];
+/// plugin.process(&mut host_buffer.bind(&inputs, &mut outputs));
+/// # }
+/// ```
+pub struct HostBufferPool<T: Float> {
+    buffers: Mutex<HashMap<ChannelCounts, Vec<HostBuffer<T>>>>,
+}
+
+/// Number of input and output channels of a pooled `HostBuffer`.
+type ChannelCounts = (usize, usize);
+
+impl<T: Float> Default for HostBufferPool<T> {
+    fn default() -> Self {
+        HostBufferPool::new()
+    }
+}
+
+impl<T: Float> HostBufferPool<T> {
+    /// Create an empty `HostBufferPool`.
+    pub fn new() -> HostBufferPool<T> {
+        HostBufferPool {
+            buffers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a `HostBuffer` for the given number of input and output channels from the pool.
+    ///
+    /// A new `HostBuffer` is allocated if none with this channel count is available.
+    pub fn get(&self, input_count: usize, output_count: usize) -> PooledHostBuffer<'_, T> {
+        let buffer = self
+            .buffers
+            .lock()
+            .unwrap()
+            .get_mut(&(input_count, output_count))
+            .and_then(Vec::pop)
+            .unwrap_or_else(|| HostBuffer::new(input_count, output_count));
+
+        PooledHostBuffer {
+            pool: self,
+            buffer: Some(buffer),
+        }
+    }
+}
+
+/// A `HostBuffer` taken from a `HostBufferPool`, which is returned to the pool on drop.
+pub struct PooledHostBuffer<'a, T: Float> {
+    pool: &'a HostBufferPool<T>,
+    buffer: Option<HostBuffer<T>>,
+}
+
+impl<'a, T: Float> Deref for PooledHostBuffer<'a, T> {
+    type Target = HostBuffer<T>;
+
+    fn deref(&self) -> &HostBuffer<T> {
+        self.buffer.as_ref().unwrap()
+    }
+}
+
+impl<'a, T: Float> DerefMut for PooledHostBuffer<'a, T> {
+    fn deref_mut(&mut self) -> &mut HostBuffer<T> {
+        self.buffer.as_mut().unwrap()
+    }
+}
+
+impl<'a, T: Float> Drop for PooledHostBuffer<'a, T> {
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            let key = (buffer.input_count(), buffer.output_count());
+            if let Ok(mut buffers) = self.pool.buffers.lock() {
+                buffers.entry(key).or_default().push(buffer);
+            }
+        }
+    }
+}
+
+/// Buffer for events sent from the host to a plugin.
 ///
-/// When the plugin is being loaded, a `Box<Arc<Mutex<T>>>` is transmuted to a `*mut c_void` pointer
-/// and placed here. When the plugin calls the callback during initialization, the host refers to
-/// this pointer to get a handle to the Host. After initialization, this pointer is invalidated and
-/// the host pointer is placed into a [reserved field] in the instance `AEffect` struct.
+/// This is the host side counterpart of `SendEventBuffer`: events are written into preallocated
+/// `api::Events` storage, which is handed to `PluginInstance::process_events`. Memory is only
+/// allocated in [`new`](#method.new), so the buffer can be reused for every processing block.
 ///
-/// The issue with this approach is that if 2 plugins are simultaneously loaded with 2 different
-/// host instances, this might fail as one host may receive a pointer to the other one. In practice
-/// this is a rare situation as you normally won't have 2 separate host instances loading at once.
+/// ```no_run
+/// # use vst::event::MidiEvent;
+/// # use vst::host::{HostEventBuffer, PluginInstance};
+/// # fn process(instance: &mut PluginInstance, event_buffer: &mut HostEventBuffer) {
+/// let events = [MidiEvent::simple([0x90, 60, 100])];
+/// event_buffer.send_events(&events, instance);
+/// # }
+/// ```
+pub struct HostEventBuffer {
+    buffer: SendEventBuffer,
+}
+
+impl Default for HostEventBuffer {
+    fn default() -> Self {
+        HostEventBuffer::new(1024)
+    }
+}
+
+impl HostEventBuffer {
+    /// Create a buffer for sending up to `capacity` events per block.
+    pub fn new(capacity: usize) -> Self {
+        HostEventBuffer {
+            buffer: SendEventBuffer::new(capacity),
+        }
+    }
+
+    /// Store `events` in the buffer, replacing its current content.
+    ///
+    /// Events beyond the capacity given to [`new`](#method.new) are dropped.
+    pub fn store_events<T: IntoIterator<Item = U>, U: WriteIntoPlaceholder>(&mut self, events: T) {
+        self.buffer.store_events(events);
+    }
+
+    /// Store `events` and send them to `instance`.
+    ///
+    /// This should be called before processing the block the events belong to.
+    pub fn send_events<T: IntoIterator<Item = U>, U: WriteIntoPlaceholder>(
+        &mut self,
+        events: T,
+        instance: &mut PluginInstance,
+    ) {
+        self.store_events(events);
+        instance.process_events(self.events());
+    }
+
+    /// Get the stored events, e.g. for `PluginInstance::process_with_events`.
+    pub fn events(&self) -> &api::Events {
+        self.buffer.events()
+    }
+
+    /// Remove all stored events.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+thread_local! {
+    /// HACK: a pointer to store the host so that it can be accessed from the `callback_wrapper`
+    /// function passed to the plugin.
+    ///
+    /// When the plugin is being loaded, a `Box<Arc<Mutex<T>>>` is transmuted to a `*mut c_void`
+    /// pointer and placed here. When the plugin calls the callback during initialization, the host
+    /// refers to this pointer to get a handle to the Host. After initialization, this pointer is
+    /// invalidated and the host pointer is placed into a [reserved field] in the instance `AEffect`
+    /// struct.
+    ///
+    /// The pointer is thread local, so plugins can be loaded on several threads at once with
+    /// different hosts. This relies on the plugin calling back into the host on the thread which
+    /// called its entry point while it is initializing.
+    ///
+    /// [reserved field]: ../api/struct.AEffect.html#structfield.reserved1
+    static LOAD_POINTER: Cell<*mut c_void> = const { Cell::new(ptr::null_mut()) };
+
+    /// The plugin ID requested through `PluginLoader::instance_with_id`, reported to the plugin
+    /// while it is initializing.
+    static LOAD_ID: Cell<Option<i32>> = const { Cell::new(None) };
+}
+
+thread_local! {
+    /// Whether `dispatch_to_host` holds a host lock on this thread, i.e. whether a plugin callback
+    /// is re-entering the host from within a host opcode.
+    static HOST_LOCKED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Lock the host and dispatch a host opcode to it.
+///
+/// Plugins may call back into the host from within any of their methods, including while the host
+/// is handling an opcode on the same thread. Locking again would deadlock then, so such a nested
+/// opcode is dropped and 0 is returned at once. If another thread holds the lock, this waits for
+/// it, so opcodes like `automate` are not lost. A poisoned lock is still used, as a panic elsewhere
+/// should not take down the plugin.
 ///
-/// [reserved field]: ../api/struct.AEffect.html#structfield.reserved1
-static mut LOAD_POINTER: *mut c_void = 0 as *mut c_void;
+/// Hosts must therefore not hold the lock on themselves while calling into the plugin.
+fn dispatch_to_host<T: Host>(
+    host: &Mutex<T>,
+    effect: *mut AEffect,
+    opcode: i32,
+    index: i32,
+    value: isize,
+    ptr: *mut c_void,
+    opt: f32,
+) -> isize {
+    if HOST_LOCKED.with(Cell::get) {
+        warn!(
+            "VST: Dropped host opcode ({}), called back from within a host opcode",
+            opcode
+        );
+        return 0;
+    }
+
+    let mut host = host.lock().unwrap_or_else(PoisonError::into_inner);
+    let _locked = HostLocked::new();
+    interfaces::host_dispatch(&mut *host, effect, opcode, index, value, ptr, opt)
+}
+
+/// Sets `HOST_LOCKED` while alive, and resets it even if the host panics.
+struct HostLocked;
+
+impl HostLocked {
+    fn new() -> HostLocked {
+        HOST_LOCKED.with(|locked| locked.set(true));
+        HostLocked
+    }
+}
+
+impl Drop for HostLocked {
+    fn drop(&mut self) {
+        HOST_LOCKED.with(|locked| locked.set(false));
+    }
+}
 
 /// Function passed to plugin to handle dispatching host opcodes.
 extern "C" fn callback_wrapper<T: Host>(
@@ -913,36 +2066,1149 @@ extern "C" fn callback_wrapper<T: Host>(
         // already been initialized
         if !effect.is_null() && (*effect).reserved1 != 0 {
             let reserved = (*effect).reserved1 as *const Arc<Mutex<T>>;
-            let host = &*reserved;
-
-            let host = &mut *host.lock().unwrap();
-
-            interfaces::host_dispatch(host, effect, opcode, index, value, ptr, opt)
+            dispatch_to_host(&*reserved, effect, opcode, index, value, ptr, opt)
         // In this case, the plugin is still undergoing initialization and so `LOAD_POINTER` is
         // dereferenced
         } else {
-            // Used only during the plugin initialization
-            let host = LOAD_POINTER as *const Arc<Mutex<T>>;
-            let host = &*host;
-            let host = &mut *host.lock().unwrap();
+            // Shell plugins ask which of their sub-plugins to create
+            if let (Ok(OpCode::CurrentId), Some(id)) = (OpCode::try_from(opcode), LOAD_ID.with(Cell::get)) {
+                return id as isize;
+            }
 
-            interfaces::host_dispatch(host, effect, opcode, index, value, ptr, opt)
+            // Used only during the plugin initialization
+            let host = LOAD_POINTER.with(Cell::get) as *const Arc<Mutex<T>>;
+            if host.is_null() {
+                trace!("VST: Got host opcode ({}) outside of plugin initialization", opcode);
+                return 0;
+            }
+            dispatch_to_host(&*host, effect, opcode, index, value, ptr, opt)
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::host::HostBuffer;
+    use std::cell::{Cell, RefCell};
+    use std::convert::TryFrom;
+    use std::os::raw::c_void;
+    use std::panic::AssertUnwindSafe;
+    use std::ptr;
+    use std::sync::{mpsc, Arc, Mutex, Weak};
+    use std::thread;
+    use std::time::Duration;
+
+    use libloading::Library;
+
+    use crate::api::{consts::VST_MAGIC, AEffect, HostCallbackProc, ModifierKey, TailSize, TimeInfo};
+    use crate::editor::{EditorPosition, EditorSize, Key, KeyCode, KnobMode, Rect};
+    use crate::host::{
+        self, BindError, CachedTimeInfo, ChunkKind, FxFileError, Host, HostBuffer, HostBufferPool, HostEventBuffer,
+        PluginInstance, PluginLoader, ProcessError,
+    };
+    use crate::plugin::{HostCallback, Info, OpCode, Plugin, PluginParameters};
+
+    thread_local! {
+        /// `(opcode, index, value, opt)` of every call made to `mock_dispatch`.
+        static DISPATCHED: RefCell<Vec<(i32, i32, isize, f32)>> = RefCell::new(Vec::new());
+        /// `(index, value)` of every parameter access, with a value of `-1.0` for reads.
+        static PARAMETERS: RefCell<Vec<(i32, f32)>> = RefCell::new(Vec::new());
+        /// Value returned by `mock_dispatch` for `GetVendorVersion`.
+        static VENDOR_VERSION: Cell<isize> = Cell::new(0);
+        /// Number of dispatched opcodes in `DISPATCHED` at every call to `mock_process`.
+        static PROCESSED: RefCell<Vec<usize>> = RefCell::new(Vec::new());
+        /// The library of a plugin instance, which `mock_dispatch` checks on `Shutdown`.
+        static LIBRARY: RefCell<Weak<Library>> = RefCell::new(Weak::new());
+        /// Whether `LIBRARY` was still loaded when `mock_dispatch` received `Shutdown`.
+        static LIBRARY_LOADED_AT_SHUTDOWN: Cell<Option<bool>> = Cell::new(None);
+        /// Value returned by `mock_dispatch` for `GetTailSize`.
+        static TAIL_SIZE: Cell<isize> = Cell::new(0);
+        /// `(kind, data)` of every chunk loaded into a `ChunkParameters`.
+        static LOADED_CHUNKS: RefCell<Vec<(&'static str, Vec<u8>)>> = RefCell::new(Vec::new());
+    }
 
-    #[test]
-    fn host_buffer() {
-        const LENGTH: usize = 1_000_000;
-        let mut host_buffer: HostBuffer<f32> = HostBuffer::new(2, 2);
-        let input_left = vec![1.0; LENGTH];
-        let input_right = vec![1.0; LENGTH];
-        let mut output_left = vec![0.0; LENGTH];
-        let mut output_right = vec![0.0; LENGTH];
+    extern "C" fn mock_dispatch(
+        effect: *mut AEffect,
+        opcode: i32,
+        index: i32,
+        value: isize,
+        ptr: *mut c_void,
+        opt: f32,
+    ) -> isize {
+        DISPATCHED.with(|calls| calls.borrow_mut().push((opcode, index, value, opt)));
+
+        match OpCode::try_from(opcode) {
+            Ok(OpCode::Shutdown) => {
+                let loaded = LIBRARY.with(|library| library.borrow().strong_count() > 0);
+                LIBRARY_LOADED_AT_SHUTDOWN.with(|shutdown| shutdown.set(Some(loaded)));
+                drop(unsafe { Box::from_raw(effect) });
+                0
+            }
+            Ok(OpCode::GetApiVersion) => 2400,
+            Ok(OpCode::GetVendorVersion) => VENDOR_VERSION.with(|version| version.get()),
+            Ok(OpCode::GetTailSize) => TAIL_SIZE.with(|tail_size| tail_size.get()),
+            // The current preset is always preset 0.
+            Ok(OpCode::GetPresetName) | Ok(OpCode::GetCurrentPresetName) => {
+                let name = format!("Preset {}\0", index);
+                unsafe { ptr::copy_nonoverlapping(name.as_ptr(), ptr as *mut u8, name.len()) };
+                1
+            }
+            // Index 0 requests the bank chunk, any other index the preset chunk.
+            Ok(OpCode::GetData) => {
+                let chunk: &'static [u8] = if index == 0 { b"bank" } else { b"preset" };
+                unsafe { *(ptr as *mut *const u8) = chunk.as_ptr() };
+                chunk.len() as isize
+            }
+            // The editor is 400 by 200 pixels at (10, 20).
+            Ok(OpCode::EditorGetRect) => {
+                static RECT: Rect = Rect {
+                    top: 20,
+                    left: 10,
+                    bottom: 220,
+                    right: 410,
+                };
+                unsafe { *(ptr as *mut *const Rect) = &RECT };
+                1
+            }
+            Ok(OpCode::EditorOpen) | Ok(OpCode::EditorSetKnobMode) => 1,
+            Ok(OpCode::EditorKeyDown) | Ok(OpCode::EditorKeyUp) => 1,
+            _ => 0,
+        }
+    }
+
+    /// Copies every input channel to the output channel with the same index.
+    extern "C" fn mock_process(effect: *mut AEffect, inputs: *const *const f32, outputs: *mut *mut f32, samples: i32) {
+        let dispatched = DISPATCHED.with(|calls| calls.borrow().len());
+        PROCESSED.with(|calls| calls.borrow_mut().push(dispatched));
+
+        unsafe {
+            let channels = (*effect).numInputs.min((*effect).numOutputs) as usize;
+            for channel in 0..channels {
+                ptr::copy_nonoverlapping(*inputs.add(channel), *outputs.add(channel), samples as usize);
+            }
+        }
+    }
+
+    extern "C" fn mock_process_deprecated(_: *mut AEffect, _: *const *const f32, _: *mut *mut f32, _: i32) {}
+
+    extern "C" fn mock_process_f64(_: *mut AEffect, _: *const *const f64, _: *mut *mut f64, _: i32) {}
+
+    extern "C" fn mock_set_parameter(_: *mut AEffect, index: i32, value: f32) {
+        PARAMETERS.with(|calls| calls.borrow_mut().push((index, value)));
+    }
+
+    extern "C" fn mock_get_parameter(_: *mut AEffect, index: i32) -> f32 {
+        PARAMETERS.with(|calls| calls.borrow_mut().push((index, -1.0)));
+        index as f32 / 10.0
+    }
+
+    /// Get a handle to the current executable, standing in for a loaded plugin library.
+    fn this_library() -> Arc<Library> {
+        #[cfg(unix)]
+        let lib = libloading::os::unix::Library::this();
+        #[cfg(windows)]
+        let lib = libloading::os::windows::Library::this().unwrap();
+
+        Arc::new(Library::from(lib))
+    }
+
+    /// Load a plugin created with this crate's plugin side.
+    fn plugin_instance<P: Plugin>() -> PluginInstance {
+        extern "C" fn host_callback(_: *mut AEffect, _: i32, _: i32, _: isize, _: *mut c_void, _: f32) -> isize {
+            1
+        }
+
+        PluginInstance::new(crate::main::<P>(host_callback), this_library())
+    }
+
+    /// Create a bare `AEffect` which records all dispatched opcodes.
+    fn mock_effect(unique_id: i32) -> *mut AEffect {
+        Box::into_raw(Box::new(AEffect {
+            magic: VST_MAGIC,
+            dispatcher: mock_dispatch,
+            _process: mock_process_deprecated,
+            setParameter: mock_set_parameter,
+            getParameter: mock_get_parameter,
+            numPrograms: 1,
+            numParams: 2,
+            numInputs: 2,
+            numOutputs: 2,
+            flags: 0,
+            reserved1: 0,
+            reserved2: 0,
+            initialDelay: 0,
+            _realQualities: 0,
+            _offQualities: 0,
+            _ioRatio: 0.0,
+            object: ptr::null_mut(),
+            user: ptr::null_mut(),
+            uniqueId: unique_id,
+            version: 1,
+            processReplacing: mock_process,
+            processReplacingF64: mock_process_f64,
+            future: [0u8; 56],
+        }))
+    }
+
+    /// Create a `PluginInstance` around a mock `AEffect`.
+    fn mock_instance() -> PluginInstance {
+        let instance = PluginInstance::new(mock_effect(1234), this_library());
+        DISPATCHED.with(|calls| calls.borrow_mut().clear());
+        PARAMETERS.with(|calls| calls.borrow_mut().clear());
+        PROCESSED.with(|calls| calls.borrow_mut().clear());
+        instance
+    }
+
+    /// Get the recorded calls to `mock_dispatch` with the given opcode.
+    fn dispatched(opcode: OpCode) -> Vec<(i32, isize, f32)> {
+        DISPATCHED.with(|calls| {
+            calls
+                .borrow()
+                .iter()
+                .filter(|call| call.0 == opcode.into())
+                .map(|&(_, index, value, opt)| (index, value, opt))
+                .collect()
+        })
+    }
+
+    #[test]
+    fn process_on_other_thread() {
+        let mut instance = mock_instance();
+
+        let outputs = thread::spawn(move || {
+            let mut host_buffer: HostBuffer<f32> = HostBuffer::from_info(&instance.get_info());
+            let inputs = vec![vec![1.0; 64], vec![2.0; 64]];
+            let mut outputs = vec![vec![0.0; 64]; 2];
+            instance.process(&mut host_buffer.bind(&inputs, &mut outputs));
+            outputs
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(outputs, vec![vec![1.0; 64], vec![2.0; 64]]);
+    }
+
+    #[test]
+    fn preset_names() {
+        let effect = mock_effect(1234);
+        unsafe { (*effect).numPrograms = 2 };
+        let instance = PluginInstance::new(effect, this_library());
+
+        assert_eq!(instance.preset_count(), 2);
+        assert_eq!(instance.preset_names(), ["Preset 0", "Preset 1"]);
+    }
+
+    #[test]
+    fn refresh_io() {
+        use super::Dispatch;
+
+        let mut instance = mock_instance();
+        assert_eq!((instance.num_inputs(), instance.num_outputs()), (2, 2));
+
+        unsafe {
+            let effect = instance.get_effect();
+            (*effect).numInputs = 1;
+            (*effect).numOutputs = 6;
+            (*effect).initialDelay = 128;
+        }
+        // Counts are only updated on request
+        assert_eq!(instance.num_outputs(), 2);
+
+        instance.refresh_io();
+        assert_eq!((instance.num_inputs(), instance.num_outputs()), (1, 6));
+        assert_eq!(instance.get_info().initial_delay, 128);
+    }
+
+    #[test]
+    fn vendor_version() {
+        let instance = mock_instance();
+        assert_eq!(instance.get_info().version, 1);
+        assert_eq!(instance.get_info().vendor_version, 1);
+        drop(instance);
+
+        VENDOR_VERSION.with(|version| version.set(4321));
+        let instance = mock_instance();
+        assert_eq!(instance.get_info().version, 1);
+        assert_eq!(instance.get_info().vendor_version, 4321);
+    }
+
+    #[test]
+    fn chunks() {
+        let instance = mock_instance();
+        assert_eq!(instance.get_chunk(ChunkKind::Bank), b"bank");
+        assert_eq!(instance.get_chunk(ChunkKind::Preset), b"preset");
+
+        instance.set_chunk(ChunkKind::Bank, b"data");
+        instance.set_chunk(ChunkKind::Preset, b"more data");
+
+        let indices = |opcode| dispatched(opcode).iter().map(|call| call.0).collect::<Vec<_>>();
+        assert_eq!(indices(OpCode::GetData), vec![0, 1]);
+        assert_eq!(indices(OpCode::SetData), vec![0, 1]);
+        assert_eq!(dispatched(OpCode::SetData)[1].1, 9);
+    }
+
+    #[test]
+    fn tail_samples() {
+        let instance = mock_instance();
+        let tail_samples = |raw: isize| {
+            TAIL_SIZE.with(|tail_size| tail_size.set(raw));
+            instance.tail_samples()
+        };
+
+        assert_eq!(tail_samples(0), TailSize::Default);
+        assert_eq!(tail_samples(1), TailSize::NoTail);
+        assert_eq!(tail_samples(2), TailSize::Samples(2));
+        assert_eq!(tail_samples(44100), TailSize::Samples(44100));
+        assert_eq!(tail_samples(-1), TailSize::Default);
+    }
+
+    #[test]
+    fn resume_and_suspend_once() {
+        let mut instance = mock_instance();
+        assert!(!instance.is_resumed());
+
+        instance.suspend();
+        instance.resume();
+        instance.resume();
+        assert!(instance.is_resumed());
+
+        instance.suspend();
+        instance.suspend();
+        assert!(!instance.is_resumed());
+
+        assert_eq!(dispatched(OpCode::StateChanged), vec![(0, 1, 0.0), (0, 0, 0.0)]);
+    }
+
+    #[test]
+    fn api_version() {
+        let instance = mock_instance();
+        assert_eq!(instance.api_version(), 2400);
+        assert_eq!(instance.get_vst_version(), 2400);
+
+        // The version is cached, so the plugin is not asked again.
+        assert!(dispatched(OpCode::GetApiVersion).is_empty());
+    }
+
+    /// Host reporting a fixed plugin ID.
+    struct ShellHost(i32);
+
+    impl Host for ShellHost {
+        fn get_plugin_id(&self) -> i32 {
+            self.0
+        }
+    }
+
+    /// Entry point of a shell plugin creating a sub-plugin with the requested unique ID.
+    fn shell_main(callback: HostCallbackProc) -> *mut AEffect {
+        let id = callback(
+            ptr::null_mut(),
+            host::OpCode::CurrentId.into(),
+            0,
+            0,
+            ptr::null_mut(),
+            0.0,
+        );
+        mock_effect(id as i32)
+    }
+
+    fn shell_loader(id: i32) -> PluginLoader<ShellHost> {
+        PluginLoader {
+            main: shell_main,
+            lib: this_library(),
+            host: Arc::new(Mutex::new(ShellHost(id))),
+        }
+    }
+
+    #[test]
+    fn editor_resize_round_trip() {
+        use crate::editor::Editor;
+
+        /// Host which records and accepts every resize request.
+        #[derive(Default)]
+        struct ResizeHost {
+            sizes: Mutex<Vec<(i32, i32)>>,
+        }
+
+        impl Host for ResizeHost {
+            fn size_window(&self, width: i32, height: i32) -> bool {
+                self.sizes.lock().unwrap().push((width, height));
+                true
+            }
+        }
+
+        /// Editor which asks the host for a larger window when it is opened.
+        struct ResizingEditor {
+            host: HostCallback,
+            size: (i32, i32),
+        }
+
+        impl Editor for ResizingEditor {
+            fn size(&self) -> (i32, i32) {
+                self.size
+            }
+
+            fn position(&self) -> (i32, i32) {
+                (0, 0)
+            }
+
+            fn open(&mut self, _parent: *mut c_void) -> bool {
+                if self.host.size_window(640, 360) {
+                    self.size = (640, 360);
+                }
+                true
+            }
+
+            fn is_open(&mut self) -> bool {
+                false
+            }
+        }
+
+        struct ResizePlugin {
+            host: HostCallback,
+        }
+
+        impl Plugin for ResizePlugin {
+            fn new(host: HostCallback) -> Self {
+                ResizePlugin { host }
+            }
+
+            fn get_info(&self) -> Info {
+                Default::default()
+            }
+
+            fn get_editor(&mut self) -> Option<Box<dyn Editor>> {
+                Some(Box::new(ResizingEditor {
+                    host: self.host,
+                    size: (320, 180),
+                }))
+            }
+        }
+
+        let host = Arc::new(Mutex::new(ResizeHost::default()));
+        let mut loader = PluginLoader {
+            main: crate::main::<ResizePlugin>,
+            lib: this_library(),
+            host: Arc::clone(&host),
+        };
+        let mut instance = loader.instance().unwrap();
+        let mut editor = instance.get_editor().unwrap();
+
+        assert_eq!(editor.size(), (320, 180));
+        assert!(editor.open(ptr::null_mut()));
+        assert_eq!(*host.lock().unwrap().sizes.lock().unwrap(), vec![(640, 360)]);
+        assert_eq!(editor.size(), (640, 360));
+        editor.close();
+    }
+
+    #[test]
+    fn load_plugin_invalid_path() {
+        let host = Arc::new(Mutex::new(ShellHost(0)));
+        let path = std::path::Path::new("/nonexistent/plugin.so");
+        assert!(matches!(
+            host::load_plugin(path, host),
+            Err(host::PluginLoadError::InvalidPath)
+        ));
+    }
+
+    #[test]
+    fn instance_outlives_loader() {
+        let mut loader = shell_loader(5);
+        let instance = loader.instance().unwrap();
+        let library = Arc::downgrade(&instance.lib);
+        LIBRARY.with(|weak| *weak.borrow_mut() = library.clone());
+
+        drop(loader);
+        assert_eq!(library.strong_count(), 1);
+
+        drop(instance);
+        assert_eq!(LIBRARY_LOADED_AT_SHUTDOWN.with(Cell::get), Some(true));
+        assert_eq!(library.strong_count(), 0);
+    }
+
+    #[test]
+    fn instance_with_id() {
+        let mut loader = shell_loader(42);
+
+        assert_eq!(loader.instance_with_id(1).unwrap().get_info().unique_id, 1);
+        assert_eq!(loader.instance_with_id(2).unwrap().get_info().unique_id, 2);
+        assert_eq!(loader.instance().unwrap().get_info().unique_id, 42);
+    }
+
+    #[test]
+    fn concurrent_loads() {
+        let threads: Vec<_> = (1..=8)
+            .map(|id| {
+                thread::spawn(move || {
+                    let mut loader = shell_loader(id);
+                    for _ in 0..100 {
+                        // Each plugin must see the host of the loader creating it.
+                        assert_eq!(loader.instance().unwrap().get_info().unique_id, id);
+                    }
+                })
+            })
+            .collect();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn callback_while_host_locked() {
+        let host = Arc::new(Mutex::new(ShellHost(7)));
+        let effect = mock_effect(1);
+        unsafe { (*effect).reserved1 = Box::into_raw(Box::new(Arc::clone(&host))) as isize };
+        let current_id =
+            || super::callback_wrapper::<ShellHost>(effect, host::OpCode::CurrentId.into(), 0, 0, ptr::null_mut(), 0.0);
+
+        // Contention with another thread is waited for instead of dropping the opcode.
+        let (locked_tx, locked_rx) = mpsc::channel();
+        let holder = Arc::clone(&host);
+        let holder = thread::spawn(move || {
+            let _guard = holder.lock().unwrap();
+            locked_tx.send(()).unwrap();
+            thread::sleep(Duration::from_millis(50));
+        });
+        locked_rx.recv().unwrap();
+        assert_eq!(current_id(), 7);
+        holder.join().unwrap();
+
+        let poisoner = Arc::clone(&host);
+        let _ = thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("poison the host lock");
+        })
+        .join();
+        assert!(host.is_poisoned());
+        assert_eq!(current_id(), 7);
+
+        unsafe {
+            drop(Box::from_raw((*effect).reserved1 as *mut Arc<Mutex<ShellHost>>));
+            drop(Box::from_raw(effect));
+        }
+    }
+
+    thread_local! {
+        /// Effect passed to `ReentrantHost`, and the result of its nested callback.
+        static REENTRANT: Cell<(*mut AEffect, Option<isize>)> = const { Cell::new((ptr::null_mut(), None)) };
+    }
+
+    /// Host calling back into itself through the plugin callback while handling an opcode.
+    struct ReentrantHost;
+
+    impl Host for ReentrantHost {
+        fn get_plugin_id(&self) -> i32 {
+            let (effect, _) = REENTRANT.with(Cell::get);
+            let opcode = host::OpCode::CurrentId.into();
+            let nested = super::callback_wrapper::<ReentrantHost>(effect, opcode, 0, 0, ptr::null_mut(), 0.0);
+            REENTRANT.with(|cell| cell.set((effect, Some(nested))));
+            5
+        }
+    }
+
+    #[test]
+    fn reentrant_callback() {
+        let host = Arc::new(Mutex::new(ReentrantHost));
+        let effect = mock_effect(1);
+        unsafe { (*effect).reserved1 = Box::into_raw(Box::new(Arc::clone(&host))) as isize };
+        REENTRANT.with(|cell| cell.set((effect, None)));
+
+        let opcode = host::OpCode::CurrentId.into();
+        let id = super::callback_wrapper::<ReentrantHost>(effect, opcode, 0, 0, ptr::null_mut(), 0.0);
+        assert_eq!(id, 5);
+        // The nested call is dropped instead of waiting for the lock held further up this thread.
+        assert_eq!(REENTRANT.with(Cell::get).1, Some(0));
+
+        unsafe { drop(Box::from_raw((*effect).reserved1 as *mut Arc<Mutex<ReentrantHost>>)) };
+
+        // A panicking host does not leave this thread marked as being within a host opcode.
+        struct PanickingHost;
+
+        impl Host for PanickingHost {
+            fn get_plugin_id(&self) -> i32 {
+                panic!("host failed");
+            }
+        }
+
+        let panicking = Mutex::new(PanickingHost);
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            super::dispatch_to_host(&panicking, effect, opcode, 0, 0, ptr::null_mut(), 0.0)
+        }));
+        assert!(result.is_err());
+
+        // Once the outer opcode is done, this thread can call into hosts again.
+        let shell_host = Arc::new(Mutex::new(ShellHost(7)));
+        unsafe { (*effect).reserved1 = Box::into_raw(Box::new(shell_host)) as isize };
+        let id = super::callback_wrapper::<ShellHost>(effect, opcode, 0, 0, ptr::null_mut(), 0.0);
+        assert_eq!(id, 7);
+
+        unsafe {
+            drop(Box::from_raw((*effect).reserved1 as *mut Arc<Mutex<ShellHost>>));
+            drop(Box::from_raw(effect));
+        }
+    }
+
+    #[test]
+    fn set_knob_mode() {
+        let instance = mock_instance();
+
+        for mode in [KnobMode::Circular, KnobMode::CircularRelative, KnobMode::Linear] {
+            assert!(instance.set_knob_mode(mode));
+        }
+
+        assert_eq!(
+            dispatched(OpCode::EditorSetKnobMode),
+            vec![(0, 0, 0.0), (0, 1, 0.0), (0, 2, 0.0)]
+        );
+    }
+
+    #[test]
+    fn checked_parameters() {
+        let instance = mock_instance();
+
+        assert_eq!(instance.get_parameter_checked(0), Some(0.0));
+        assert_eq!(instance.get_parameter_checked(1), Some(0.1));
+        assert_eq!(instance.get_parameter_checked(2), None);
+        assert_eq!(instance.get_parameter_checked(-1), None);
+
+        assert!(instance.set_parameter_checked(1, 0.5));
+        assert!(!instance.set_parameter_checked(2, 0.5));
+        assert!(!instance.set_parameter_checked(-1, 0.5));
+
+        // Out of range indices never reach the plugin.
+        let calls = PARAMETERS.with(|calls| calls.borrow().clone());
+        assert_eq!(calls, vec![(0, -1.0), (1, -1.0), (1, 0.5)]);
+    }
+
+    #[test]
+    fn editor_size_and_position() {
+        let mut instance = mock_instance();
+        assert_eq!(
+            instance.editor_size(),
+            Some(EditorSize {
+                width: 400,
+                height: 200
+            })
+        );
+        assert_eq!(instance.editor_position(), Some(EditorPosition { x: 10, y: 20 }));
+
+        let editor = instance.get_editor().unwrap();
+        assert_eq!(editor.size(), (400, 200));
+        assert_eq!(editor.position(), (10, 20));
+
+        // This crate's plugin side answers `EditorGetRect` with 0 if there is no editor.
+        let instance = plugin_instance::<ChunkPlugin>();
+        assert_eq!(instance.editor_size(), None);
+        assert_eq!(instance.editor_position(), None);
+    }
+
+    #[test]
+    fn editor_mut() {
+        let mut instance = mock_instance();
+        assert!(instance.editor_mut().is_none());
+
+        let mut editor = instance.get_editor().unwrap();
+        assert!(instance.get_editor().is_none());
+        assert!(editor.open(ptr::null_mut()));
+
+        let active = instance.editor_mut().unwrap();
+        assert!(active.is_open());
+        active.idle();
+        assert_eq!(dispatched(OpCode::EditorIdle).len(), 1);
+
+        editor.close();
+        assert!(!instance.editor_mut().unwrap().is_open());
+    }
+
+    #[test]
+    fn editor_keys() {
+        let instance = mock_instance();
+        let key = KeyCode {
+            character: 'a',
+            key: Key::None,
+            modifier: (ModifierKey::SHIFT | ModifierKey::CONTROL).bits(),
+        };
+
+        assert!(instance.editor_key_down(key));
+        assert!(instance.editor_key_up(KeyCode {
+            key: Key::Return,
+            ..key
+        }));
+
+        let down = dispatched(OpCode::EditorKeyDown);
+        assert_eq!(down.len(), 1);
+        assert_eq!((down[0].0, down[0].1), ('a' as i32, Key::None.into()));
+        assert_eq!(down[0].2.to_bits(), 0b1001);
+
+        let up = dispatched(OpCode::EditorKeyUp);
+        assert_eq!(up.len(), 1);
+        assert_eq!((up[0].0, up[0].1), ('a' as i32, Key::Return.into()));
+        assert_eq!(up[0].2.to_bits(), 0b1001);
+    }
+
+    /// Plugin parameters storing their state in chunks, recording loaded chunks in `LOADED_CHUNKS`.
+    struct ChunkParameters;
+
+    impl PluginParameters for ChunkParameters {
+        fn get_preset_data(&self) -> Vec<u8> {
+            b"preset".to_vec()
+        }
+
+        fn get_bank_data(&self) -> Vec<u8> {
+            b"bank".to_vec()
+        }
+
+        fn load_preset_data(&self, data: &[u8]) {
+            LOADED_CHUNKS.with(|chunks| chunks.borrow_mut().push(("preset", data.to_vec())));
+        }
+
+        fn load_bank_data(&self, data: &[u8]) {
+            LOADED_CHUNKS.with(|chunks| chunks.borrow_mut().push(("bank", data.to_vec())));
+        }
+    }
+
+    struct ChunkPlugin;
+
+    impl Plugin for ChunkPlugin {
+        fn new(_host: HostCallback) -> Self {
+            ChunkPlugin
+        }
+
+        fn get_info(&self) -> Info {
+            Info {
+                unique_id: 4321,
+                preset_chunks: true,
+                ..Default::default()
+            }
+        }
+
+        fn get_parameter_object(&mut self) -> Arc<dyn PluginParameters> {
+            Arc::new(ChunkParameters)
+        }
+    }
+
+    #[test]
+    fn host_event_buffer() {
+        use crate::event::{Event, MidiEvent};
+
+        thread_local! {
+            static RECEIVED: RefCell<Vec<(usize, [u8; 3])>> = RefCell::new(Vec::new());
+        }
+
+        struct EventPlugin;
+
+        impl Plugin for EventPlugin {
+            fn new(_host: HostCallback) -> Self {
+                EventPlugin
+            }
+
+            fn get_info(&self) -> Info {
+                Default::default()
+            }
+
+            fn process_events(&mut self, events: &crate::api::Events) {
+                let address = events as *const _ as usize;
+                for event in events.events() {
+                    if let Event::Midi(event) = event {
+                        RECEIVED.with(|received| received.borrow_mut().push((address, event.data)));
+                    }
+                }
+            }
+        }
+
+        let mut instance = plugin_instance::<EventPlugin>();
+        let mut event_buffer = HostEventBuffer::new(4);
+        let address = event_buffer.events() as *const _ as usize;
+
+        let notes = [60, 64, 67];
+        for _ in 0..2 {
+            let events = notes.iter().map(|&note| MidiEvent::simple([0x90, note, 100]));
+            event_buffer.send_events(events, &mut instance);
+        }
+
+        let expected: Vec<_> = notes.iter().map(|&note| (address, [0x90, note, 100])).collect();
+        let received = RECEIVED.with(|received| received.borrow().clone());
+        assert_eq!(received[..3], expected[..]);
+        assert_eq!(received[3..], expected[..]);
+    }
+
+    #[test]
+    fn host_buffer_without_inputs() {
+        use crate::buffer::AudioBuffer;
+
+        struct Instrument;
+
+        impl Plugin for Instrument {
+            fn new(_host: HostCallback) -> Self {
+                Instrument
+            }
+
+            fn get_info(&self) -> Info {
+                Info {
+                    inputs: 0,
+                    outputs: 2,
+                    ..Default::default()
+                }
+            }
+
+            fn process(&mut self, buffer: &mut AudioBuffer<f32>) {
+                assert_eq!(buffer.input_count(), 0);
+                for (channel, output) in buffer.split().1.into_iter().enumerate() {
+                    output.fill(channel as f32 + 1.0);
+                }
+            }
+        }
+
+        let mut instance = plugin_instance::<Instrument>();
+        let mut host_buffer: HostBuffer<f32> = HostBuffer::from_info(&instance.get_info());
+        assert_eq!(host_buffer.input_count(), 0);
+        assert_eq!(host_buffer.output_count(), 2);
+
+        let inputs: [Vec<f32>; 0] = [];
+        let mut outputs = vec![vec![0.0; 64]; 2];
+        let mut buffer = host_buffer.bind(&inputs, &mut outputs);
+        assert_eq!(buffer.samples(), 64);
+        instance.process(&mut buffer);
+        assert_eq!(outputs, vec![vec![1.0; 64], vec![2.0; 64]]);
+    }
+
+    #[test]
+    fn smoke_test() {
+        use crate::buffer::AudioBuffer;
+
+        thread_local! {
+            /// `(sample rate, block size, resumed)` seen by `process`, and the value to output.
+            static SEEN: Cell<Option<(f32, i64, bool)>> = Cell::new(None);
+            static OUTPUT: Cell<f32> = Cell::new(0.0);
+            static PANIC: Cell<bool> = Cell::new(false);
+        }
+
+        #[derive(Default)]
+        struct TestedPlugin {
+            sample_rate: f32,
+            block_size: i64,
+            resumed: bool,
+        }
+
+        impl Plugin for TestedPlugin {
+            fn new(_host: HostCallback) -> Self {
+                Default::default()
+            }
+
+            fn get_info(&self) -> Info {
+                Info {
+                    inputs: 0,
+                    outputs: 2,
+                    ..Default::default()
+                }
+            }
+
+            fn set_sample_rate(&mut self, rate: f32) {
+                self.sample_rate = rate;
+            }
+
+            fn set_block_size(&mut self, size: i64) {
+                self.block_size = size;
+            }
+
+            fn resume(&mut self) {
+                self.resumed = true;
+            }
+
+            fn suspend(&mut self) {
+                self.resumed = false;
+            }
+
+            fn process(&mut self, buffer: &mut AudioBuffer<f32>) {
+                SEEN.with(|seen| seen.set(Some((self.sample_rate, self.block_size, self.resumed))));
+                let value = OUTPUT.with(Cell::get);
+                for output in buffer.split().1.into_iter().skip(1) {
+                    output.fill(value);
+                }
+                if PANIC.with(Cell::get) {
+                    panic!("Processing failed");
+                }
+            }
+        }
+
+        let mut instance = plugin_instance::<TestedPlugin>();
+        assert_eq!(instance.smoke_test(), Ok(()));
+        assert_eq!(SEEN.with(Cell::get), Some((44100.0, 512, true)));
+        assert!(!instance.is_resumed());
+
+        OUTPUT.with(|output| output.set(f32::NAN));
+        assert_eq!(instance.smoke_test(), Err(ProcessError::NonFiniteOutput { channel: 1 }));
+
+        // The plugin catches the panic itself and outputs silence instead.
+        PANIC.with(|panic| panic.set(true));
+        assert_eq!(instance.smoke_test(), Ok(()));
+    }
+
+    /// Get the chunks loaded into a `ChunkParameters`.
+    fn loaded_chunks() -> Vec<(&'static str, Vec<u8>)> {
+        LOADED_CHUNKS.with(|chunks| chunks.borrow().clone())
+    }
+
+    #[test]
+    fn preset_and_bank_data() {
+        let mut instance = plugin_instance::<ChunkPlugin>();
+        let params = instance.get_parameter_object();
+
+        assert_eq!(params.get_preset_data(), b"preset");
+        assert_eq!(params.get_bank_data(), b"bank");
+
+        params.load_preset_data(b"new preset");
+        params.load_bank_data(b"new bank");
+
+        assert_eq!(
+            loaded_chunks(),
+            vec![("preset", b"new preset".to_vec()), ("bank", b"new bank".to_vec())]
+        );
+    }
+
+    #[test]
+    fn fxp_parameters() {
+        let instance = mock_instance();
+        let fxp = instance.save_fxp();
+
+        assert_eq!(&fxp[0..4], b"CcnK");
+        assert_eq!(
+            i32::from_be_bytes([fxp[4], fxp[5], fxp[6], fxp[7]]) as usize,
+            fxp.len() - 8
+        );
+        assert_eq!(&fxp[8..12], b"FxCk");
+        assert_eq!(&fxp[12..16], &1i32.to_be_bytes());
+        assert_eq!(&fxp[16..20], &1234i32.to_be_bytes());
+        assert_eq!(&fxp[24..28], &2i32.to_be_bytes());
+        assert_eq!(&fxp[56..60], &0.0f32.to_bits().to_be_bytes());
+        assert_eq!(&fxp[60..64], &0.1f32.to_bits().to_be_bytes());
+        assert_eq!(fxp.len(), 64);
+
+        PARAMETERS.with(|calls| calls.borrow_mut().clear());
+        instance.load_fxp(&fxp).unwrap();
+        PARAMETERS.with(|calls| assert_eq!(*calls.borrow(), vec![(0, 0.0), (1, 0.1)]));
+
+        assert_eq!(instance.load_fxp(&fxp[..60]), Err(FxFileError::Truncated));
+        assert_eq!(instance.load_fxp(b"not a preset file"), Err(FxFileError::InvalidMagic));
+        assert_eq!(
+            plugin_instance::<ChunkPlugin>().load_fxp(&fxp),
+            Err(FxFileError::WrongPlugin)
+        );
+
+        // A bank of parameter presets contains a complete preset file per preset.
+        let fxb = instance.save_fxb();
+        assert_eq!(&fxb[8..12], b"FxBk");
+        assert_eq!(&fxb[fxb.len() - fxp.len()..], &fxp[..]);
+
+        PARAMETERS.with(|calls| calls.borrow_mut().clear());
+        instance.load_fxb(&fxb).unwrap();
+        PARAMETERS.with(|calls| assert_eq!(*calls.borrow(), vec![(0, 0.0), (1, 0.1)]));
+    }
+
+    #[test]
+    fn fxp_and_fxb_chunks() {
+        let instance = plugin_instance::<ChunkPlugin>();
+
+        let fxp = instance.save_fxp();
+        assert_eq!(&fxp[8..12], b"FPCh");
+        assert_eq!(&fxp[fxp.len() - 6..], b"preset");
+
+        let fxb = instance.save_fxb();
+        assert_eq!(&fxb[0..4], b"CcnK");
+        assert_eq!(&fxb[8..12], b"FBCh");
+        assert_eq!(&fxb[16..20], &4321i32.to_be_bytes());
+        assert_eq!(&fxb[fxb.len() - 4..], b"bank");
+
+        instance.load_fxp(&fxp).unwrap();
+        instance.load_fxb(&fxb).unwrap();
+        assert_eq!(
+            loaded_chunks(),
+            vec![("preset", b"preset".to_vec()), ("bank", b"bank".to_vec())]
+        );
+
+        assert_eq!(instance.load_fxb(&fxp), Err(FxFileError::InvalidMagic));
+    }
+
+    #[test]
+    fn fxb_parameter_bank() {
+        use std::sync::atomic::{AtomicI32, Ordering};
+
+        /// Three presets of one parameter each.
+        #[derive(Default)]
+        struct BankParameters {
+            preset: AtomicI32,
+            values: Mutex<[f32; 3]>,
+            names: Mutex<[String; 3]>,
+        }
+
+        impl PluginParameters for BankParameters {
+            fn change_preset(&self, preset: i32) {
+                self.preset.store(preset, Ordering::Relaxed);
+            }
+
+            fn get_preset_num(&self) -> i32 {
+                self.preset.load(Ordering::Relaxed)
+            }
+
+            fn set_preset_name(&self, name: String) {
+                self.names.lock().unwrap()[self.get_preset_num() as usize] = name;
+            }
+
+            fn get_preset_name(&self, preset: i32) -> String {
+                self.names.lock().unwrap()[preset as usize].clone()
+            }
+
+            fn get_parameter(&self, _index: i32) -> f32 {
+                self.values.lock().unwrap()[self.get_preset_num() as usize]
+            }
+
+            fn set_parameter(&self, _index: i32, value: f32) {
+                self.values.lock().unwrap()[self.get_preset_num() as usize] = value;
+            }
+        }
+
+        struct BankPlugin;
+
+        impl Plugin for BankPlugin {
+            fn new(_host: HostCallback) -> Self {
+                BankPlugin
+            }
+
+            fn get_info(&self) -> Info {
+                Info {
+                    presets: 3,
+                    parameters: 1,
+                    ..Default::default()
+                }
+            }
+
+            fn get_parameter_object(&mut self) -> Arc<dyn PluginParameters> {
+                Arc::new(BankParameters::default())
+            }
+        }
+
+        let mut instance = plugin_instance::<BankPlugin>();
+        let params = instance.get_parameter_object();
+        let bank = || {
+            let current = params.get_preset_num();
+            let bank: Vec<_> = (0..3)
+                .map(|preset| {
+                    params.change_preset(preset);
+                    (params.get_preset_name(preset), params.get_parameter(0))
+                })
+                .collect();
+            params.change_preset(current);
+            bank
+        };
+
+        let names = ["Bass", "Größe", "Straße"];
+        for (preset, name) in names.iter().enumerate() {
+            params.change_preset(preset as i32);
+            params.set_preset_name(name.to_string());
+            params.set_parameter(0, preset as f32 / 4.0);
+        }
+        params.change_preset(1);
+        let fxb = instance.save_fxb();
+        let saved = vec![
+            ("Bass".to_string(), 0.0),
+            ("Größe".to_string(), 0.25),
+            ("Straße".to_string(), 0.5),
+        ];
+
+        // Nothing is loaded from a file which turns out to be truncated.
+        for preset in 0..3 {
+            params.change_preset(preset);
+            params.set_preset_name(String::new());
+            params.set_parameter(0, 1.0);
+        }
+        params.change_preset(2);
+        let unchanged = bank();
+        assert_eq!(instance.load_fxb(&fxb[..fxb.len() - 4]), Err(FxFileError::Truncated));
+        assert_eq!(bank(), unchanged);
+        assert_eq!(params.get_preset_num(), 2);
+
+        instance.load_fxb(&fxb).unwrap();
+        assert_eq!(bank(), saved);
+        assert_eq!(params.get_preset_num(), 2);
+
+        // A single preset keeps its name the same way.
+        params.change_preset(1);
+        let fxp = instance.save_fxp();
+        params.set_preset_name(String::new());
+        instance.load_fxp(&fxp).unwrap();
+        assert_eq!(params.get_preset_name(1), "Größe");
+
+        // 14 two byte characters do not fit in the 27 bytes available for a name.
+        let name = super::fx_preset_name(&"ä".repeat(14));
+        assert_eq!(std::str::from_utf8(&name[..26]), Ok(&*"ä".repeat(13)));
+        assert_eq!(name[26..], [0, 0]);
+    }
+
+    #[test]
+    fn set_parameter_as_gesture() {
+        #[derive(Default)]
+        struct GestureHost {
+            calls: RefCell<Vec<(&'static str, i32, f32)>>,
+        }
+
+        impl Host for GestureHost {
+            fn automate(&self, index: i32, value: f32) {
+                let set = PARAMETERS.with(|calls| calls.borrow().clone());
+                assert_eq!(set, vec![(index, value)], "parameter must be set before automate");
+                self.calls.borrow_mut().push(("automate", index, value));
+            }
+
+            fn begin_edit(&self, index: i32) {
+                assert!(PARAMETERS.with(|calls| calls.borrow().is_empty()));
+                self.calls.borrow_mut().push(("begin_edit", index, 0.0));
+            }
+
+            fn end_edit(&self, index: i32) {
+                self.calls.borrow_mut().push(("end_edit", index, 0.0));
+            }
+        }
+
+        let instance = mock_instance();
+        let host = GestureHost::default();
+        instance.set_parameter_as_gesture(&host, 1, 0.75);
+
+        assert_eq!(
+            *host.calls.borrow(),
+            vec![("begin_edit", 1, 0.0), ("automate", 1, 0.75), ("end_edit", 1, 0.0)]
+        );
+        assert_eq!(PARAMETERS.with(|calls| calls.borrow().clone()), vec![(1, 0.75)]);
+    }
+
+    #[test]
+    fn process_with_events() {
+        let mut instance = mock_instance();
+        let events = crate::api::Events {
+            num_events: 0,
+            _reserved: 0,
+            events: [ptr::null_mut(); 2],
+        };
+
+        let input = vec![0.5; 64];
+        let mut left = vec![0.0; 64];
+        let mut right = vec![0.0; 64];
+        let mut host_buffer: HostBuffer<f32> = HostBuffer::new(2, 2);
+        let mut buffer = host_buffer.bind(&[&input, &input], &mut [&mut left, &mut right]);
+        instance.process_with_events(&mut buffer, &events);
+
+        let calls = DISPATCHED.with(|calls| calls.borrow().clone());
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].0, OpCode::ProcessEvents.into());
+        assert_eq!(PROCESSED.with(|calls| calls.borrow().clone()), vec![1]);
+        assert_eq!(left, input);
+    }
+
+    #[test]
+    fn host_buffer() {
+        const LENGTH: usize = 1_000_000;
+        let mut host_buffer: HostBuffer<f32> = HostBuffer::new(2, 2);
+        let input_left = vec![1.0; LENGTH];
+        let input_right = vec![1.0; LENGTH];
+        let mut output_left = vec![0.0; LENGTH];
+        let mut output_right = vec![0.0; LENGTH];
         {
             let mut audio_buffer = {
                 // Slices given to `bind` need not persist, but the sample arrays do.
@@ -959,4 +3225,166 @@ mod tests {
         assert_eq!(output_left, vec![2.0; LENGTH]);
         assert_eq!(output_right, vec![2.0; LENGTH]);
     }
+
+    #[test]
+    fn host_buffer_bind_checked() {
+        let mut host_buffer: HostBuffer<f32> = HostBuffer::new(2, 2);
+        let inputs = vec![vec![0.0; 64], vec![0.0; 64]];
+
+        let mut outputs = vec![vec![0.0; 64], vec![0.0; 32]];
+        assert_eq!(
+            host_buffer.bind_checked(&inputs, &mut outputs).err(),
+            Some(BindError::MismatchingOutputLengths)
+        );
+
+        let mismatching_inputs = vec![vec![0.0; 64], vec![0.0; 32]];
+        let mut outputs = vec![vec![0.0; 64], vec![0.0; 64]];
+        assert_eq!(
+            host_buffer.bind_checked(&mismatching_inputs, &mut outputs).err(),
+            Some(BindError::MismatchingInputLengths)
+        );
+
+        let mut too_many_outputs = vec![vec![0.0; 64]; 3];
+        assert_eq!(
+            host_buffer.bind_checked(&inputs, &mut too_many_outputs).err(),
+            Some(BindError::TooManyOutputs)
+        );
+
+        let buffer = host_buffer.bind_checked(&inputs, &mut outputs).unwrap();
+        assert_eq!(buffer.samples(), 64);
+    }
+
+    #[test]
+    #[should_panic(expected = "Mismatching lengths of input arrays")]
+    fn host_buffer_bind_mismatching_lengths() {
+        let mut host_buffer: HostBuffer<f32> = HostBuffer::new(2, 0);
+        let inputs = vec![vec![0.0; 64], vec![0.0; 32]];
+        let mut outputs: Vec<Vec<f32>> = Vec::new();
+        host_buffer.bind(&inputs, &mut outputs);
+    }
+
+    #[test]
+    fn host_buffer_bind_ragged() {
+        let mut host_buffer: HostBuffer<f32> = HostBuffer::new(2, 2);
+        let inputs = vec![vec![1.0; 64], vec![1.0; 48]];
+        let mut outputs = vec![vec![0.0; 32], vec![0.0; 64]];
+        {
+            let mut buffer = host_buffer.bind_ragged(&inputs, &mut outputs);
+            assert_eq!(buffer.samples(), 32);
+            for (input, output) in buffer.zip() {
+                output.copy_from_slice(input);
+            }
+        }
+        assert_eq!(outputs[0], vec![1.0; 32]);
+        assert_eq!(&outputs[1][..32], &[1.0; 32][..]);
+        assert_eq!(&outputs[1][32..], &[0.0; 32][..]);
+
+        let mut outputs: Vec<Vec<f32>> = Vec::new();
+        assert_eq!(host_buffer.bind_ragged(&inputs[..0], &mut outputs).samples(), 0);
+    }
+
+    #[test]
+    fn host_buffer_bind_interleaved() {
+        let mut host_buffer: HostBuffer<f32> = HostBuffer::new(2, 2);
+        let input: Vec<f32> = (0..128).map(|x| x as f32).collect();
+        let mut output = vec![0.0; 128];
+
+        let left: Vec<f32> = input.iter().step_by(2).cloned().collect();
+        let right: Vec<f32> = input.iter().skip(1).step_by(2).cloned().collect();
+
+        {
+            let mut buffer = host_buffer.bind_interleaved(&input, &mut output, 2);
+            assert_eq!(buffer.samples(), 64);
+
+            let (inputs, mut outputs) = buffer.split();
+            assert_eq!(inputs.get(0), &left[..]);
+            assert_eq!(inputs.get(1), &right[..]);
+
+            // Swap the channels.
+            outputs.get_mut(0).copy_from_slice(inputs.get(1));
+            outputs.get_mut(1).copy_from_slice(inputs.get(0));
+        }
+
+        let swapped: Vec<f32> = right.iter().zip(&left).flat_map(|(&r, &l)| vec![r, l]).collect();
+        assert_eq!(output, swapped);
+    }
+
+    #[test]
+    #[should_panic(expected = "Mismatching lengths of interleaved arrays")]
+    fn host_buffer_bind_interleaved_odd_length() {
+        let mut host_buffer: HostBuffer<f32> = HostBuffer::new(2, 2);
+        let input = vec![0.0; 63];
+        let mut output = vec![0.0; 63];
+        host_buffer.bind_interleaved(&input, &mut output, 2);
+    }
+
+    #[test]
+    fn host_buffer_pool() {
+        let pool: HostBufferPool<f32> = HostBufferPool::new();
+
+        let (inputs_ptr, outputs_ptr) = {
+            let buffer = pool.get(2, 2);
+            (buffer.inputs.as_ptr(), buffer.outputs.as_ptr())
+        };
+
+        // The same allocations are handed out again for the same channel count.
+        for _ in 0..3 {
+            let mut buffer = pool.get(2, 2);
+            assert_eq!(buffer.inputs.as_ptr(), inputs_ptr);
+            assert_eq!(buffer.outputs.as_ptr(), outputs_ptr);
+
+            let inputs = vec![vec![1.0; 16]; 2];
+            let mut outputs = vec![vec![0.0; 16]; 2];
+            assert_eq!(buffer.bind(&inputs, &mut outputs).samples(), 16);
+        }
+
+        // Buffers in use are not shared, and other channel counts get their own buffers.
+        let first = pool.get(2, 2);
+        let second = pool.get(2, 2);
+        assert_ne!(first.inputs.as_ptr(), second.inputs.as_ptr());
+
+        let mono = pool.get(1, 1);
+        assert_eq!((mono.input_count(), mono.output_count()), (1, 1));
+    }
+
+    #[test]
+    fn cached_time_info() {
+        struct CountingHost {
+            sample_pos: Cell<f64>,
+            computed: Cell<usize>,
+            time_info: CachedTimeInfo,
+        }
+
+        impl Host for CountingHost {
+            fn get_time_info(&self, _mask: i32) -> Option<TimeInfo> {
+                let sample_pos = self.sample_pos.get();
+                self.time_info.get_or_compute(sample_pos, || {
+                    self.computed.set(self.computed.get() + 1);
+                    Some(TimeInfo {
+                        sample_pos,
+                        ..Default::default()
+                    })
+                })
+            }
+        }
+
+        let host = CountingHost {
+            sample_pos: Cell::new(0.0),
+            computed: Cell::new(0),
+            time_info: CachedTimeInfo::new(),
+        };
+
+        for block in 0..3u32 {
+            host.sample_pos.set(f64::from(block * 512));
+            for mask in 0..4 {
+                let time_info = host.get_time_info(mask).unwrap();
+                assert_eq!(time_info.sample_pos, f64::from(block * 512));
+            }
+            assert_eq!(host.computed.get(), block as usize + 1);
+        }
+
+        host.time_info.invalidate();
+        host.get_time_info(0);
+        assert_eq!(host.computed.get(), 4);
+    }
 }
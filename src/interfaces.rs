@@ -4,15 +4,22 @@
 
 use std::cell::Cell;
 use std::os::raw::{c_char, c_void};
+use std::panic::{self, AssertUnwindSafe};
 use std::{mem, slice};
 
+use num_traits::Float;
+
 use crate::{
     api::{self, consts::*, AEffect, TimeInfo},
     buffer::AudioBuffer,
+    cache::dispatch_with_info,
     editor::{Key, KeyCode, KnobMode, Rect},
     host::Host,
 };
 
+#[cfg(feature = "strict_threading")]
+use crate::threading::ProcessThreadToken;
+
 /// Deprecated process function.
 pub extern "C" fn process_deprecated(
     _effect: *mut AEffect,
@@ -31,11 +38,29 @@ pub extern "C" fn process_replacing(
 ) {
     // Handle to the VST
     let plugin = unsafe { (*effect).get_plugin() };
-    let info = unsafe { (*effect).get_info() };
-    let (input_count, output_count) = (info.inputs as usize, info.outputs as usize);
-    let mut buffer =
-        unsafe { AudioBuffer::from_raw(input_count, output_count, raw_inputs, raw_outputs, samples as usize) };
-    plugin.process(&mut buffer);
+    let cache = unsafe { (*effect).get_process_cache() };
+    cache.check_processing();
+    let (input_count, output_count) = cache.channel_counts(unsafe { &*effect });
+    // The buffer is created within `catch_unwind`, as it checks the host's pointers in debug builds.
+    let mut buffer = None;
+    let process = || {
+        let buffer = buffer.insert(unsafe {
+            AudioBuffer::from_raw(input_count, output_count, raw_inputs, raw_outputs, samples as usize)
+        });
+        #[cfg(not(feature = "strict_threading"))]
+        plugin.process(buffer);
+        #[cfg(feature = "strict_threading")]
+        plugin.process_with_token(buffer, &ProcessThreadToken::new());
+    };
+    if panic::catch_unwind(AssertUnwindSafe(process)).is_err() {
+        match buffer.as_mut() {
+            Some(buffer) => {
+                error!("VST: Plugin panicked while processing, outputting silence");
+                silence(buffer);
+            }
+            None => error!("VST: Host passed invalid channels for processing"),
+        }
+    }
 }
 
 /// VST2.4 replacing function with `f64` values.
@@ -46,11 +71,39 @@ pub extern "C" fn process_replacing_f64(
     samples: i32,
 ) {
     let plugin = unsafe { (*effect).get_plugin() };
-    let info = unsafe { (*effect).get_info() };
-    let (input_count, output_count) = (info.inputs as usize, info.outputs as usize);
-    let mut buffer =
-        unsafe { AudioBuffer::from_raw(input_count, output_count, raw_inputs, raw_outputs, samples as usize) };
-    plugin.process_f64(&mut buffer);
+    let cache = unsafe { (*effect).get_process_cache() };
+    cache.check_processing();
+    let (input_count, output_count) = cache.channel_counts(unsafe { &*effect });
+    // The buffer is created within `catch_unwind`, as it checks the host's pointers in debug builds.
+    let mut buffer = None;
+    let process = || {
+        let buffer = buffer.insert(unsafe {
+            AudioBuffer::from_raw(input_count, output_count, raw_inputs, raw_outputs, samples as usize)
+        });
+        #[cfg(not(feature = "strict_threading"))]
+        plugin.process_f64(buffer);
+        #[cfg(feature = "strict_threading")]
+        plugin.process_f64_with_token(buffer, &ProcessThreadToken::new());
+    };
+    if panic::catch_unwind(AssertUnwindSafe(process)).is_err() {
+        match buffer.as_mut() {
+            Some(buffer) => {
+                error!("VST: Plugin panicked while processing, outputting silence");
+                silence(buffer);
+            }
+            None => error!("VST: Host passed invalid channels for processing"),
+        }
+    }
+}
+
+/// Fill the outputs of `buffer` with silence.
+///
+/// A panic must not unwind into the host, so the processing functions catch it and use this to
+/// replace whatever the plugin left in the outputs.
+fn silence<T: Float>(buffer: &mut AudioBuffer<T>) {
+    for output in buffer.split().1.into_iter() {
+        output.fill(T::zero());
+    }
 }
 
 /// VST2.4 set parameter function.
@@ -65,15 +118,22 @@ pub extern "C" fn get_parameter(effect: *mut AEffect, index: i32) -> f32 {
 
 /// Copy a string into a destination buffer.
 ///
-/// String will be cut at `max` characters.
+/// The buffer is `max` bytes long. The string is cut at the last character boundary that leaves
+/// room for the null terminator.
 fn copy_string(dst: *mut c_void, src: &str, max: usize) -> isize {
     unsafe {
         use libc::{memcpy, memset};
         use std::cmp::min;
 
+        // Leave room for the null terminator and don't split a multi-byte character
+        let mut len = min(max.saturating_sub(1), src.len());
+        while !src.is_char_boundary(len) {
+            len -= 1;
+        }
+
         let dst = dst as *mut c_void;
         memset(dst, 0, max);
-        memcpy(dst, src.as_ptr() as *const c_void, min(max, src.as_bytes().len()));
+        memcpy(dst, src.as_ptr() as *const c_void, len);
     }
 
     1 // Success
@@ -97,6 +157,8 @@ pub extern "C" fn dispatch(
     let get_plugin = || unsafe { (*effect).get_plugin() };
     let get_editor = || unsafe { (*effect).get_editor() };
     let params = unsafe { (*effect).get_params() };
+    // The `Info` cached when the plugin was created, so hosts querying it don't rebuild it.
+    let info = unsafe { (*effect).get_info() };
 
     match opcode {
         Ok(OpCode::Initialize) => get_plugin().init(),
@@ -107,7 +169,7 @@ pub extern "C" fn dispatch(
 
         Ok(OpCode::ChangePreset) => params.change_preset(value as i32),
         Ok(OpCode::GetCurrentPresetNum) => return params.get_preset_num() as isize,
-        Ok(OpCode::SetCurrentPresetName) => params.set_preset_name(read_string(ptr)),
+        Ok(OpCode::SetCurrentPresetName) => params.set_preset_name(read_string_max(ptr, MAX_PRESET_NAME_LEN)),
         Ok(OpCode::GetCurrentPresetName) => {
             let num = params.get_preset_num();
             return copy_string(ptr, &params.get_preset_name(num), MAX_PRESET_NAME_LEN);
@@ -121,17 +183,28 @@ pub extern "C" fn dispatch(
         }
         Ok(OpCode::GetParameterName) => return copy_string(ptr, &params.get_parameter_name(index), MAX_PARAM_STR_LEN),
 
-        Ok(OpCode::SetSampleRate) => get_plugin().set_sample_rate(opt),
-        Ok(OpCode::SetBlockSize) => get_plugin().set_block_size(value as i64),
+        Ok(OpCode::SetSampleRate) => {
+            unsafe { (*effect).get_process_cache() }.check_suspended("set_sample_rate");
+            get_plugin().set_sample_rate(opt)
+        }
+        Ok(OpCode::SetBlockSize) => {
+            unsafe { (*effect).get_process_cache() }.check_suspended("set_block_size");
+            get_plugin().set_block_size(value as i64)
+        }
         Ok(OpCode::StateChanged) => {
+            let cache = unsafe { (*effect).get_cache() };
             if value == 1 {
+                cache.update_initial_delay();
                 get_plugin().resume();
             } else {
                 get_plugin().suspend();
+                cache.update_initial_delay();
             }
+            cache.process.set_resumed(value == 1);
         }
 
         Ok(OpCode::EditorGetRect) => {
+            unsafe { (*effect).get_cache().create_lazy_editor() };
             if let Some(ref mut editor) = get_editor() {
                 let size = editor.size();
                 let pos = editor.position();
@@ -151,11 +224,13 @@ pub extern "C" fn dispatch(
             }
         }
         Ok(OpCode::EditorOpen) => {
+            unsafe { (*effect).get_cache().create_lazy_editor() };
             if let Some(ref mut editor) = get_editor() {
                 // `ptr` is a window handle to the parent window.
                 // See the documentation for `Editor::open` for details.
-                if editor.open(ptr) {
-                    return 1;
+                match editor.open_result(ptr) {
+                    Ok(()) => return 1,
+                    Err(err) => warn!("VST: Failed to open editor: {}", err),
                 }
             }
         }
@@ -166,11 +241,10 @@ pub extern "C" fn dispatch(
         }
 
         Ok(OpCode::EditorIdle) => {
-            if let Some(ref mut editor) = get_editor() {
-                editor.idle();
-            }
+            unsafe { (*effect).get_cache().idle_editor() };
         }
 
+        // An index of 0 refers to the whole bank, any other index (usually 1) to the current preset.
         Ok(OpCode::GetData) => {
             let mut chunks = if index == 0 {
                 params.get_bank_data()
@@ -199,7 +273,11 @@ pub extern "C" fn dispatch(
         }
 
         Ok(OpCode::ProcessEvents) => {
-            get_plugin().process_events(unsafe { &*(ptr as *const api::Events) });
+            let events = unsafe { &*(ptr as *const api::Events) };
+            #[cfg(not(feature = "strict_threading"))]
+            get_plugin().process_events(events);
+            #[cfg(feature = "strict_threading")]
+            get_plugin().process_events_with_token(events, &ProcessThreadToken::new());
         }
         Ok(OpCode::CanBeAutomated) => return params.can_be_automated(index) as isize,
         Ok(OpCode::StringToParameter) => return params.string_to_parameter(index, read_string(ptr)) as isize,
@@ -207,7 +285,7 @@ pub extern "C" fn dispatch(
         Ok(OpCode::GetPresetName) => return copy_string(ptr, &params.get_preset_name(index), MAX_PRESET_NAME_LEN),
 
         Ok(OpCode::GetInputInfo) => {
-            if index >= 0 && index < get_plugin().get_info().inputs {
+            if index >= 0 && index < info.inputs {
                 unsafe {
                     let ptr = ptr as *mut api::ChannelProperties;
                     *ptr = get_plugin().get_input_info(index).into();
@@ -215,7 +293,7 @@ pub extern "C" fn dispatch(
             }
         }
         Ok(OpCode::GetOutputInfo) => {
-            if index >= 0 && index < get_plugin().get_info().outputs {
+            if index >= 0 && index < info.outputs {
                 unsafe {
                     let ptr = ptr as *mut api::ChannelProperties;
                     *ptr = get_plugin().get_output_info(index).into();
@@ -223,18 +301,24 @@ pub extern "C" fn dispatch(
             }
         }
         Ok(OpCode::GetCategory) => {
-            return get_plugin().get_info().category.into();
+            return info.category.into();
         }
 
-        Ok(OpCode::GetEffectName) => return copy_string(ptr, &get_plugin().get_info().name, MAX_VENDOR_STR_LEN),
+        Ok(OpCode::GetEffectName) => return copy_string(ptr, &info.name, MAX_EFFECT_NAME_LEN),
 
-        Ok(OpCode::GetVendorName) => return copy_string(ptr, &get_plugin().get_info().vendor, MAX_VENDOR_STR_LEN),
-        Ok(OpCode::GetProductName) => return copy_string(ptr, &get_plugin().get_info().name, MAX_PRODUCT_STR_LEN),
-        Ok(OpCode::GetVendorVersion) => return get_plugin().get_info().version as isize,
+        Ok(OpCode::GetVendorName) => return copy_string(ptr, &info.vendor, MAX_VENDOR_STR_LEN),
+        Ok(OpCode::GetProductName) => return copy_string(ptr, &info.name, MAX_PRODUCT_STR_LEN),
+        Ok(OpCode::GetVendorVersion) => {
+            return if info.vendor_version != 0 {
+                info.vendor_version
+            } else {
+                info.version
+            } as isize;
+        }
         Ok(OpCode::VendorSpecific) => return get_plugin().vendor_specific(index, value, ptr, opt),
         Ok(OpCode::CanDo) => {
-            let can_do = CanDo::from_str(&read_string(ptr));
-            return get_plugin().can_do(can_do).into();
+            let can_do = CanDo::from(read_string_max(ptr, MAX_CAN_DO_STR_LEN).as_str());
+            return dispatch_with_info(&mut **get_plugin(), info, |plugin| plugin.can_do(can_do)).into();
         }
         Ok(OpCode::GetTailSize) => {
             if get_plugin().get_tail_size() == 0 {
@@ -244,35 +328,71 @@ pub extern "C" fn dispatch(
             }
         }
 
-        //OpCode::GetParamInfo => { /*TODO*/ }
-        Ok(OpCode::GetApiVersion) => return 2400,
+        Ok(OpCode::GetParamInfo) => {
+            if ptr.is_null() || index < 0 || index >= info.parameters {
+                return 0;
+            }
+            match params.parameter_is_stepped(index) {
+                Some(steps) if steps >= 2 => {
+                    let mut flags = api::ParameterFlags::USES_INTEGER_MIN_MAX | api::ParameterFlags::USES_INT_STEP;
+                    if steps == 2 {
+                        flags |= api::ParameterFlags::IS_SWITCH;
+                    }
+                    unsafe {
+                        let properties = ptr as *mut api::ParameterProperties;
+                        *properties = mem::zeroed();
+                        (*properties).flags = flags.bits();
+                        (*properties).max_integer = (steps - 1).min(i32::MAX as u32) as i32;
+                        (*properties).step_integer = 1;
+                        (*properties).large_step_integer = 1;
+                    }
+                    return 1;
+                }
+                _ => return 0,
+            }
+        }
+        Ok(OpCode::GetApiVersion) => return get_plugin().get_vst_version() as isize,
 
         Ok(OpCode::EditorKeyDown) => {
             if let Some(ref mut editor) = get_editor() {
-                if let Ok(key) = Key::try_from(value) {
-                    editor.key_down(KeyCode {
-                        character: index as u8 as char,
-                        key,
-                        modifier: opt.to_bits() as u8,
-                    });
+                match Key::try_from(value) {
+                    Ok(key) => {
+                        if editor.key_down(KeyCode {
+                            character: index as u8 as char,
+                            key,
+                            modifier: opt.to_bits() as u8,
+                        }) {
+                            return 1;
+                        }
+                    }
+                    Err(_) => trace!("VST: Got unknown key code ({})", value),
                 }
             }
         }
         Ok(OpCode::EditorKeyUp) => {
             if let Some(ref mut editor) = get_editor() {
-                if let Ok(key) = Key::try_from(value) {
-                    editor.key_up(KeyCode {
-                        character: index as u8 as char,
-                        key,
-                        modifier: opt.to_bits() as u8,
-                    });
+                match Key::try_from(value) {
+                    Ok(key) => {
+                        if editor.key_up(KeyCode {
+                            character: index as u8 as char,
+                            key,
+                            modifier: opt.to_bits() as u8,
+                        }) {
+                            return 1;
+                        }
+                    }
+                    Err(_) => trace!("VST: Got unknown key code ({})", value),
                 }
             }
         }
+        Ok(OpCode::_KeysRequired) => {
+            let wants_keyboard = get_editor().as_ref().is_some_and(|editor| editor.wants_keyboard());
+            return if wants_keyboard { 0 } else { 1 };
+        }
         Ok(OpCode::EditorSetKnobMode) => {
             if let Some(ref mut editor) = get_editor() {
-                if let Ok(knob_mode) = KnobMode::try_from(value) {
-                    editor.set_knob_mode(knob_mode);
+                if editor.set_knob_mode(KnobMode::from_raw(value)) {
+                    return 1;
                 }
             }
         }
@@ -280,8 +400,12 @@ pub extern "C" fn dispatch(
         Ok(OpCode::StartProcess) => get_plugin().start_process(),
         Ok(OpCode::StopProcess) => get_plugin().stop_process(),
 
-        Ok(OpCode::GetNumMidiInputs) => return unsafe { (*effect).get_info() }.midi_inputs as isize,
-        Ok(OpCode::GetNumMidiOutputs) => return unsafe { (*effect).get_info() }.midi_outputs as isize,
+        Ok(OpCode::GetNumMidiInputs) => {
+            return dispatch_with_info(&mut **get_plugin(), info, |plugin| plugin.get_num_midi_inputs()) as isize
+        }
+        Ok(OpCode::GetNumMidiOutputs) => {
+            return dispatch_with_info(&mut **get_plugin(), info, |plugin| plugin.get_num_midi_outputs()) as isize
+        }
 
         _ => {
             debug!("Unimplemented opcode ({:?})", opcode);
@@ -317,10 +441,15 @@ pub fn host_dispatch(
         Ok(OpCode::EndEdit) => host.end_edit(index),
 
         Ok(OpCode::Idle) => host.idle(),
+        Ok(OpCode::CurrentId) => return host.get_plugin_id() as isize,
+        Ok(OpCode::SizeWindow) => return host.size_window(index, value as i32) as isize,
 
         // ...
         Ok(OpCode::CanDo) => {
-            info!("Plugin is asking if host can: {}.", read_string(ptr));
+            info!(
+                "Plugin is asking if host can: {}.",
+                read_string_max(ptr, MAX_CAN_DO_STR_LEN)
+            );
         }
 
         Ok(OpCode::GetVendorVersion) => return host.get_info().0,
@@ -346,6 +475,14 @@ pub fn host_dispatch(
             };
         }
         Ok(OpCode::GetBlockSize) => return host.get_block_size(),
+        Ok(OpCode::GetSampleRate) => return host.get_sample_rate() as isize,
+
+        Ok(OpCode::VendorSpecific) if index == CHAIN_POSITION_VENDOR_INDEX && !ptr.is_null() => {
+            if let Some((position, total)) = host.get_plugin_chain_position() {
+                unsafe { *(ptr as *mut [usize; 2]) = [position, total] };
+                return 1;
+            }
+        }
 
         _ => {
             trace!("VST: Got unimplemented host opcode ({:?})", opcode);
@@ -362,9 +499,266 @@ pub fn host_dispatch(
     0
 }
 
+// The VST API does not limit the length of can-do strings, the longest known ones are well below
+// this.
+const MAX_CAN_DO_STR_LEN: usize = 64;
+
 // Read a string from the `ptr` buffer
 fn read_string(ptr: *mut c_void) -> String {
     use std::ffi::CStr;
 
     String::from_utf8_lossy(unsafe { CStr::from_ptr(ptr as *mut c_char).to_bytes() }).into_owned()
 }
+
+// Read a string of at most `max` bytes from the `ptr` buffer, which does not need to be null
+// terminated if it is `max` bytes long.
+fn read_string_max(ptr: *mut c_void, max: usize) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+
+    // Don't read past the null terminator, the buffer may be shorter than `max`
+    let ptr = ptr as *const u8;
+    let len = (0..max).position(|i| unsafe { *ptr.add(i) } == 0).unwrap_or(max);
+    String::from_utf8_lossy(unsafe { slice::from_raw_parts(ptr, len) }).into_owned()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::os::raw::c_void;
+    use std::ptr;
+
+    use super::{copy_string, read_string_max};
+    use crate::api::AEffect;
+    use crate::plugin::{OpCode, Plugin};
+
+    /// A plugin created through `crate::main`, which is shut down when this is dropped.
+    pub(crate) struct TestEffect(pub *mut AEffect);
+
+    impl TestEffect {
+        /// Create a plugin of type `P`, with a host which only answers the version query in `main`.
+        pub fn new<P: Plugin>() -> TestEffect {
+            extern "C" fn callback(_: *mut AEffect, _: i32, _: i32, _: isize, _: *mut c_void, _: f32) -> isize {
+                2400
+            }
+
+            TestEffect(crate::main::<P>(callback))
+        }
+
+        /// Dispatch `opcode` with `value` and a null pointer to the plugin.
+        pub fn dispatch(&self, opcode: OpCode, value: isize) -> isize {
+            unsafe { ((*self.0).dispatcher)(self.0, opcode.into(), 0, value, ptr::null_mut(), 0.0) }
+        }
+
+        /// Process `samples` samples from `inputs` into `outputs` with `processReplacing`.
+        pub fn process(&self, inputs: &[&[f32]], outputs: &mut [&mut [f32]], samples: usize) {
+            let inputs: Vec<*const f32> = inputs.iter().map(|input| input.as_ptr()).collect();
+            let mut outputs: Vec<*mut f32> = outputs.iter_mut().map(|output| output.as_mut_ptr()).collect();
+            unsafe { ((*self.0).processReplacing)(self.0, inputs.as_ptr(), outputs.as_mut_ptr(), samples as i32) };
+        }
+    }
+
+    impl Drop for TestEffect {
+        fn drop(&mut self) {
+            self.dispatch(OpCode::Shutdown, 0);
+        }
+    }
+
+    #[test]
+    fn read_string_max_unterminated() {
+        let mut buffer = *b"receiveVstEvents";
+        let ptr = buffer.as_mut_ptr() as *mut c_void;
+
+        assert_eq!(read_string_max(ptr, buffer.len()), "receiveVstEvents");
+        assert_eq!(read_string_max(ptr, 7), "receive");
+    }
+
+    #[test]
+    fn read_string_max_terminated() {
+        let mut buffer = *b"bypass\0garbage";
+        let ptr = buffer.as_mut_ptr() as *mut c_void;
+
+        assert_eq!(read_string_max(ptr, buffer.len()), "bypass");
+        assert_eq!(read_string_max(std::ptr::null_mut(), 8), "");
+    }
+
+    #[test]
+    fn copy_string_char_boundary() {
+        let mut buffer = [0xffu8; 8];
+        let ptr = buffer.as_mut_ptr() as *mut c_void;
+
+        // The 'é' occupies bytes 6 and 7, so it doesn't fit before the null terminator
+        copy_string(ptr, "Preseté", buffer.len());
+        assert_eq!(&buffer, b"Preset\0\0");
+
+        copy_string(ptr, "Presets!", buffer.len());
+        assert_eq!(&buffer, b"Presets\0");
+    }
+
+    /// A plugin whose `process` panics outputs silence instead of unwinding into the host.
+    #[test]
+    fn process_panic_is_caught() {
+        use crate::buffer::AudioBuffer;
+        use crate::plugin::{HostCallback, Info};
+
+        struct PanickingPlugin;
+
+        impl Plugin for PanickingPlugin {
+            fn new(_host: HostCallback) -> Self {
+                PanickingPlugin
+            }
+
+            fn get_info(&self) -> Info {
+                Info {
+                    inputs: 0,
+                    outputs: 1,
+                    ..Default::default()
+                }
+            }
+
+            fn process(&mut self, buffer: &mut AudioBuffer<f32>) {
+                buffer.split().1[0][0] = 1.0;
+                panic!("process panicked");
+            }
+        }
+
+        let effect = TestEffect::new::<PanickingPlugin>();
+        let mut output = [0.5f32; 16];
+        effect.process(&[], &mut [&mut output], 16);
+        assert_eq!(output, [0.0; 16]);
+    }
+
+    /// Null channel pointers from the host are caught in debug builds instead of unwinding into
+    /// the host.
+    #[cfg(debug_assertions)]
+    #[test]
+    fn process_invalid_channels_are_caught() {
+        use crate::plugin::{HostCallback, Info};
+
+        struct MonoPlugin;
+
+        impl Plugin for MonoPlugin {
+            fn new(_host: HostCallback) -> Self {
+                MonoPlugin
+            }
+
+            fn get_info(&self) -> Info {
+                Info {
+                    inputs: 0,
+                    outputs: 1,
+                    ..Default::default()
+                }
+            }
+        }
+
+        let effect = TestEffect::new::<MonoPlugin>();
+        let mut outputs = [std::ptr::null_mut::<f32>()];
+        unsafe { ((*effect.0).processReplacing)(effect.0, std::ptr::null(), outputs.as_mut_ptr(), 16) };
+    }
+
+    /// A plugin declaring more outputs than the host allocated only gets the allocated ones.
+    #[test]
+    fn process_clamps_channel_counts() {
+        use std::cell::Cell;
+
+        use crate::buffer::AudioBuffer;
+        use crate::plugin::{HostCallback, Info};
+
+        thread_local! {
+            static OUTPUT_COUNT: Cell<Option<usize>> = Cell::new(None);
+        }
+
+        struct WidePlugin;
+
+        impl Plugin for WidePlugin {
+            fn new(_host: HostCallback) -> Self {
+                WidePlugin
+            }
+
+            fn get_info(&self) -> Info {
+                Info {
+                    inputs: 0,
+                    outputs: 8,
+                    ..Default::default()
+                }
+            }
+
+            fn process(&mut self, buffer: &mut AudioBuffer<f32>) {
+                OUTPUT_COUNT.with(|count| count.set(Some(buffer.output_count())));
+                for output in buffer.split().1.into_iter() {
+                    output.fill(1.0);
+                }
+            }
+        }
+
+        let effect = TestEffect::new::<WidePlugin>();
+        // The host only allocates as many outputs as the `AEffect` reports.
+        unsafe { (*effect.0).numOutputs = 2 };
+        let mut left = [0.0f32; 16];
+        let mut right = [0.0f32; 16];
+
+        effect.process(&[], &mut [&mut left, &mut right], 16);
+        assert_eq!(OUTPUT_COUNT.with(Cell::get), Some(2));
+        assert_eq!(left, [1.0; 16]);
+        assert_eq!(right, [1.0; 16]);
+    }
+
+    /// Out of order calls from the host are reported in debug builds.
+    #[cfg(debug_assertions)]
+    #[test]
+    fn state_machine_warnings() {
+        use crate::cache::take_warnings;
+        use crate::plugin::{HostCallback, Info};
+
+        struct SilentPlugin;
+
+        impl Plugin for SilentPlugin {
+            fn new(_host: HostCallback) -> Self {
+                SilentPlugin
+            }
+
+            fn get_info(&self) -> Info {
+                Info {
+                    inputs: 0,
+                    outputs: 0,
+                    ..Default::default()
+                }
+            }
+        }
+
+        let effect = TestEffect::new::<SilentPlugin>();
+        let dispatch = |opcode: OpCode, value: isize| {
+            effect.dispatch(opcode, value);
+        };
+        let process = || effect.process(&[], &mut [], 16);
+
+        dispatch(OpCode::SetSampleRate, 0);
+        assert!(take_warnings().is_empty());
+
+        // Processing while suspended is only reported once
+        process();
+        process();
+        assert_eq!(
+            take_warnings(),
+            ["VST: Host is processing audio while the plugin is suspended"]
+        );
+
+        dispatch(OpCode::StateChanged, 1);
+        process();
+        assert!(take_warnings().is_empty());
+
+        dispatch(OpCode::SetSampleRate, 0);
+        dispatch(OpCode::SetBlockSize, 64);
+        assert_eq!(
+            take_warnings(),
+            [
+                "VST: Host called set_sample_rate while the plugin is resumed",
+                "VST: Host called set_block_size while the plugin is resumed",
+            ]
+        );
+
+        dispatch(OpCode::StateChanged, 0);
+        drop(effect);
+        assert!(take_warnings().is_empty());
+    }
+}
@@ -129,11 +129,14 @@ pub mod host;
 mod interfaces;
 pub mod plugin;
 pub mod prelude;
+#[cfg(feature = "strict_threading")]
+pub mod threading;
 pub mod util;
 
 use api::consts::VST_MAGIC;
 use api::{AEffect, HostCallbackProc};
 use cache::PluginCache;
+use host::Host;
 use plugin::{HostCallback, Plugin};
 
 /// Exports the necessary symbols for the plugin to be used by a VST host.
@@ -163,6 +166,29 @@ macro_rules! plugin_main {
     };
 }
 
+/// Creates an instance of the plugin `T` and returns a raw pointer to its `AEffect` struct.
+///
+/// This is what the entry points exported by `plugin_main!` call. Use it directly when the plugin
+/// is linked statically into a host (e.g. on iOS), where the host cannot look up exported
+/// symbols and instead calls into the plugin crate itself. The host must pass a callback which
+/// answers `host::OpCode::Version` with a non-zero value, or a null pointer is returned.
+///
+/// ```no_run
+/// # use vst::api::{AEffect, HostCallbackProc};
+/// # use vst::prelude::*;
+/// # struct MyPlugin;
+/// # impl Plugin for MyPlugin {
+/// #     fn new(_host: HostCallback) -> Self { MyPlugin }
+/// #     fn get_info(&self) -> Info { Default::default() }
+/// # }
+/// fn create_plugin(callback: HostCallbackProc) -> *mut AEffect {
+///     vst::export_plugin::<MyPlugin>(callback)
+/// }
+/// ```
+pub fn export_plugin<T: Plugin>(callback: HostCallbackProc) -> *mut AEffect {
+    main::<T>(callback)
+}
+
 /// Initializes a VST plugin and returns a raw pointer to an AEffect struct.
 #[doc(hidden)]
 pub fn main<T: Plugin>(callback: HostCallbackProc) -> *mut AEffect {
@@ -212,12 +238,22 @@ pub fn main<T: Plugin>(callback: HostCallbackProc) -> *mut AEffect {
         // TODO: Better criteria would probably be useful here...
         return ptr::null_mut();
     }
+    // Shell plugins are told which sub-plugin to create through this id, which is only
+    // available from the host while `main` runs.
+    let plugin_id = host.get_plugin_id();
 
     trace!("Creating VST plugin instance...");
-    let mut plugin = T::new(host);
+    let mut plugin = match T::try_new(host) {
+        Ok(plugin) => plugin,
+        Err(err) => {
+            error!("VST: Failed to create plugin: {}", err);
+            drop(unsafe { Box::from_raw(raw_effect) });
+            return ptr::null_mut();
+        }
+    };
     let info = plugin.get_info();
     let params = plugin.get_parameter_object();
-    let editor = plugin.get_editor();
+    let editor = if info.lazy_editor { None } else { plugin.get_editor() };
 
     // Update AEffect in place
     let effect = unsafe { &mut *raw_effect };
@@ -234,7 +270,7 @@ pub fn main<T: Plugin>(callback: HostCallbackProc) -> *mut AEffect {
             flag |= PluginFlags::CAN_DOUBLE_REPLACING;
         }
 
-        if editor.is_some() {
+        if info.lazy_editor || editor.is_some() {
             flag |= PluginFlags::HAS_EDITOR;
         }
 
@@ -254,7 +290,7 @@ pub fn main<T: Plugin>(callback: HostCallbackProc) -> *mut AEffect {
     };
     effect.initialDelay = info.initial_delay;
     effect.object = Box::into_raw(Box::new(Box::new(plugin) as Box<dyn Plugin>)) as *mut _;
-    effect.user = Box::into_raw(Box::new(PluginCache::new(&info, params, editor))) as *mut _;
+    effect.user = Box::into_raw(Box::new(PluginCache::new(&info, params, editor, host, plugin_id))) as *mut _;
     effect.uniqueId = info.unique_id;
     effect.version = info.version;
 
@@ -270,7 +306,7 @@ mod tests {
     use crate::{
         api::{consts::VST_MAGIC, AEffect},
         interfaces,
-        plugin::{HostCallback, Info, Plugin},
+        plugin::{self, HostCallback, Info, Plugin, PluginInitError},
     };
 
     struct TestPlugin;
@@ -339,6 +375,27 @@ mod tests {
         assert_eq!(VSTPluginMain(fail_callback), ptr::null_mut());
     }
 
+    #[test]
+    fn plugin_init_error() {
+        struct FailingPlugin;
+
+        impl Plugin for FailingPlugin {
+            fn new(_host: HostCallback) -> Self {
+                FailingPlugin
+            }
+
+            fn try_new(_host: HostCallback) -> Result<Self, PluginInitError> {
+                Err(PluginInitError::MissingResource("impulse.wav".to_string()))
+            }
+
+            fn get_info(&self) -> Info {
+                Default::default()
+            }
+        }
+
+        assert_eq!(crate::main::<FailingPlugin>(pass_callback), ptr::null_mut());
+    }
+
     #[test]
     fn aeffect_created() {
         let aeffect = VSTPluginMain(pass_callback);
@@ -385,6 +442,23 @@ mod tests {
         assert!(plugin.get_info().name == "Test Plugin");
     }
 
+    #[test]
+    fn export_plugin() {
+        let aeffect = crate::export_plugin::<TestPlugin>(pass_callback);
+        assert!(!aeffect.is_null());
+        assert_eq!(unsafe { (*aeffect).uniqueId }, 5678);
+
+        assert_eq!(crate::export_plugin::<TestPlugin>(fail_callback), ptr::null_mut());
+    }
+
+    #[test]
+    fn vendor_version_defaults_to_version() {
+        let aeffect = VSTPluginMain(pass_callback);
+        let opcode = plugin::OpCode::GetVendorVersion.into();
+        let vendor_version = unsafe { ((*aeffect).dispatcher)(aeffect, opcode, 0, 0, ptr::null_mut(), 0.0) };
+        assert_eq!(vendor_version, 1234);
+    }
+
     #[test]
     fn aeffect_params() {
         // Assert that 2 function pointers are equal.
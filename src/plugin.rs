@@ -2,24 +2,34 @@
 
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt;
 use std::os::raw::c_void;
 use std::ptr;
+use std::str::FromStr;
 use std::sync::Arc;
 
 use crate::{
     api::{self, consts::VST_MAGIC, AEffect, HostCallbackProc, Supported, TimeInfo},
     buffer::AudioBuffer,
+    cache::{with_cached_info, PluginCache},
     channels::ChannelInfo,
     editor::Editor,
     host::{self, Host},
 };
 
+#[cfg(feature = "strict_threading")]
+use crate::threading::ProcessThreadToken;
+
 /// Plugin type. Generally either Effect or Synth.
 ///
 /// Other types are not necessary to build a plugin and are only useful for the host to categorize
-/// the plugin.
+/// the plugin. More categories may be added in the future, so matches outside of this crate need
+/// a wildcard arm.
 #[repr(isize)]
 #[derive(Clone, Copy, Debug, TryFromPrimitive, IntoPrimitive)]
+#[non_exhaustive]
 pub enum Category {
     /// Unknown / not implemented
     Unknown,
@@ -183,7 +193,7 @@ pub enum OpCode {
     /// Soft bypass (automatable).
     /// [value]: 1 = bypass, 0 = nobypass.
     SoftBypass,
-    // [ptr]: buffer for effect name, limited to `kVstMaxEffectNameLen`
+    /// [ptr]: buffer for effect name, limited to `consts::MAX_EFFECT_NAME_LEN`.
     GetEffectName,
 
     /// Deprecated.
@@ -211,11 +221,13 @@ pub enum OpCode {
     _SetVewPosition,
 
     /// [index]: param index
-    /// [ptr]: `*mut VstParamInfo` //TODO: Implement
+    /// [ptr]: `*mut api::ParameterProperties`
     /// [return]: 1 if supported
     GetParamInfo,
 
-    /// Deprecated.
+    /// Deprecated, but still sent by some hosts.
+    /// [return]: 0 if the editor wants keyboard events, 1 if not. This is inverted for
+    /// compatibility with VST 1.0 plugins, which always received keyboard events.
     _KeysRequired,
 
     /// [return]: 2400 for vst 2.4.
@@ -294,10 +306,38 @@ pub enum OpCode {
     GetNumMidiOutputs,
 }
 
+/// The reason a plugin could not be created, returned by `Plugin::try_new`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginInitError {
+    /// The plugin does not support the host it is loaded into.
+    UnsupportedHost,
+
+    /// A resource needed by the plugin, such as a file, could not be loaded.
+    MissingResource(String),
+
+    /// Creating the plugin failed for another reason, described by the message.
+    Other(String),
+}
+
+impl fmt::Display for PluginInitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PluginInitError::UnsupportedHost => write!(f, "Unsupported host"),
+            PluginInitError::MissingResource(resource) => write!(f, "Missing resource: {}", resource),
+            PluginInitError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for PluginInitError {}
+
 /// A structure representing static plugin information.
 #[derive(Clone, Debug)]
 pub struct Info {
     /// Plugin Name.
+    ///
+    /// Hosts query this name with buffers of `MAX_EFFECT_NAME_LEN` bytes, so it is truncated to 31
+    /// bytes when reported as effect name, and to 63 bytes when reported as product name.
     pub name: String,
 
     /// Plugin Vendor.
@@ -310,9 +350,15 @@ pub struct Info {
     pub parameters: i32,
 
     /// Number of inputs.
+    ///
+    /// Instruments which do not process audio input should set this to 0. The `AudioBuffer`
+    /// passed to `process` then has no input channels, even if the host allocated some.
     pub inputs: i32,
 
     /// Number of outputs.
+    ///
+    /// Hosts allocate as many output channels as declared here, so `process` is never given more
+    /// output channels than this, even if the `AEffect` is changed later.
     pub outputs: i32,
 
     /// Number of MIDI input channels (1-16), or 0 for the default of 16 channels.
@@ -329,7 +375,12 @@ pub struct Info {
     /// Plugin version (e.g. 0001 = `v0.0.0.1`, 1283 = `v1.2.8.3`).
     pub version: i32,
 
-    /// Plugin category. Possible values are found in `enums::PluginCategory`.
+    /// Vendor specific version reported to the host when it asks for it separately.
+    ///
+    /// If this is `0`, `version` is reported instead. Default is `0`.
+    pub vendor_version: i32,
+
+    /// Plugin category. See `Category`.
     pub category: Category,
 
     /// Latency of the plugin in samples.
@@ -353,6 +404,53 @@ pub struct Info {
     ///
     /// Default is `false`.
     pub silent_when_stopped: bool,
+
+    /// Indicates that the plugin receives MIDI events.
+    ///
+    /// Answers the `ReceiveEvents` and `ReceiveMidiEvent` can-dos in the default
+    /// `Plugin::can_do`. Default is `false`.
+    pub receive_midi: bool,
+
+    /// Indicates that the plugin sends MIDI events to the host.
+    ///
+    /// Answers the `SendEvents` and `SendMidiEvent` can-dos in the default `Plugin::can_do`.
+    /// Default is `false`.
+    pub send_midi: bool,
+
+    /// Indicates that the plugin receives SysEx events. Answers the `ReceiveSysExEvent` can-do.
+    ///
+    /// Default is `false`.
+    pub receive_sysex: bool,
+
+    /// Indicates that the plugin uses time info from the host. Answers the `ReceiveTimeInfo`
+    /// can-do.
+    ///
+    /// Default is `false`.
+    pub receive_time_info: bool,
+
+    /// Indicates that the plugin supports offline processing. Answers the `Offline` can-do.
+    ///
+    /// Default is `false`.
+    pub offline: bool,
+
+    /// Indicates that the plugin provides MIDI program names. Answers the `MidiProgramNames`
+    /// can-do.
+    ///
+    /// Default is `false`.
+    pub midi_program_names: bool,
+
+    /// Indicates that the plugin implements a soft bypass. Answers the `Bypass` can-do.
+    ///
+    /// Default is `false`.
+    pub bypass: bool,
+
+    /// Indicates that the plugin has an editor which should only be created once the host needs it.
+    ///
+    /// If true, the host is told that the plugin has an editor, and instead of `Plugin::get_editor`,
+    /// `PluginParameters::create_editor` is called when the host first asks for the editor size or
+    /// opens it. Hosts which never show the user interface then never create the editor. If
+    /// `create_editor` returns `None` at that point, the editor opcodes fail. Default is `false`.
+    pub lazy_editor: bool,
 }
 
 impl Default for Info {
@@ -371,6 +469,7 @@ impl Default for Info {
 
             unique_id: 0, // This must be changed.
             version: 1,   // v0.0.0.1
+            vendor_version: 0,
 
             category: Category::Effect,
 
@@ -379,12 +478,21 @@ impl Default for Info {
             preset_chunks: false,
             f64_precision: false,
             silent_when_stopped: false,
+
+            receive_midi: false,
+            send_midi: false,
+            receive_sysex: false,
+            receive_time_info: false,
+            offline: false,
+            midi_program_names: false,
+            bypass: false,
+            lazy_editor: false,
         }
     }
 }
 
 /// Features which are optionally supported by a plugin. These are queried by the host at run time.
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 #[allow(missing_docs)]
 pub enum CanDo {
     SendEvents,
@@ -404,12 +512,10 @@ pub enum CanDo {
     Other(String),
 }
 
-impl CanDo {
-    // TODO: implement FromStr
-    #![allow(clippy::should_implement_trait)]
+impl<'a> From<&'a str> for CanDo {
     /// Converts a string to a `CanDo` instance. Any given string that does not match the predefined
     /// values will return a `CanDo::Other` value.
-    pub fn from_str(s: &str) -> CanDo {
+    fn from(s: &'a str) -> CanDo {
         use self::CanDo::*;
 
         match s {
@@ -430,6 +536,25 @@ impl CanDo {
     }
 }
 
+impl CanDo {
+    /// Converts a string to a `CanDo` instance. Any given string that does not match the predefined
+    /// values will return a `CanDo::Other` value.
+    #[deprecated(note = "Use `CanDo::from` or `str::parse` instead")]
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> CanDo {
+        CanDo::from(s)
+    }
+}
+
+impl FromStr for CanDo {
+    type Err = Infallible;
+
+    /// Never fails, as unknown strings are returned as `CanDo::Other`.
+    fn from_str(s: &str) -> Result<CanDo, Infallible> {
+        Ok(CanDo::from(s))
+    }
+}
+
 impl Into<String> for CanDo {
     fn into(self) -> String {
         use self::CanDo::*;
@@ -476,6 +601,11 @@ impl Into<String> for CanDo {
 #[allow(unused_variables)]
 pub trait Plugin: Send {
     /// This method must return an `Info` struct.
+    ///
+    /// When the plugin is loaded by a host, this is called once when the plugin is created, and
+    /// host queries for the plugin information as well as the default implementations of other
+    /// methods use that `Info`. Those default implementations call this method again if the plugin
+    /// is used directly instead of through a host.
     fn get_info(&self) -> Info;
 
     /// Called during initialization to pass a `HostCallback` to the plugin.
@@ -521,6 +651,20 @@ pub trait Plugin: Send {
     where
         Self: Sized;
 
+    /// Create the plugin, or report why it could not be created.
+    ///
+    /// This is what actually gets called when the host loads the plugin, and calls `new` by
+    /// default. Plugins which can fail to initialize should override it instead of panicking in
+    /// `new`, as a panic must not unwind into the host. If it returns an error, the error is logged
+    /// and the host is told that loading the plugin failed. `new` still has to be implemented, but
+    /// is not called by this crate if `try_new` is overridden.
+    fn try_new(host: HostCallback) -> Result<Self, PluginInitError>
+    where
+        Self: Sized,
+    {
+        Ok(Self::new(host))
+    }
+
     /// Called when plugin is fully initialized.
     ///
     /// This method is only called while the plugin is in the *suspended* state.
@@ -551,10 +695,38 @@ pub trait Plugin: Send {
 
     /// Return whether plugin supports specified action.
     ///
+    /// The default implementation answers `Yes` or `No` from the capability flags in `Info`
+    /// (`receive_midi`, `send_midi`, etc.) and `Maybe` for any other can-do. As the flags default
+    /// to `false`, plugins which don't set them answer `No` to the can-dos they cover.
+    ///
     /// This method is only called while the plugin is in the *suspended* state.
     fn can_do(&self, can_do: CanDo) -> Supported {
         info!("Host is asking if plugin can: {:?}.", can_do);
-        Supported::Maybe
+
+        let supported = with_cached_info(self, |info| match can_do {
+            CanDo::ReceiveEvents | CanDo::ReceiveMidiEvent => Some(info.receive_midi),
+            CanDo::SendEvents | CanDo::SendMidiEvent => Some(info.send_midi),
+            CanDo::ReceiveSysExEvent => Some(info.receive_sysex),
+            CanDo::ReceiveTimeInfo => Some(info.receive_time_info),
+            CanDo::Offline => Some(info.offline),
+            CanDo::MidiProgramNames => Some(info.midi_program_names),
+            CanDo::Bypass => Some(info.bypass),
+            _ => None,
+        });
+
+        match supported {
+            Some(true) => Supported::Yes,
+            Some(false) => Supported::No,
+            None => Supported::Maybe,
+        }
+    }
+
+    /// Get the VST API version reported to the host, e.g. `2400` for VST 2.4.
+    ///
+    /// This crate implements VST 2.4, so only override this if a host needs to see an older
+    /// version.
+    fn get_vst_version(&self) -> i32 {
+        2400
     }
 
     /// Get the tail size of plugin when it is stopped. Used in offline processing as well.
@@ -655,11 +827,62 @@ pub trait Plugin: Send {
     /// This method is only called while the plugin is in the *resumed* state.
     fn process_events(&mut self, events: &api::Events) {}
 
+    /// Process an audio buffer containing `f32` values, with proof of running on the processing
+    /// thread. Only available with the `strict_threading` feature, which calls this instead of
+    /// `process`.
+    ///
+    /// Calls `process` by default.
+    #[cfg(feature = "strict_threading")]
+    fn process_with_token(&mut self, buffer: &mut AudioBuffer<f32>, token: &ProcessThreadToken) {
+        self.process(buffer)
+    }
+
+    /// Process an audio buffer containing `f64` values, with proof of running on the processing
+    /// thread. Only available with the `strict_threading` feature, which calls this instead of
+    /// `process_f64`.
+    ///
+    /// Calls `process_f64` by default.
+    #[cfg(feature = "strict_threading")]
+    fn process_f64_with_token(&mut self, buffer: &mut AudioBuffer<f64>, token: &ProcessThreadToken) {
+        self.process_f64(buffer)
+    }
+
+    /// Handle incoming events sent from the host, with proof of running on the processing thread.
+    /// Only available with the `strict_threading` feature, which calls this instead of
+    /// `process_events`.
+    ///
+    /// Calls `process_events` by default.
+    #[cfg(feature = "strict_threading")]
+    fn process_events_with_token(&mut self, events: &api::Events, token: &ProcessThreadToken) {
+        self.process_events(events)
+    }
+
     /// Get a reference to the shared parameter object.
+    ///
+    /// This is called exactly once, right after the plugin is created. The returned `Arc` is kept
+    /// for the lifetime of the plugin and used for all parameter and preset calls from the host,
+    /// so the plugin must keep its own clone of it (as in the examples) instead of creating a new
+    /// parameter object here, or the two will not share any state.
     fn get_parameter_object(&mut self) -> Arc<dyn PluginParameters> {
         Arc::new(DummyPluginParameters)
     }
 
+    /// Get the number of MIDI input channels (1-16), or 0 for the default of 16 channels.
+    ///
+    /// Defaults to `Info::midi_inputs`. Override this if the number of channels depends on the
+    /// plugin configuration.
+    fn get_num_midi_inputs(&self) -> i32 {
+        with_cached_info(self, |info| info.midi_inputs)
+    }
+
+    /// Get the number of MIDI output channels (1-16), or 0 for the default of 16 channels.
+    ///
+    /// Defaults to `Info::midi_outputs`. Override this if the number of channels depends on the
+    /// plugin configuration.
+    fn get_num_midi_outputs(&self) -> i32 {
+        with_cached_info(self, |info| info.midi_outputs)
+    }
+
     /// Get information about an input channel. Only used by some hosts.
     fn get_input_info(&self, input: i32) -> ChannelInfo {
         ChannelInfo::new(
@@ -694,8 +917,11 @@ pub trait Plugin: Send {
     fn stop_process(&mut self) {}
 
     /// Return handle to plugin editor if supported.
-    /// The method need only return the object on the first call.
-    /// Subsequent calls can just return `None`.
+    ///
+    /// This is called exactly once, right after the plugin is created, and the returned editor is
+    /// kept for the lifetime of the plugin. If `Info::lazy_editor` is set, it is not called, and
+    /// `PluginParameters::create_editor` is used instead. If it returns `None`, all editor opcodes
+    /// from the host are ignored.
     ///
     /// The editor object will typically contain an `Arc` reference to the parameter
     /// object through which it can communicate with the audio processing.
@@ -757,6 +983,15 @@ pub trait PluginParameters: Sync {
         true
     }
 
+    /// Return the number of discrete steps of the parameter at `index`, if it is stepped.
+    ///
+    /// Hosts are told that the parameter takes the integer values `0..steps`, and a parameter with
+    /// 2 steps is reported as a switch. The parameter value itself is still between 0.0 and 1.0.
+    /// `None`, or fewer than 2 steps, leaves the parameter continuous.
+    fn parameter_is_stepped(&self, index: i32) -> Option<u32> {
+        None
+    }
+
     /// Use String as input for parameter value. Used by host to provide an editable field to
     /// adjust a parameter value. E.g. "100" may be interpreted as 100hz for parameter. Returns if
     /// the input string was used.
@@ -783,6 +1018,32 @@ pub trait PluginParameters: Sync {
     /// If `preset_chunks` is set to true in plugin info, this should load a preset bank from the
     /// given chunk data.
     fn load_bank_data(&self, data: &[u8]) {}
+
+    /// Get the current latency of the plugin in samples, if it can change at run time.
+    ///
+    /// Unlike `HostCallback::set_initial_delay`, which must be called on the host's main thread,
+    /// this allows the processing thread to change the latency, e.g. by storing it in an
+    /// `AtomicI32`. The value is read on the main thread right after the plugin is suspended and
+    /// right before it is resumed, as hosts only pick up a new latency while the plugin is
+    /// *suspended*. If it changed, `AEffect::initialDelay` is updated and the host is notified
+    /// through `IOChanged`, so a new latency takes effect the next time the host suspends the plugin.
+    ///
+    /// The default returns `None`, leaving the latency at `Info::initial_delay`.
+    fn get_initial_delay(&self) -> Option<i32> {
+        None
+    }
+
+    /// Create the editor of a plugin with `Info::lazy_editor` set.
+    ///
+    /// This is called on the UI thread when the host first asks for the editor size or opens the
+    /// editor, which may happen while the processing thread is using the `Plugin`. That is why the
+    /// editor is created from the parameter object, which the editor will typically keep a
+    /// reference to anyway. If it returns `None`, the editor opcodes fail.
+    ///
+    /// The default returns `None`.
+    fn create_editor(self: Arc<Self>) -> Option<Box<dyn Editor>> {
+        None
+    }
 }
 
 struct DummyPluginParameters;
@@ -794,7 +1055,23 @@ impl PluginParameters for DummyPluginParameters {}
 /// # Panics
 ///
 /// All methods in this struct will panic if the `HostCallback` was constructed using
-/// `Default::default()` rather than being set to the value passed to `Plugin::new`.
+/// `Default::default()` rather than being set to the value passed to `Plugin::new`. Use
+/// [`is_initialized`](#method.is_initialized) to check for this.
+///
+/// # Threads
+///
+/// A `HostCallback` can be copied into an editor or a `PluginParameters` object and used from any
+/// thread, but hosts expect some calls on particular threads:
+///
+/// * `automate`, `begin_edit`, `end_edit` and `edit_gesture` report user interaction and are
+///   meant to be called from the editor (UI) thread, or from the processing thread for automation
+///   generated while processing.
+/// * `get_info`, `vst_version`, `get_plugin_id`, `get_block_size`, `get_sample_rate` and
+///   `get_plugin_chain_position` only query the host and are safe to call from the UI thread
+///   while audio is being processed.
+/// * `get_time_info` and `process_events` refer to the block being processed and should only be
+///   called from within `Plugin::process`.
+/// * `set_initial_delay` must be called on the main thread while the plugin is suspended.
 #[derive(Copy, Clone)]
 pub struct HostCallback {
     callback: Option<HostCallbackProc>,
@@ -838,6 +1115,41 @@ impl HostCallback {
         unsafe { (*self.effect).magic as i32 == VST_MAGIC }
     }
 
+    /// Whether this `HostCallback` refers to a host, rather than being constructed using
+    /// `Default::default()`.
+    ///
+    /// Editors and parameter objects which may hold a default `HostCallback` can check this
+    /// instead of panicking when calling the host.
+    pub fn is_initialized(&self) -> bool {
+        self.callback.is_some() && !self.effect.is_null() && self.is_effect_valid()
+    }
+
+    /// The plugin id the host returned while this plugin was being created.
+    ///
+    /// Shell plugins learn which of their sub-plugins to create from `get_plugin_id` during
+    /// `Plugin::new`, but the host only answers that query while the plugin is being loaded. This
+    /// method returns the id cached at that point, so it can be used after initialization as well.
+    /// Before the plugin has been fully created it asks the host directly.
+    pub fn current_plugin_id(&self) -> i32 {
+        match self.cache() {
+            Some(cache) => cache.plugin_id,
+            None => self.get_plugin_id(),
+        }
+    }
+
+    /// The cache of the plugin, which is only available once the plugin has been fully created.
+    fn cache(&self) -> Option<&PluginCache> {
+        if !self.is_initialized() {
+            return None;
+        }
+        let effect = unsafe { &*self.effect };
+        if effect.user.is_null() {
+            None
+        } else {
+            Some(unsafe { effect.get_cache() })
+        }
+    }
+
     /// Create a new Host structure wrapping a host callback.
     #[doc(hidden)]
     pub fn wrap(callback: HostCallbackProc, effect: *mut AEffect) -> HostCallback {
@@ -864,6 +1176,51 @@ impl HostCallback {
         self.effect
     }
 
+    /// Report a new latency of the plugin in samples to the host.
+    ///
+    /// This updates the `initialDelay` field read by the host and notifies it that the
+    /// input/output setup has changed. Hosts only pick up the new value while the plugin is in the
+    /// *suspended* state, so this method should only be called from within `Plugin::suspend` or
+    /// before `Plugin::resume` is called. To change the latency from the processing thread,
+    /// implement `PluginParameters::get_initial_delay` instead.
+    pub fn set_initial_delay(&self, samples: i32) {
+        if self.is_effect_valid() {
+            unsafe { (*self.effect).initialDelay = samples };
+            self.callback(self.effect, host::OpCode::IOChanged, 0, 0, ptr::null_mut(), 0.0);
+        }
+    }
+
+    /// Signal the host that the value for the parameter has changed, clamping `value` to
+    /// `0.0..=1.0` first.
+    ///
+    /// VST parameter values are normalized to `0.0..=1.0`, and hosts store whatever value they
+    /// receive as automation. Use this instead of `automate` when the value is computed and
+    /// might fall outside of that range.
+    pub fn automate_clamped(&self, index: i32, value: f32) {
+        self.automate(index, value.clamp(0.0, 1.0));
+    }
+
+    /// Start an edit gesture for the parameter at `index`.
+    ///
+    /// `begin_edit` is sent to the host immediately, and `end_edit` is sent when the returned
+    /// `EditGesture` is dropped. Use `EditGesture::set` to automate the parameter in between.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use vst::plugin::HostCallback;
+    /// # let host = HostCallback::default();
+    /// let gesture = host.edit_gesture(0);
+    /// gesture.set(0.25);
+    /// gesture.set(0.5);
+    /// // `end_edit` is sent here.
+    /// drop(gesture);
+    /// ```
+    pub fn edit_gesture(&self, index: i32) -> EditGesture<'_> {
+        self.begin_edit(index);
+        EditGesture { host: self, index }
+    }
+
     fn read_string(&self, opcode: host::OpCode, max: usize) -> String {
         self.read_string_param(opcode, 0, 0, 0.0, max)
     }
@@ -878,9 +1235,39 @@ impl HostCallback {
     }
 }
 
+/// A parameter edit gesture in progress, created by `HostCallback::edit_gesture`.
+///
+/// The host is told that the gesture ended when this value is dropped, so every `begin_edit`
+/// sent through it is always paired with an `end_edit`.
+pub struct EditGesture<'a> {
+    host: &'a HostCallback,
+    index: i32,
+}
+
+impl<'a> EditGesture<'a> {
+    /// Get the index of the parameter being edited.
+    pub fn index(&self) -> i32 {
+        self.index
+    }
+
+    /// Signal the host that the value of the parameter has changed.
+    pub fn set(&self, value: f32) {
+        self.host.automate(self.index, value);
+    }
+}
+
+impl<'a> Drop for EditGesture<'a> {
+    fn drop(&mut self) {
+        self.host.end_edit(self.index);
+    }
+}
+
 impl Host for HostCallback {
     /// Signal the host that the value for the parameter has changed.
     ///
+    /// The value should be normalized to `0.0..=1.0`; it is passed to the host unchanged. See
+    /// `automate_clamped` for a version which enforces this.
+    ///
     /// Make sure to also call `begin_edit` and `end_edit` when a parameter
     /// has been touched. This is important for the host to determine
     /// if a user interaction is happening and the automation should be recorded.
@@ -901,6 +1288,21 @@ impl Host for HostCallback {
         self.callback(self.effect, host::OpCode::EndEdit, index, 0, ptr::null_mut(), 0.0);
     }
 
+    /// Ask the host to resize the editor window to `width` by `height` pixels.
+    ///
+    /// Returns `true` if the host resized the window. `Editor::size` should report the new size
+    /// afterwards.
+    fn size_window(&self, width: i32, height: i32) -> bool {
+        self.callback(
+            self.effect,
+            host::OpCode::SizeWindow,
+            width,
+            height as isize,
+            ptr::null_mut(),
+            0.0,
+        ) != 0
+    }
+
     fn get_plugin_id(&self) -> i32 {
         self.callback(self.effect, host::OpCode::CurrentId, 0, 0, ptr::null_mut(), 0.0) as i32
     }
@@ -909,12 +1311,22 @@ impl Host for HostCallback {
         self.callback(self.effect, host::OpCode::Idle, 0, 0, ptr::null_mut(), 0.0);
     }
 
+    /// Get vendor and product information.
+    ///
+    /// The host is only asked once the plugin has been created, later calls return the cached
+    /// strings.
     fn get_info(&self) -> (isize, String, String) {
         use api::consts::*;
-        let version = self.callback(self.effect, host::OpCode::CurrentId, 0, 0, ptr::null_mut(), 0.0) as isize;
-        let vendor_name = self.read_string(host::OpCode::GetVendorString, MAX_VENDOR_STR_LEN);
-        let product_name = self.read_string(host::OpCode::GetProductString, MAX_PRODUCT_STR_LEN);
-        (version, vendor_name, product_name)
+        let query = || {
+            let version = self.callback(self.effect, host::OpCode::GetVendorVersion, 0, 0, ptr::null_mut(), 0.0);
+            let vendor_name = self.read_string(host::OpCode::GetVendorString, MAX_VENDOR_STR_LEN);
+            let product_name = self.read_string(host::OpCode::GetProductString, MAX_PRODUCT_STR_LEN);
+            (version, vendor_name, product_name)
+        };
+        match self.cache() {
+            Some(cache) => cache.host_info.get_or_init(query).clone(),
+            None => query(),
+        }
     }
 
     /// Send events to the host.
@@ -962,6 +1374,28 @@ impl Host for HostCallback {
         self.callback(self.effect, host::OpCode::GetBlockSize, 0, 0, ptr::null_mut(), 0.0)
     }
 
+    /// Get the current sample rate from the host.
+    ///
+    /// Useful before the first call to `Plugin::set_sample_rate`. The host returns the sample
+    /// rate as a whole number of Hz.
+    fn get_sample_rate(&self) -> f32 {
+        self.callback(self.effect, host::OpCode::GetSampleRate, 0, 0, ptr::null_mut(), 0.0) as f32
+    }
+
+    /// Ask the host for the position of this plugin in its plugin chain.
+    ///
+    /// Only hosts which implement the `api::consts::CHAIN_POSITION_VENDOR_INDEX` vendor
+    /// extension answer this, such as hosts built on this crate.
+    fn get_plugin_chain_position(&self) -> Option<(usize, usize)> {
+        let mut position = [0usize; 2];
+        let index = api::consts::CHAIN_POSITION_VENDOR_INDEX;
+        let ptr = position.as_mut_ptr() as *mut c_void;
+        match self.callback(self.effect, host::OpCode::VendorSpecific, index, 0, ptr, 0.0) {
+            1 => Some((position[0], position[1])),
+            _ => None,
+        }
+    }
+
     /// Refresh UI after the plugin's parameters changed.
     fn update_display(&self) {
         self.callback(self.effect, host::OpCode::UpdateDisplay, 0, 0, ptr::null_mut(), 0.0);
@@ -974,6 +1408,39 @@ mod tests {
 
     use crate::plugin;
 
+    #[test]
+    fn can_do_strings() {
+        use crate::plugin::CanDo::{self, *};
+
+        let known = [
+            ("sendVstEvents", SendEvents),
+            ("sendVstMidiEvent", SendMidiEvent),
+            ("receiveVstEvents", ReceiveEvents),
+            ("receiveVstMidiEvent", ReceiveMidiEvent),
+            ("receiveVstTimeInfo", ReceiveTimeInfo),
+            ("offline", Offline),
+            ("midiProgramNames", MidiProgramNames),
+            ("bypass", Bypass),
+            ("receiveVstSysexEvent", ReceiveSysExEvent),
+            ("midiSingleNoteTuningChange", MidiSingleNoteTuningChange),
+            ("midiKeyBasedInstrumentControl", MidiKeyBasedInstrumentControl),
+        ];
+
+        for (string, can_do) in known.iter().cloned() {
+            assert_eq!(string.parse::<CanDo>(), Ok(can_do.clone()));
+            assert_eq!(CanDo::from(string), can_do.clone());
+            #[allow(deprecated)]
+            let from_str = CanDo::from_str(string);
+            assert_eq!(from_str, can_do.clone());
+            assert_eq!(Into::<String>::into(can_do), string);
+        }
+
+        assert_eq!(
+            "hasCockosExtensions".parse(),
+            Ok(Other("hasCockosExtensions".to_string()))
+        );
+    }
+
     /// Create a plugin instance.
     ///
     /// This is a macro to allow you to specify attributes on the created struct.
@@ -1083,4 +1550,796 @@ mod tests {
         let aeffect = instance();
         (unsafe { (*aeffect).dispatcher })(aeffect, plugin::OpCode::Initialize.into(), 0, 0, ptr::null_mut(), 0.0);
     }
+
+    thread_local! {
+        /// `(opcode, index, value, opt)` of every call made to `recording_callback`.
+        static CALLS: std::cell::RefCell<Vec<(i32, i32, isize, f32)>> = std::cell::RefCell::new(Vec::new());
+        /// The id `recording_callback` answers `CurrentId` with.
+        static CURRENT_ID: std::cell::Cell<isize> = std::cell::Cell::new(0);
+    }
+
+    extern "C" fn recording_callback(
+        _effect: *mut AEffect,
+        opcode: i32,
+        index: i32,
+        value: isize,
+        _ptr: *mut c_void,
+        opt: f32,
+    ) -> isize {
+        match OpCode::try_from(opcode) {
+            Ok(OpCode::Version) => 2400,
+            Ok(OpCode::GetSampleRate) => 48000,
+            Ok(OpCode::CurrentId) => CURRENT_ID.with(|id| id.get()),
+            _ => {
+                CALLS.with(|calls| calls.borrow_mut().push((opcode, index, value, opt)));
+                0
+            }
+        }
+    }
+
+    /// Create a plugin whose `HostCallback` records every call made to the host.
+    fn recording_host() -> HostCallback {
+        let aeffect = main::<TestPlugin>(recording_callback);
+        HostCallback::wrap(recording_callback, aeffect)
+    }
+
+    /// Get the calls made to the host through `recording_host`.
+    fn recorded_calls() -> Vec<(i32, i32, isize, f32)> {
+        CALLS.with(|calls| calls.borrow().clone())
+    }
+
+    /// Editor used by the tests of the editor opcodes.
+    #[derive(Default)]
+    struct TestEditor {
+        open: bool,
+        /// Returned from `idle_interval_ms`.
+        idle_interval_ms: Option<u32>,
+        /// Returned from `wants_keyboard`.
+        wants_keyboard: bool,
+        /// Incremented on every call to `idle`.
+        idle_calls: Option<&'static std::sync::atomic::AtomicUsize>,
+    }
+
+    impl crate::editor::Editor for TestEditor {
+        fn size(&self) -> (i32, i32) {
+            (100, 50)
+        }
+
+        fn position(&self) -> (i32, i32) {
+            (0, 0)
+        }
+
+        fn idle(&mut self) {
+            if let Some(calls) = self.idle_calls {
+                calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+        }
+
+        fn idle_interval_ms(&self) -> Option<u32> {
+            self.idle_interval_ms
+        }
+
+        fn wants_keyboard(&self) -> bool {
+            self.wants_keyboard
+        }
+
+        fn close(&mut self) {
+            self.open = false;
+        }
+
+        fn open(&mut self, _parent: *mut c_void) -> bool {
+            self.open = true;
+            true
+        }
+
+        fn is_open(&mut self) -> bool {
+            self.open
+        }
+    }
+
+    #[test]
+    fn current_plugin_id_is_cached() {
+        CURRENT_ID.with(|id| id.set(0x5368_6c31));
+        let host = recording_host();
+        assert_eq!(host.current_plugin_id(), 0x5368_6c31);
+
+        // The host only reports the id while loading, later queries must use the cached value.
+        CURRENT_ID.with(|id| id.set(0));
+        assert_eq!(host.get_plugin_id(), 0);
+        assert_eq!(host.current_plugin_id(), 0x5368_6c31);
+    }
+
+    #[test]
+    fn host_info_is_cached() {
+        let host = recording_host();
+        for _ in 0..3 {
+            assert_eq!(host.get_info(), (0, String::new(), String::new()));
+        }
+
+        let vendor_string: i32 = OpCode::GetVendorString.into();
+        let vendor_calls = recorded_calls().iter().filter(|call| call.0 == vendor_string).count();
+        assert_eq!(vendor_calls, 1);
+    }
+
+    #[test]
+    fn edit_gesture() {
+        let host = recording_host();
+
+        {
+            let gesture = host.edit_gesture(7);
+            assert_eq!(gesture.index(), 7);
+            gesture.set(0.25);
+            gesture.set(0.75);
+        }
+
+        assert_eq!(
+            recorded_calls(),
+            vec![
+                (OpCode::BeginEdit.into(), 7, 0, 0.0),
+                (OpCode::Automate.into(), 7, 0, 0.25),
+                (OpCode::Automate.into(), 7, 0, 0.75),
+                (OpCode::EndEdit.into(), 7, 0, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn host_callback_is_initialized() {
+        assert!(!HostCallback::default().is_initialized());
+        assert!(recording_host().is_initialized());
+    }
+
+    #[test]
+    fn automate_clamped() {
+        let host = recording_host();
+        host.automate_clamped(3, 1.5);
+        host.automate_clamped(3, -0.5);
+        host.automate_clamped(3, 0.5);
+
+        assert_eq!(
+            recorded_calls(),
+            vec![
+                (OpCode::Automate.into(), 3, 0, 1.0),
+                (OpCode::Automate.into(), 3, 0, 0.0),
+                (OpCode::Automate.into(), 3, 0, 0.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn num_midi_channels() {
+        struct MidiPlugin;
+
+        impl Plugin for MidiPlugin {
+            fn new(_host: HostCallback) -> Self {
+                MidiPlugin
+            }
+
+            fn get_info(&self) -> Info {
+                Info {
+                    midi_inputs: 1,
+                    midi_outputs: 1,
+                    ..Default::default()
+                }
+            }
+
+            fn get_num_midi_inputs(&self) -> i32 {
+                4
+            }
+        }
+
+        let aeffect = main::<MidiPlugin>(recording_callback);
+        let dispatch = |opcode: plugin::OpCode| unsafe {
+            ((*aeffect).dispatcher)(aeffect, opcode.into(), 0, 0, ptr::null_mut(), 0.0)
+        };
+
+        assert_eq!(dispatch(plugin::OpCode::GetNumMidiInputs), 4);
+        assert_eq!(dispatch(plugin::OpCode::GetNumMidiOutputs), 1);
+    }
+
+    #[test]
+    fn editor_idle_interval() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use crate::editor::Editor;
+
+        static IDLE_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        struct EditorPlugin;
+
+        impl Plugin for EditorPlugin {
+            fn new(_host: HostCallback) -> Self {
+                EditorPlugin
+            }
+
+            fn get_info(&self) -> Info {
+                Default::default()
+            }
+
+            fn get_editor(&mut self) -> Option<Box<dyn Editor>> {
+                Some(Box::new(TestEditor {
+                    idle_interval_ms: Some(100),
+                    idle_calls: Some(&IDLE_CALLS),
+                    ..Default::default()
+                }))
+            }
+        }
+
+        let aeffect = main::<EditorPlugin>(recording_callback);
+        let opcode = plugin::OpCode::EditorIdle.into();
+        for _ in 0..10 {
+            unsafe { ((*aeffect).dispatcher)(aeffect, opcode, 0, 0, ptr::null_mut(), 0.0) };
+        }
+
+        assert_eq!(IDLE_CALLS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn keys_required() {
+        use crate::editor::Editor;
+
+        struct KeyboardPlugin;
+
+        impl Plugin for KeyboardPlugin {
+            fn new(_host: HostCallback) -> Self {
+                KeyboardPlugin
+            }
+
+            fn get_info(&self) -> Info {
+                Default::default()
+            }
+
+            fn get_editor(&mut self) -> Option<Box<dyn Editor>> {
+                Some(Box::new(TestEditor {
+                    wants_keyboard: true,
+                    ..Default::default()
+                }))
+            }
+        }
+
+        let keys_required = |aeffect: *mut AEffect| unsafe {
+            let opcode = plugin::OpCode::_KeysRequired.into();
+            ((*aeffect).dispatcher)(aeffect, opcode, 0, 0, ptr::null_mut(), 0.0)
+        };
+
+        // The return value is inverted: 0 means that keys are required.
+        assert_eq!(keys_required(main::<KeyboardPlugin>(recording_callback)), 0);
+        assert_eq!(keys_required(main::<TestPlugin>(recording_callback)), 1);
+    }
+
+    #[test]
+    fn editor_is_cached() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use crate::editor::Editor;
+
+        static GET_EDITOR_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        // Naively returns a new editor on every call
+        struct EditorPlugin;
+
+        impl Plugin for EditorPlugin {
+            fn new(_host: HostCallback) -> Self {
+                EditorPlugin
+            }
+
+            fn get_info(&self) -> Info {
+                Default::default()
+            }
+
+            fn get_editor(&mut self) -> Option<Box<dyn Editor>> {
+                GET_EDITOR_CALLS.fetch_add(1, Ordering::SeqCst);
+                Some(Box::<TestEditor>::default())
+            }
+        }
+
+        struct NoEditorPlugin;
+
+        impl Plugin for NoEditorPlugin {
+            fn new(_host: HostCallback) -> Self {
+                NoEditorPlugin
+            }
+
+            fn get_info(&self) -> Info {
+                Default::default()
+            }
+        }
+
+        let dispatch = |aeffect: *mut AEffect, opcode: plugin::OpCode, ptr: *mut c_void| unsafe {
+            ((*aeffect).dispatcher)(aeffect, opcode.into(), 0, 0, ptr, 0.0)
+        };
+        let mut rect: *mut c_void = ptr::null_mut();
+        let rect_ptr = &mut rect as *mut *mut c_void as *mut c_void;
+
+        let aeffect = main::<EditorPlugin>(recording_callback);
+        assert_eq!(dispatch(aeffect, plugin::OpCode::EditorOpen, ptr::null_mut()), 1);
+        assert_eq!(dispatch(aeffect, plugin::OpCode::EditorGetRect, rect_ptr), 1);
+        dispatch(aeffect, plugin::OpCode::EditorClose, ptr::null_mut());
+        assert_eq!(dispatch(aeffect, plugin::OpCode::EditorOpen, ptr::null_mut()), 1);
+        assert_eq!(GET_EDITOR_CALLS.load(Ordering::SeqCst), 1);
+        unsafe { drop(Box::from_raw(rect as *mut crate::editor::Rect)) };
+
+        let aeffect = main::<NoEditorPlugin>(recording_callback);
+        rect = ptr::null_mut();
+        assert_eq!(
+            unsafe { (*aeffect).flags } & crate::api::PluginFlags::HAS_EDITOR.bits(),
+            0
+        );
+        assert_eq!(dispatch(aeffect, plugin::OpCode::EditorOpen, ptr::null_mut()), 0);
+        assert_eq!(dispatch(aeffect, plugin::OpCode::EditorGetRect, rect_ptr), 0);
+        assert!(rect.is_null());
+        dispatch(aeffect, plugin::OpCode::EditorIdle, ptr::null_mut());
+        dispatch(aeffect, plugin::OpCode::EditorClose, ptr::null_mut());
+    }
+
+    #[test]
+    fn parameter_object_is_cached() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        use crate::plugin::PluginParameters;
+
+        static GET_PARAMETER_OBJECT_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        struct CountingParameters;
+
+        impl PluginParameters for CountingParameters {}
+
+        struct ParametersPlugin;
+
+        impl Plugin for ParametersPlugin {
+            fn new(_host: HostCallback) -> Self {
+                ParametersPlugin
+            }
+
+            fn get_info(&self) -> Info {
+                Info {
+                    parameters: 2,
+                    presets: 2,
+                    ..Default::default()
+                }
+            }
+
+            fn get_parameter_object(&mut self) -> Arc<dyn PluginParameters> {
+                GET_PARAMETER_OBJECT_CALLS.fetch_add(1, Ordering::SeqCst);
+                Arc::new(CountingParameters)
+            }
+        }
+
+        let aeffect = main::<ParametersPlugin>(recording_callback);
+        let mut name = [0u8; 64];
+        let name = name.as_mut_ptr() as *mut c_void;
+        let dispatch = |opcode: plugin::OpCode, index: i32| unsafe {
+            ((*aeffect).dispatcher)(aeffect, opcode.into(), index, 0, name, 0.0)
+        };
+        dispatch(plugin::OpCode::GetParameterName, 1);
+        dispatch(plugin::OpCode::ChangePreset, 1);
+        dispatch(plugin::OpCode::GetCurrentPresetName, 0);
+        unsafe {
+            ((*aeffect).setParameter)(aeffect, 0, 0.5);
+            ((*aeffect).getParameter)(aeffect, 0);
+        }
+
+        assert_eq!(GET_PARAMETER_OBJECT_CALLS.load(Ordering::SeqCst), 1);
+        dispatch(plugin::OpCode::Shutdown, 0);
+    }
+
+    #[test]
+    fn lazy_editor() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        use crate::api::PluginFlags;
+        use crate::editor::Editor;
+        use crate::plugin::PluginParameters;
+
+        static CREATE_EDITOR_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        struct LazyEditorParameters;
+
+        impl PluginParameters for LazyEditorParameters {
+            fn create_editor(self: Arc<Self>) -> Option<Box<dyn Editor>> {
+                CREATE_EDITOR_CALLS.fetch_add(1, Ordering::SeqCst);
+                Some(Box::<TestEditor>::default())
+            }
+        }
+
+        struct LazyEditorPlugin;
+
+        impl Plugin for LazyEditorPlugin {
+            fn new(_host: HostCallback) -> Self {
+                LazyEditorPlugin
+            }
+
+            fn get_info(&self) -> Info {
+                Info {
+                    inputs: 1,
+                    outputs: 1,
+                    lazy_editor: true,
+                    ..Default::default()
+                }
+            }
+
+            fn get_parameter_object(&mut self) -> Arc<dyn PluginParameters> {
+                Arc::new(LazyEditorParameters)
+            }
+
+            fn get_editor(&mut self) -> Option<Box<dyn Editor>> {
+                panic!("get_editor must not be called for a lazy editor");
+            }
+        }
+
+        let aeffect = main::<LazyEditorPlugin>(recording_callback);
+        let dispatch = |opcode: plugin::OpCode, value: isize, ptr: *mut c_void| unsafe {
+            ((*aeffect).dispatcher)(aeffect, opcode.into(), 0, value, ptr, 0.0)
+        };
+        assert_ne!(unsafe { (*aeffect).flags } & PluginFlags::HAS_EDITOR.bits(), 0);
+
+        // A headless host processes audio without ever touching the editor.
+        let input = [0.0f32; 16];
+        let mut output = [0.0f32; 16];
+        let mut inputs = [input.as_ptr()];
+        let mut outputs = [output.as_mut_ptr()];
+        dispatch(plugin::OpCode::StateChanged, 1, ptr::null_mut());
+        unsafe { ((*aeffect).processReplacing)(aeffect, inputs.as_mut_ptr(), outputs.as_mut_ptr(), 16) };
+        dispatch(plugin::OpCode::StateChanged, 0, ptr::null_mut());
+        dispatch(plugin::OpCode::EditorIdle, 0, ptr::null_mut());
+        assert_eq!(CREATE_EDITOR_CALLS.load(Ordering::SeqCst), 0);
+
+        let mut rect: *mut c_void = ptr::null_mut();
+        let rect_ptr = &mut rect as *mut *mut c_void as *mut c_void;
+        assert_eq!(dispatch(plugin::OpCode::EditorGetRect, 0, rect_ptr), 1);
+        assert_eq!(dispatch(plugin::OpCode::EditorOpen, 0, ptr::null_mut()), 1);
+        assert_eq!(CREATE_EDITOR_CALLS.load(Ordering::SeqCst), 1);
+        unsafe { drop(Box::from_raw(rect as *mut crate::editor::Rect)) };
+
+        dispatch(plugin::OpCode::EditorClose, 0, ptr::null_mut());
+        dispatch(plugin::OpCode::Shutdown, 0, ptr::null_mut());
+    }
+
+    #[test]
+    fn info_is_cached() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static GET_INFO_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        struct InfoPlugin;
+
+        impl Plugin for InfoPlugin {
+            fn new(_host: HostCallback) -> Self {
+                InfoPlugin
+            }
+
+            fn get_info(&self) -> Info {
+                GET_INFO_CALLS.fetch_add(1, Ordering::SeqCst);
+                Info {
+                    name: "Info Plugin".to_string(),
+                    inputs: 1,
+                    outputs: 1,
+                    midi_inputs: 3,
+                    ..Default::default()
+                }
+            }
+        }
+
+        let aeffect = main::<InfoPlugin>(recording_callback);
+        let mut name = [0u8; 64];
+        let mut properties: crate::api::ChannelProperties = unsafe { std::mem::zeroed() };
+        let dispatch = |opcode: plugin::OpCode, ptr: *mut u8| unsafe {
+            ((*aeffect).dispatcher)(aeffect, opcode.into(), 0, 0, ptr as *mut c_void, 0.0)
+        };
+
+        dispatch(plugin::OpCode::GetEffectName, name.as_mut_ptr());
+        dispatch(plugin::OpCode::GetVendorName, name.as_mut_ptr());
+        dispatch(plugin::OpCode::GetProductName, name.as_mut_ptr());
+        dispatch(plugin::OpCode::GetCategory, ptr::null_mut());
+        dispatch(plugin::OpCode::GetVendorVersion, ptr::null_mut());
+        dispatch(plugin::OpCode::GetInputInfo, &mut properties as *mut _ as *mut u8);
+        assert_eq!(dispatch(plugin::OpCode::GetNumMidiInputs, ptr::null_mut()), 3);
+        assert_eq!(dispatch(plugin::OpCode::GetNumMidiOutputs, ptr::null_mut()), 0);
+        let mut can_do = *b"receiveVstMidiEvent\0";
+        let no: isize = crate::api::Supported::No.into();
+        assert_eq!(dispatch(plugin::OpCode::CanDo, can_do.as_mut_ptr()), no);
+
+        let input = [0.0f32; 4];
+        let mut output = [0.0f32; 4];
+        let mut inputs = [input.as_ptr()];
+        let mut outputs = [output.as_mut_ptr()];
+        unsafe { ((*aeffect).processReplacing)(aeffect, inputs.as_mut_ptr(), outputs.as_mut_ptr(), 4) };
+
+        assert_eq!(&name[..12], b"Info Plugin\0");
+        assert_eq!(GET_INFO_CALLS.load(Ordering::SeqCst), 1);
+
+        // Used directly, the default methods build the `Info` themselves.
+        assert_eq!(InfoPlugin.get_num_midi_inputs(), 3);
+        assert_eq!(GET_INFO_CALLS.load(Ordering::SeqCst), 2);
+
+        dispatch(plugin::OpCode::Shutdown, ptr::null_mut());
+    }
+
+    #[test]
+    fn effect_name_truncation() {
+        struct LongNamePlugin;
+
+        impl Plugin for LongNamePlugin {
+            fn new(_host: HostCallback) -> Self {
+                LongNamePlugin
+            }
+
+            fn get_info(&self) -> Info {
+                Info {
+                    name: "A plugin name which is longer than 32 bytes".to_string(),
+                    ..Default::default()
+                }
+            }
+        }
+
+        let aeffect = main::<LongNamePlugin>(recording_callback);
+        let mut name = [0xffu8; 80];
+        let dispatch = |opcode: plugin::OpCode, ptr: *mut u8| unsafe {
+            ((*aeffect).dispatcher)(aeffect, opcode.into(), 0, 0, ptr as *mut c_void, 0.0)
+        };
+
+        // The effect name buffer is only 32 bytes long, including the null terminator.
+        dispatch(plugin::OpCode::GetEffectName, name.as_mut_ptr());
+        assert_eq!(&name[..32], b"A plugin name which is longer t\0");
+        assert_eq!(name[32], 0xff);
+
+        // The product name may use up to 64 bytes.
+        name = [0xffu8; 80];
+        dispatch(plugin::OpCode::GetProductName, name.as_mut_ptr());
+        assert_eq!(&name[..44], b"A plugin name which is longer than 32 bytes\0");
+        assert_eq!(name[64], 0xff);
+    }
+
+    #[test]
+    fn vst_version() {
+        struct OldPlugin;
+
+        impl Plugin for OldPlugin {
+            fn new(_host: HostCallback) -> Self {
+                OldPlugin
+            }
+
+            fn get_info(&self) -> Info {
+                Default::default()
+            }
+
+            fn get_vst_version(&self) -> i32 {
+                2300
+            }
+        }
+
+        let aeffect = main::<OldPlugin>(recording_callback);
+        let opcode = plugin::OpCode::GetApiVersion.into();
+        let version = unsafe { ((*aeffect).dispatcher)(aeffect, opcode, 0, 0, ptr::null_mut(), 0.0) };
+        assert_eq!(version, 2300);
+    }
+
+    #[test]
+    fn can_do_from_info() {
+        use crate::api::Supported;
+        use crate::plugin::CanDo::{self, *};
+
+        struct FlagPlugin(Info);
+
+        impl Plugin for FlagPlugin {
+            fn new(_host: HostCallback) -> Self {
+                FlagPlugin(Default::default())
+            }
+
+            fn get_info(&self) -> Info {
+                self.0.clone()
+            }
+        }
+
+        let flags: [(fn(&mut Info), &[CanDo]); 7] = [
+            (|info| info.receive_midi = true, &[ReceiveEvents, ReceiveMidiEvent]),
+            (|info| info.send_midi = true, &[SendEvents, SendMidiEvent]),
+            (|info| info.receive_sysex = true, &[ReceiveSysExEvent]),
+            (|info| info.receive_time_info = true, &[ReceiveTimeInfo]),
+            (|info| info.offline = true, &[Offline]),
+            (|info| info.midi_program_names = true, &[MidiProgramNames]),
+            (|info| info.bypass = true, &[Bypass]),
+        ];
+        let recognized: Vec<CanDo> = flags.iter().flat_map(|(_, can_dos)| can_dos.iter().cloned()).collect();
+
+        let plugin = FlagPlugin(Default::default());
+        for can_do in &recognized {
+            assert_eq!(plugin.can_do(can_do.clone()), Supported::No, "{:?}", can_do);
+        }
+
+        for (set_flag, enabled) in flags.iter() {
+            let mut info = Info::default();
+            set_flag(&mut info);
+            let plugin = FlagPlugin(info);
+
+            for can_do in &recognized {
+                let expected = if enabled.contains(can_do) {
+                    Supported::Yes
+                } else {
+                    Supported::No
+                };
+                assert_eq!(plugin.can_do(can_do.clone()), expected, "{:?}", can_do);
+            }
+        }
+
+        assert_eq!(plugin.can_do(MidiSingleNoteTuningChange), Supported::Maybe);
+        assert_eq!(
+            plugin.can_do(Other("hasCockosExtensions".to_string())),
+            Supported::Maybe
+        );
+    }
+
+    #[test]
+    fn plugin_chain_position() {
+        extern "C" fn chain_callback(
+            effect: *mut AEffect,
+            opcode: i32,
+            index: i32,
+            value: isize,
+            ptr: *mut c_void,
+            opt: f32,
+        ) -> isize {
+            struct ChainHost;
+
+            impl Host for ChainHost {
+                fn get_plugin_chain_position(&self) -> Option<(usize, usize)> {
+                    Some((2, 3))
+                }
+            }
+
+            crate::interfaces::host_dispatch(&mut ChainHost, effect, opcode, index, value, ptr, opt)
+        }
+
+        assert_eq!(recording_host().get_plugin_chain_position(), None);
+
+        let aeffect = main::<TestPlugin>(chain_callback);
+        let host = HostCallback::wrap(chain_callback, aeffect);
+        assert_eq!(host.get_plugin_chain_position(), Some((2, 3)));
+    }
+
+    #[test]
+    fn initial_delay_from_parameters() {
+        use std::sync::atomic::{AtomicI32, Ordering};
+        use std::sync::Arc;
+
+        use crate::plugin::PluginParameters;
+
+        static LATENCY: AtomicI32 = AtomicI32::new(64);
+
+        struct LatencyParameters;
+
+        impl PluginParameters for LatencyParameters {
+            fn get_initial_delay(&self) -> Option<i32> {
+                Some(LATENCY.load(Ordering::Relaxed))
+            }
+        }
+
+        struct LatencyPlugin;
+
+        impl Plugin for LatencyPlugin {
+            fn new(_host: HostCallback) -> Self {
+                LatencyPlugin
+            }
+
+            fn get_info(&self) -> Info {
+                Info {
+                    initial_delay: 64,
+                    ..Default::default()
+                }
+            }
+
+            fn get_parameter_object(&mut self) -> Arc<dyn PluginParameters> {
+                Arc::new(LatencyParameters)
+            }
+        }
+
+        let aeffect = main::<LatencyPlugin>(recording_callback);
+        let state_changed = |value: isize| unsafe {
+            let opcode = plugin::OpCode::StateChanged.into();
+            ((*aeffect).dispatcher)(aeffect, opcode, 0, value, ptr::null_mut(), 0.0)
+        };
+
+        // Unchanged latency is not reported
+        state_changed(1);
+        assert!(recorded_calls().is_empty());
+
+        // Lookahead turned on by the processing thread, not reported while resumed
+        LATENCY.store(256, Ordering::Relaxed);
+        unsafe {
+            let opcode = plugin::OpCode::EditorIdle.into();
+            ((*aeffect).dispatcher)(aeffect, opcode, 0, 0, ptr::null_mut(), 0.0);
+        }
+        assert_eq!(unsafe { (*aeffect).initialDelay }, 64);
+        assert!(recorded_calls().is_empty());
+
+        state_changed(0);
+
+        assert_eq!(unsafe { (*aeffect).initialDelay }, 256);
+        assert_eq!(recorded_calls(), vec![(OpCode::IOChanged.into(), 0, 0, 0.0)]);
+    }
+
+    #[test]
+    fn stepped_parameter_properties() {
+        use std::mem::MaybeUninit;
+        use std::sync::Arc;
+
+        use crate::api::{ParameterFlags, ParameterProperties};
+        use crate::plugin::PluginParameters;
+
+        struct SteppedParameters;
+
+        impl PluginParameters for SteppedParameters {
+            fn parameter_is_stepped(&self, index: i32) -> Option<u32> {
+                match index {
+                    0 => Some(2),
+                    1 => Some(5),
+                    _ => None,
+                }
+            }
+        }
+
+        struct SteppedPlugin;
+
+        impl Plugin for SteppedPlugin {
+            fn new(_host: HostCallback) -> Self {
+                SteppedPlugin
+            }
+
+            fn get_info(&self) -> Info {
+                Info {
+                    parameters: 3,
+                    ..Default::default()
+                }
+            }
+
+            fn get_parameter_object(&mut self) -> Arc<dyn PluginParameters> {
+                Arc::new(SteppedParameters)
+            }
+        }
+
+        let aeffect = main::<SteppedPlugin>(recording_callback);
+        let properties = |index: i32| unsafe {
+            let mut properties = MaybeUninit::<ParameterProperties>::zeroed();
+            let opcode = plugin::OpCode::GetParamInfo.into();
+            let ptr = properties.as_mut_ptr() as *mut c_void;
+            let result = ((*aeffect).dispatcher)(aeffect, opcode, index, 0, ptr, 0.0);
+            (result, properties.assume_init())
+        };
+
+        let (result, toggle) = properties(0);
+        assert_eq!(result, 1);
+        let flags = ParameterFlags::from_bits_truncate(toggle.flags);
+        assert!(flags.contains(ParameterFlags::IS_SWITCH | ParameterFlags::USES_INT_STEP));
+        assert_eq!((toggle.min_integer, toggle.max_integer, toggle.step_integer), (0, 1, 1));
+
+        let (result, stepped) = properties(1);
+        assert_eq!(result, 1);
+        let flags = ParameterFlags::from_bits_truncate(stepped.flags);
+        assert!(flags.contains(ParameterFlags::USES_INTEGER_MIN_MAX | ParameterFlags::USES_INT_STEP));
+        assert!(!flags.contains(ParameterFlags::IS_SWITCH));
+        assert_eq!(stepped.max_integer, 4);
+
+        // Continuous and out of range parameters have no properties
+        assert_eq!(properties(2).0, 0);
+        assert_eq!(properties(3).0, 0);
+    }
+
+    #[test]
+    fn get_sample_rate() {
+        let host = recording_host();
+        assert_eq!(host.get_sample_rate(), 48000.0);
+    }
+
+    #[test]
+    fn set_initial_delay() {
+        let host = recording_host();
+        assert_eq!(unsafe { (*host.raw_effect()).initialDelay }, 0);
+
+        host.set_initial_delay(512);
+
+        assert_eq!(unsafe { (*host.raw_effect()).initialDelay }, 512);
+        assert_eq!(recorded_calls(), vec![(OpCode::IOChanged.into(), 0, 0, 0.0)]);
+    }
 }
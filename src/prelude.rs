@@ -7,6 +7,6 @@ pub use crate::buffer::{AudioBuffer, SendEventBuffer};
 #[doc(no_inline)]
 pub use crate::event::{Event, MidiEvent};
 #[doc(no_inline)]
-pub use crate::plugin::{CanDo, Category, HostCallback, Info, Plugin, PluginParameters};
+pub use crate::plugin::{CanDo, Category, HostCallback, Info, Plugin, PluginInitError, PluginParameters};
 #[doc(no_inline)]
-pub use crate::util::{AtomicFloat, ParameterTransfer};
+pub use crate::util::{AtomicFloat, ParameterTransfer, Smoothed, TransferParameter, TransferParameters};
@@ -0,0 +1,53 @@
+//! Types expressing on which thread plugin code runs.
+//!
+//! This is a prototype, enabled with the `strict_threading` feature. Hosts call `Plugin::process`,
+//! `Plugin::process_f64` and `Plugin::process_events` on the processing thread, but nothing in
+//! their signatures says so. With this feature, the host calls the `*_with_token` variants of these
+//! methods instead, which also receive a `&ProcessThreadToken`:
+//!
+//! ```
+//! # use vst::buffer::AudioBuffer;
+//! # use vst::plugin::{HostCallback, Info, Plugin};
+//! use vst::threading::ProcessThreadToken;
+//!
+//! # struct GainPlugin;
+//! impl Plugin for GainPlugin {
+//! #     fn new(_host: HostCallback) -> Self { GainPlugin }
+//! #     fn get_info(&self) -> Info { Default::default() }
+//!     // ...
+//!     fn process_with_token(&mut self, buffer: &mut AudioBuffer<f32>, token: &ProcessThreadToken) {
+//!         for (input, output) in buffer.zip() {
+//!             apply_gain(input, output, token);
+//!         }
+//!     }
+//! }
+//!
+//! /// Can only be called while processing, as only the processing thread has a token.
+//! fn apply_gain(input: &[f32], output: &mut [f32], _token: &ProcessThreadToken) {
+//!     for (input, output) in input.iter().zip(output) {
+//!         *output = *input * 0.5;
+//!     }
+//! }
+//! ```
+//!
+//! Code which must only run while processing can take a `&ProcessThreadToken` parameter. The token
+//! can only be created by this crate, is only lent out for the duration of a processing call and
+//! can't be sent to or shared with another thread, so such code can't be called from an editor or
+//! from `PluginParameters` by accident.
+
+use std::marker::PhantomData;
+
+/// Proof that the current code runs inside a processing call on the processing thread.
+///
+/// See the [module documentation](index.html) for details.
+pub struct ProcessThreadToken {
+    /// Makes the token neither `Send` nor `Sync`, and prevents construction outside of this crate.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl ProcessThreadToken {
+    /// Create a token. Must only be called on the processing thread.
+    pub(crate) fn new() -> ProcessThreadToken {
+        ProcessThreadToken { _not_send: PhantomData }
+    }
+}
@@ -5,6 +5,9 @@ use std::sync::atomic::{AtomicU32, Ordering};
 /// Designed for the common case of sharing VST parameters between
 /// multiple threads when no synchronization or change notification
 /// is needed.
+///
+/// With the `serde` feature enabled, it is serialized as a plain `f32`, so parameter structs
+/// can derive `Serialize` and `Deserialize` for chunk based presets.
 pub struct AtomicFloat {
     atomic: AtomicU32,
 }
@@ -57,3 +60,38 @@ impl From<AtomicFloat> for f32 {
         value.get()
     }
 }
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for AtomicFloat {
+    /// Serializes as the plain `f32` value.
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f32(self.get())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for AtomicFloat {
+    /// Deserializes from a plain `f32` value.
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        f32::deserialize(deserializer).map(AtomicFloat::new)
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use serde::de::{value::Error, Deserialize, IntoDeserializer};
+    use serde_test::{assert_ser_tokens, Token};
+
+    use super::AtomicFloat;
+
+    #[test]
+    fn serde_round_trip() {
+        for &value in &[0.25, -1.5, 1e-7, f32::MAX] {
+            let atomic = AtomicFloat::new(value);
+            assert_ser_tokens(&atomic, &[Token::F32(value)]);
+
+            let deserializer = IntoDeserializer::<Error>::into_deserializer(value);
+            assert_eq!(AtomicFloat::deserialize(deserializer).unwrap().get(), value);
+        }
+    }
+}
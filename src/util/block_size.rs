@@ -0,0 +1,122 @@
+/// Remembers the maximum block size announced by the host.
+///
+/// Hosts announce the largest number of samples they will pass to `process` through
+/// `Plugin::set_block_size`. Plugins which pre-allocate scratch buffers can forward that call
+/// to a `BlockSizeTracker` and query it later, instead of keeping track of it themselves.
+///
+/// ```rust
+/// # use vst::util::BlockSizeTracker;
+/// let mut block_size = BlockSizeTracker::new();
+/// assert_eq!(block_size.max_block_size(), None);
+///
+/// block_size.set_block_size(512);
+/// assert_eq!(block_size.max_block_size(), Some(512));
+/// assert!(block_size.fits(256));
+/// assert!(!block_size.fits(1024));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BlockSizeTracker {
+    max: Option<usize>,
+}
+
+impl BlockSizeTracker {
+    /// Create a tracker which does not know the block size yet.
+    pub fn new() -> BlockSizeTracker {
+        BlockSizeTracker { max: None }
+    }
+
+    /// Record the block size passed to `Plugin::set_block_size`.
+    ///
+    /// Sizes which are not positive are ignored, keeping the previous value. Returns whether the
+    /// size was accepted.
+    pub fn set_block_size(&mut self, size: i64) -> bool {
+        match usize::try_from(size) {
+            Ok(size) if size > 0 => {
+                self.max = Some(size);
+                true
+            }
+            _ => {
+                warn!("VST: Ignoring invalid block size {}", size);
+                false
+            }
+        }
+    }
+
+    /// The last valid block size announced by the host, if any.
+    pub fn max_block_size(&self) -> Option<usize> {
+        self.max
+    }
+
+    /// Whether a block of `samples` samples is within the announced block size.
+    ///
+    /// Returns `true` if no block size is known yet.
+    pub fn fits(&self, samples: usize) -> bool {
+        !matches!(self.max, Some(max) if samples > max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use crate::buffer::AudioBuffer;
+    use crate::interfaces::tests::TestEffect;
+    use crate::plugin::{HostCallback, Info, OpCode, Plugin};
+    use crate::util::BlockSizeTracker;
+
+    #[test]
+    fn invalid_sizes_are_ignored() {
+        let mut tracker = BlockSizeTracker::new();
+        assert!(!tracker.set_block_size(0));
+        assert_eq!(tracker.max_block_size(), None);
+        assert!(tracker.fits(4096));
+
+        assert!(tracker.set_block_size(128));
+        assert!(!tracker.set_block_size(-1));
+        assert_eq!(tracker.max_block_size(), Some(128));
+    }
+
+    thread_local! {
+        static SEEN: Cell<Option<(Option<usize>, bool)>> = Cell::new(None);
+    }
+
+    struct TrackingPlugin {
+        block_size: BlockSizeTracker,
+    }
+
+    impl Plugin for TrackingPlugin {
+        fn new(_host: HostCallback) -> Self {
+            TrackingPlugin {
+                block_size: BlockSizeTracker::new(),
+            }
+        }
+
+        fn get_info(&self) -> Info {
+            Info {
+                inputs: 1,
+                outputs: 1,
+                ..Default::default()
+            }
+        }
+
+        fn set_block_size(&mut self, size: i64) {
+            self.block_size.set_block_size(size);
+        }
+
+        fn process(&mut self, buffer: &mut AudioBuffer<f32>) {
+            let seen = (self.block_size.max_block_size(), self.block_size.fits(buffer.samples()));
+            SEEN.with(|cell| cell.set(Some(seen)));
+        }
+    }
+
+    #[test]
+    fn block_size_available_in_process() {
+        let effect = TestEffect::new::<TrackingPlugin>();
+        let input = [0.0f32; 64];
+        let mut output = [0.0f32; 64];
+
+        effect.dispatch(OpCode::SetBlockSize, 64);
+        effect.process(&[&input], &mut [&mut output], 64);
+        assert_eq!(SEEN.with(Cell::get), Some((Some(64), true)));
+    }
+}
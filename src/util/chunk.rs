@@ -0,0 +1,112 @@
+//! Helpers for versioning preset and bank chunk data.
+//!
+//! Plugins with `preset_chunks` enabled store their state in opaque chunks. Prefixing these
+//! chunks with a header makes it possible to detect data written by other plugins or by an
+//! incompatible version of the same plugin, instead of silently loading corrupt state.
+//!
+//! ```
+//! use vst::util::chunk;
+//!
+//! let data = chunk::write_versioned(2, b"state");
+//! let (version, payload) = chunk::read_versioned(&data).unwrap();
+//! assert_eq!(version, 2);
+//! assert_eq!(payload, b"state");
+//! ```
+
+use std::error::Error;
+use std::fmt;
+
+/// Magic number identifying a versioned chunk.
+const MAGIC: [u8; 4] = *b"VstC";
+
+/// Size of the header: magic, version and payload length.
+const HEADER_LEN: usize = 12;
+
+/// All possible errors that can occur when reading a versioned chunk.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ChunkError {
+    /// The data is too short to contain a chunk header.
+    MissingHeader,
+
+    /// The data does not start with the chunk magic number, so it was not written by
+    /// `write_versioned`.
+    InvalidMagic,
+
+    /// The data is shorter than the payload length stored in the header.
+    Truncated,
+}
+
+impl fmt::Display for ChunkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::ChunkError::*;
+        let description = match self {
+            MissingHeader => "The chunk is too short to contain a header",
+            InvalidMagic => "The chunk does not start with a valid header",
+            Truncated => "The chunk payload is shorter than specified in its header",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+impl Error for ChunkError {}
+
+/// Create chunk data consisting of a header containing `version`, followed by `payload`.
+pub fn write_versioned(version: u32, payload: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(HEADER_LEN + payload.len());
+    data.extend_from_slice(&MAGIC);
+    data.extend_from_slice(&version.to_le_bytes());
+    data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    data.extend_from_slice(payload);
+    data
+}
+
+/// Read chunk data written by `write_versioned`, returning the version and payload.
+///
+/// Any data following the payload is ignored.
+pub fn read_versioned(data: &[u8]) -> Result<(u32, &[u8]), ChunkError> {
+    if data.len() < HEADER_LEN {
+        return Err(ChunkError::MissingHeader);
+    }
+    if data[0..4] != MAGIC {
+        return Err(ChunkError::InvalidMagic);
+    }
+
+    let version = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+    let len = u32::from_le_bytes([data[8], data[9], data[10], data[11]]) as usize;
+
+    match data[HEADER_LEN..].get(..len) {
+        Some(payload) => Ok((version, payload)),
+        None => Err(ChunkError::Truncated),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::chunk::{read_versioned, write_versioned, ChunkError};
+
+    #[test]
+    fn round_trip() {
+        let data = write_versioned(3, &[1, 2, 3, 4]);
+        assert_eq!(read_versioned(&data), Ok((3, &[1, 2, 3, 4][..])));
+
+        let data = write_versioned(0, &[]);
+        assert_eq!(read_versioned(&data), Ok((0, &[][..])));
+    }
+
+    #[test]
+    fn truncated() {
+        let data = write_versioned(1, &[1, 2, 3, 4]);
+
+        assert_eq!(read_versioned(&data[..data.len() - 1]), Err(ChunkError::Truncated));
+        assert_eq!(read_versioned(&data[..11]), Err(ChunkError::MissingHeader));
+        assert_eq!(read_versioned(&[]), Err(ChunkError::MissingHeader));
+    }
+
+    #[test]
+    fn invalid_magic() {
+        let mut data = write_versioned(1, &[1, 2, 3, 4]);
+        data[0] = b'X';
+
+        assert_eq!(read_versioned(&data), Err(ChunkError::InvalidMagic));
+    }
+}
@@ -0,0 +1,80 @@
+//! Conversion between linear gain and decibels.
+
+/// Level in decibels treated as silence.
+///
+/// `linear_to_db` returns this instead of negative infinity for silence, and `db_to_linear`
+/// returns `0.0` for this level and anything below it.
+pub const SILENCE_DB: f32 = -100.0;
+
+/// Convert a level in decibels to a linear gain factor, e.g. `-6.0` to about `0.501`.
+///
+/// Levels at or below [`SILENCE_DB`] result in `0.0`.
+pub fn db_to_linear(db: f32) -> f32 {
+    if db <= SILENCE_DB {
+        0.0
+    } else {
+        10.0f32.powf(db / 20.0)
+    }
+}
+
+/// Convert a linear gain factor to a level in decibels, e.g. `0.5` to about `-6.02`.
+///
+/// Factors which are zero, negative or correspond to less than [`SILENCE_DB`] result in
+/// `SILENCE_DB`.
+pub fn linear_to_db(linear: f32) -> f32 {
+    if linear > 0.0 {
+        (20.0 * linear.log10()).max(SILENCE_DB)
+    } else {
+        SILENCE_DB
+    }
+}
+
+/// `f64` version of [`db_to_linear`].
+pub fn db_to_linear_f64(db: f64) -> f64 {
+    if db <= f64::from(SILENCE_DB) {
+        0.0
+    } else {
+        10.0f64.powf(db / 20.0)
+    }
+}
+
+/// `f64` version of [`linear_to_db`].
+pub fn linear_to_db_f64(linear: f64) -> f64 {
+    if linear > 0.0 {
+        (20.0 * linear.log10()).max(f64::from(SILENCE_DB))
+    } else {
+        f64::from(SILENCE_DB)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::db::*;
+
+    #[test]
+    fn unity_gain() {
+        assert_eq!(db_to_linear(0.0), 1.0);
+        assert_eq!(linear_to_db(1.0), 0.0);
+        assert_eq!(db_to_linear_f64(0.0), 1.0);
+        assert_eq!(linear_to_db_f64(1.0), 0.0);
+    }
+
+    #[test]
+    fn minus_six_db() {
+        assert!((db_to_linear(-6.0) - 0.501).abs() < 1e-3);
+        assert!((linear_to_db(0.5) + 6.0206).abs() < 1e-3);
+        assert!((db_to_linear_f64(-6.0) - 0.501_187).abs() < 1e-6);
+        assert!((linear_to_db_f64(db_to_linear_f64(-6.0)) + 6.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn silence_floor() {
+        assert_eq!(linear_to_db(0.0), SILENCE_DB);
+        assert_eq!(linear_to_db(-1.0), SILENCE_DB);
+        assert_eq!(linear_to_db(1e-9), SILENCE_DB);
+        assert_eq!(db_to_linear(SILENCE_DB), 0.0);
+        assert_eq!(db_to_linear(f32::NEG_INFINITY), 0.0);
+        assert_eq!(linear_to_db_f64(0.0), f64::from(SILENCE_DB));
+        assert_eq!(db_to_linear_f64(-120.0), 0.0);
+    }
+}
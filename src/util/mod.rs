@@ -1,7 +1,18 @@
 //! Structures for easing the implementation of VST plugins.
 
 mod atomic_float;
+mod block_size;
+pub mod chunk;
+pub mod db;
 mod parameter_transfer;
+mod smoothed;
+mod split_block;
+mod transfer_parameters;
 
 pub use self::atomic_float::AtomicFloat;
+pub use self::block_size::BlockSizeTracker;
+pub use self::db::{db_to_linear, db_to_linear_f64, linear_to_db, linear_to_db_f64};
 pub use self::parameter_transfer::{ParameterTransfer, ParameterTransferIterator};
+pub use self::smoothed::Smoothed;
+pub use self::split_block::split_block_at_events;
+pub use self::transfer_parameters::{TransferParameter, TransferParameters};
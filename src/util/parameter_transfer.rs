@@ -39,6 +39,34 @@ impl ParameterTransfer {
         f32::from_bits(self.values[index].load(Ordering::Relaxed))
     }
 
+    /// Get the number of parameters.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns true if there are no parameters.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Get the current values of all parameters, in index order.
+    ///
+    /// Unlike `iterate`, this does not look at or modify which parameters are marked as changed.
+    pub fn snapshot(&self) -> Vec<f32> {
+        (0..self.len()).map(|index| self.get_parameter(index)).collect()
+    }
+
+    /// Get the number of parameters currently marked as changed, without clearing them.
+    ///
+    /// When parameters are changed on a different thread, the count is only a snapshot, like the
+    /// result of `iterate`.
+    pub fn changed_count(&self) -> usize {
+        self.changed
+            .iter()
+            .map(|word| word.load(Ordering::Acquire).count_ones() as usize)
+            .sum()
+    }
+
     /// Iterate over all parameters marked as changed. If `acquire` is `true`,
     /// mark all returned parameters as no longer changed.
     ///
@@ -129,6 +157,39 @@ mod tests {
     const PARAMETERS: usize = 1000;
     const UPDATES: usize = 1_000_000;
 
+    #[test]
+    fn snapshot() {
+        let transfer = ParameterTransfer::new(3);
+        assert_eq!(transfer.len(), 3);
+        assert_eq!(transfer.snapshot(), vec![0.0; 3]);
+
+        transfer.set_parameter(0, 0.25);
+        transfer.set_parameter(2, 0.75);
+        assert_eq!(transfer.snapshot(), vec![0.25, 0.0, 0.75]);
+
+        // Taking a snapshot leaves the changed parameters marked.
+        assert_eq!(transfer.iterate(true).collect::<Vec<_>>(), vec![(0, 0.25), (2, 0.75)]);
+        assert_eq!(transfer.snapshot(), vec![0.25, 0.0, 0.75]);
+        assert!(transfer.iterate(true).next().is_none());
+    }
+
+    #[test]
+    fn changed_count() {
+        let transfer = ParameterTransfer::new(130);
+        assert_eq!(transfer.changed_count(), 0);
+
+        transfer.set_parameter(0, 0.25);
+        transfer.set_parameter(0, 0.5);
+        transfer.set_parameter(70, 0.5);
+        transfer.set_parameter(129, 1.0);
+        assert_eq!(transfer.changed_count(), 3);
+
+        // Counting does not clear the changed parameters.
+        assert_eq!(transfer.changed_count(), 3);
+        assert_eq!(transfer.iterate(true).count(), 3);
+        assert_eq!(transfer.changed_count(), 0);
+    }
+
     #[test]
     fn parameter_transfer() {
         let transfer = Arc::new(ParameterTransfer::new(PARAMETERS));
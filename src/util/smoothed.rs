@@ -0,0 +1,156 @@
+/// One-pole smoother for click-free parameter changes.
+///
+/// Each call to [`get`](#method.get) moves the current value a fixed fraction (the smoothing
+/// coefficient) of the remaining distance towards the target value.
+///
+/// ```rust
+/// # use vst::util::Smoothed;
+/// let mut gain = Smoothed::new(0.0);
+/// gain.set_sample_rate(44100.0);
+/// gain.set_time_constant_ms(10.0);
+/// gain.set(1.0);
+/// let first = gain.get();
+/// assert!(first > 0.0 && first < 1.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Smoothed {
+    state: f32,
+    target: f32,
+    coefficient: f32,
+    sample_rate: f32,
+    time_constant_ms: Option<f32>,
+}
+
+/// Coefficient used until one is set explicitly.
+const DEFAULT_COEFFICIENT: f32 = 0.01;
+
+impl Smoothed {
+    /// Create a smoother which starts at (and targets) `value`.
+    pub fn new(value: f32) -> Smoothed {
+        Smoothed {
+            state: value,
+            target: value,
+            coefficient: DEFAULT_COEFFICIENT,
+            sample_rate: 44100.0,
+            time_constant_ms: None,
+        }
+    }
+
+    /// Set the fraction of the remaining distance to the target covered on each step.
+    ///
+    /// The coefficient is clamped to `0.0..=1.0`, where `1.0` disables smoothing.
+    pub fn set_coefficient(&mut self, coefficient: f32) {
+        self.coefficient = coefficient.clamp(0.0, 1.0);
+        self.time_constant_ms = None;
+    }
+
+    /// Get the current smoothing coefficient.
+    pub fn coefficient(&self) -> f32 {
+        self.coefficient
+    }
+
+    /// Set the sample rate at which `get` is called.
+    ///
+    /// If a time constant was set with `set_time_constant_ms`, the coefficient is recalculated.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        if let Some(time_constant_ms) = self.time_constant_ms {
+            self.set_time_constant_ms(time_constant_ms);
+        }
+    }
+
+    /// Set the time in milliseconds after which about 63% of a change has been applied.
+    ///
+    /// A time constant of zero or less disables smoothing.
+    pub fn set_time_constant_ms(&mut self, time_constant_ms: f32) {
+        let samples = time_constant_ms * 0.001 * self.sample_rate;
+        self.coefficient = if samples > 0.0 {
+            1.0 - (-1.0 / samples).exp()
+        } else {
+            1.0
+        };
+        self.time_constant_ms = Some(time_constant_ms);
+    }
+
+    /// Set the target value to smooth towards.
+    pub fn set(&mut self, value: f32) {
+        self.target = value;
+    }
+
+    /// Get the target value.
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    /// Advance the smoother by one sample and return the new current value.
+    pub fn get(&mut self) -> f32 {
+        self.state += (self.target - self.state) * self.coefficient;
+        self.state
+    }
+
+    /// Get the current value without advancing the smoother.
+    pub fn current(&self) -> f32 {
+        self.state
+    }
+
+    /// Jump to `value` immediately, without smoothing.
+    pub fn reset(&mut self, value: f32) {
+        self.state = value;
+        self.target = value;
+    }
+}
+
+impl Default for Smoothed {
+    fn default() -> Self {
+        Smoothed::new(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::Smoothed;
+
+    #[test]
+    fn converges_to_target() {
+        let mut smoothed = Smoothed::new(0.0);
+        smoothed.set(1.0);
+
+        let mut previous = smoothed.current();
+        for _ in 0..1000 {
+            let value = smoothed.get();
+            assert!(value > previous && value <= 1.0);
+            previous = value;
+        }
+        assert!((1.0 - previous).abs() < 1e-3);
+    }
+
+    #[test]
+    fn time_constant() {
+        let mut smoothed = Smoothed::new(0.0);
+        smoothed.set_time_constant_ms(10.0);
+        smoothed.set_sample_rate(1000.0);
+        smoothed.set(1.0);
+
+        let mut value = 0.0;
+        for _ in 0..10 {
+            value = smoothed.get();
+        }
+        assert!((value - (1.0 - (-1.0f32).exp())).abs() < 1e-3);
+
+        smoothed.set_time_constant_ms(0.0);
+        smoothed.set(0.5);
+        assert_eq!(smoothed.get(), 0.5);
+    }
+
+    #[test]
+    fn reset_jumps_instantly() {
+        let mut smoothed = Smoothed::new(0.0);
+        smoothed.set(1.0);
+        smoothed.get();
+
+        smoothed.reset(0.25);
+        assert_eq!(smoothed.current(), 0.25);
+        assert_eq!(smoothed.target(), 0.25);
+        assert_eq!(smoothed.get(), 0.25);
+    }
+}
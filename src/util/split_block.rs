@@ -0,0 +1,112 @@
+use std::mem;
+
+use crate::api;
+use crate::event::Event;
+
+/// Split a processing block of `samples` samples at the positions of `events`.
+///
+/// Returns contiguous sub-ranges `[start, end)` covering the whole block, each together with the
+/// events occurring at `start`. Synths can process the block range by range, applying the events
+/// of a range before rendering it, instead of hand-rolling that loop.
+///
+/// Events keep their order within a range. Events with a negative `delta_frames` are moved to
+/// the start of the block, and those past its end to its last sample.
+///
+/// ```rust
+/// # use vst::api;
+/// # use vst::buffer::AudioBuffer;
+/// # use vst::event::Event;
+/// # use vst::util::split_block_at_events;
+/// fn process(buffer: &mut AudioBuffer<f32>, events: &api::Events) {
+///     for (start, end, events) in split_block_at_events(buffer.samples(), events) {
+///         for event in events {
+///             // Apply the event...
+///         }
+///         // ...and render samples `start..end`.
+///     }
+/// }
+/// ```
+pub fn split_block_at_events(samples: usize, events: &api::Events) -> Vec<(usize, usize, Vec<Event<'_>>)> {
+    let last = samples.saturating_sub(1);
+    let mut events: Vec<(usize, Event)> = events
+        .events()
+        .map(|event| ((event.delta_frames().max(0) as usize).min(last), event))
+        .collect();
+    // The sort is stable, so simultaneous events keep their order.
+    events.sort_by_key(|&(position, _)| position);
+
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    let mut current = Vec::new();
+    for (position, event) in events {
+        if position != start {
+            ranges.push((start, position, mem::take(&mut current)));
+            start = position;
+        }
+        current.push(event);
+    }
+    if start < samples || !current.is_empty() {
+        ranges.push((start, samples, current));
+    }
+
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::SendEventBuffer;
+    use crate::event::{Event, MidiEvent};
+    use crate::util::split_block_at_events;
+
+    fn note_on(note: u8, delta_frames: i32) -> Event<'static> {
+        Event::Midi(MidiEvent {
+            delta_frames,
+            ..MidiEvent::simple([0x90, note, 100])
+        })
+    }
+
+    /// Get the ranges of a split block with the notes of their events.
+    fn split(samples: usize, events: Vec<Event>) -> Vec<(usize, usize, Vec<u8>)> {
+        let mut buffer = SendEventBuffer::new(events.len());
+        buffer.store_events(events);
+
+        split_block_at_events(samples, buffer.events())
+            .into_iter()
+            .map(|(start, end, events)| {
+                let notes = events
+                    .iter()
+                    .map(|event| match event {
+                        Event::Midi(event) => event.data[1],
+                        _ => panic!("Expected a midi event"),
+                    })
+                    .collect();
+                (start, end, notes)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn two_notes() {
+        assert_eq!(
+            split(128, vec![note_on(60, 0), note_on(64, 100)]),
+            vec![(0, 100, vec![60]), (100, 128, vec![64])]
+        );
+    }
+
+    #[test]
+    fn unsorted_and_out_of_range() {
+        assert_eq!(
+            split(
+                64,
+                vec![note_on(60, 32), note_on(62, 200), note_on(64, 32), note_on(65, -5)]
+            ),
+            vec![(0, 32, vec![65]), (32, 63, vec![60, 64]), (63, 64, vec![62])]
+        );
+    }
+
+    #[test]
+    fn no_events() {
+        assert_eq!(split(64, vec![]), vec![(0, 64, vec![])]);
+        assert_eq!(split(0, vec![]), vec![]);
+    }
+}
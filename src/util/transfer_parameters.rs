@@ -0,0 +1,170 @@
+use crate::plugin::PluginParameters;
+use crate::util::ParameterTransfer;
+
+/// Formats a parameter value for display, see [`TransferParameter::with_formatter`].
+type Formatter = Box<dyn Fn(f32) -> String + Send + Sync>;
+
+/// Description of a single parameter of a [`TransferParameters`].
+pub struct TransferParameter {
+    name: String,
+    label: String,
+    default: f32,
+    formatter: Option<Formatter>,
+}
+
+impl TransferParameter {
+    /// Describe a parameter with the given name, unit label (e.g. "dB") and default value.
+    pub fn new(name: impl Into<String>, label: impl Into<String>, default: f32) -> TransferParameter {
+        TransferParameter {
+            name: name.into(),
+            label: label.into(),
+            default,
+            formatter: None,
+        }
+    }
+
+    /// Format the value of this parameter for display with `formatter`.
+    ///
+    /// Without a formatter, the value is shown with three decimals.
+    pub fn with_formatter(mut self, formatter: impl Fn(f32) -> String + Send + Sync + 'static) -> TransferParameter {
+        self.formatter = Some(Box::new(formatter));
+        self
+    }
+}
+
+/// Ready-made `PluginParameters` backed by a [`ParameterTransfer`].
+///
+/// Parameter values set by the host are stored in the `ParameterTransfer`, where the processing
+/// thread picks up the changes with [`transfer`](#method.transfer)`().iterate(true)`. Names,
+/// labels and display text come from the [`TransferParameter`] descriptions.
+///
+/// All parameters start out at their default value, and are marked as changed so that the first
+/// iteration reports them.
+///
+/// ```rust
+/// # use vst::util::{TransferParameter, TransferParameters};
+/// let params = TransferParameters::new(vec![
+///     TransferParameter::new("Gain", "dB", 0.5).with_formatter(|value| format!("{:.1}", value * 12.0 - 6.0)),
+///     TransferParameter::new("Mix", "%", 1.0),
+/// ]);
+///
+/// for (index, value) in params.transfer().iterate(true) {
+///     // Apply the change on the processing thread.
+/// }
+/// ```
+pub struct TransferParameters {
+    transfer: ParameterTransfer,
+    parameters: Vec<TransferParameter>,
+}
+
+impl TransferParameters {
+    /// Create parameters from their descriptions, in index order.
+    pub fn new(parameters: Vec<TransferParameter>) -> TransferParameters {
+        let transfer = ParameterTransfer::new(parameters.len());
+        for (index, parameter) in parameters.iter().enumerate() {
+            transfer.set_parameter(index, parameter.default);
+        }
+
+        TransferParameters { transfer, parameters }
+    }
+
+    /// Get the `ParameterTransfer` holding the parameter values.
+    pub fn transfer(&self) -> &ParameterTransfer {
+        &self.transfer
+    }
+
+    /// Get the number of parameters, to use for `Info::parameters`.
+    pub fn len(&self) -> usize {
+        self.parameters.len()
+    }
+
+    /// Returns true if there are no parameters.
+    pub fn is_empty(&self) -> bool {
+        self.parameters.is_empty()
+    }
+
+    fn parameter(&self, index: i32) -> Option<(usize, &TransferParameter)> {
+        let index = usize::try_from(index).ok()?;
+        self.parameters.get(index).map(|parameter| (index, parameter))
+    }
+}
+
+impl PluginParameters for TransferParameters {
+    fn get_parameter_label(&self, index: i32) -> String {
+        self.parameter(index)
+            .map(|(_, parameter)| parameter.label.clone())
+            .unwrap_or_default()
+    }
+
+    fn get_parameter_text(&self, index: i32) -> String {
+        match self.parameter(index) {
+            Some((index, parameter)) => {
+                let value = self.transfer.get_parameter(index);
+                match parameter.formatter {
+                    Some(ref formatter) => formatter(value),
+                    None => format!("{:.3}", value),
+                }
+            }
+            None => String::new(),
+        }
+    }
+
+    fn get_parameter_name(&self, index: i32) -> String {
+        self.parameter(index)
+            .map(|(_, parameter)| parameter.name.clone())
+            .unwrap_or_default()
+    }
+
+    fn get_parameter(&self, index: i32) -> f32 {
+        self.parameter(index)
+            .map(|(index, _)| self.transfer.get_parameter(index))
+            .unwrap_or(0.0)
+    }
+
+    fn set_parameter(&self, index: i32, value: f32) {
+        if let Some((index, _)) = self.parameter(index) {
+            self.transfer.set_parameter(index, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::plugin::PluginParameters;
+    use crate::util::{TransferParameter, TransferParameters};
+
+    fn parameters() -> TransferParameters {
+        TransferParameters::new(vec![
+            TransferParameter::new("Gain", "dB", 0.5).with_formatter(|value| format!("{:+.1}", value * 12.0 - 6.0)),
+            TransferParameter::new("Mix", "%", 1.0),
+        ])
+    }
+
+    #[test]
+    fn set_parameter_is_observable() {
+        let params = parameters();
+        assert_eq!(
+            params.transfer().iterate(true).collect::<Vec<_>>(),
+            [(0, 0.5), (1, 1.0)]
+        );
+        assert_eq!(params.transfer().iterate(true).count(), 0);
+
+        params.set_parameter(1, 0.25);
+        params.set_parameter(2, 0.75);
+        params.set_parameter(-1, 0.75);
+        assert_eq!(params.get_parameter(1), 0.25);
+        assert_eq!(params.transfer().iterate(true).collect::<Vec<_>>(), [(1, 0.25)]);
+    }
+
+    #[test]
+    fn descriptions() {
+        let params = parameters();
+        assert_eq!(params.len(), 2);
+        assert_eq!(params.get_parameter_name(0), "Gain");
+        assert_eq!(params.get_parameter_label(0), "dB");
+        assert_eq!(params.get_parameter_text(0), "+0.0");
+        assert_eq!(params.get_parameter_text(1), "1.000");
+        assert_eq!(params.get_parameter_name(2), "");
+        assert_eq!(params.get_parameter(2), 0.0);
+    }
+}
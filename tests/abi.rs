@@ -0,0 +1,210 @@
+//! Checks that the FFI structs in `vst::api` match the memory layout of the VST 2.4 SDK headers.
+//!
+//! The expected values are those of the C structs in `aeffect.h` and `aeffectx.h`, where
+//! `VstIntPtr` and all pointers are pointer sized and everything else is naturally aligned.
+
+use std::mem::{align_of, offset_of, size_of};
+
+use vst::api::*;
+
+/// Pointer size in bytes.
+const PTR: usize = size_of::<usize>();
+
+/// Select the expected value for 64 bit or 32 bit targets.
+const fn by_width(bits64: usize, bits32: usize) -> usize {
+    if PTR == 8 {
+        bits64
+    } else {
+        bits32
+    }
+}
+
+/// Assert the size, alignment and field offsets of a struct.
+macro_rules! assert_layout {
+    ($ty:ty { size: $size:expr, align: $align:expr, $($field:ident: $offset:expr),* $(,)? }) => {
+        assert_eq!(size_of::<$ty>(), $size, "size of {}", stringify!($ty));
+        assert_eq!(align_of::<$ty>(), $align, "alignment of {}", stringify!($ty));
+        $(
+            assert_eq!(
+                offset_of!($ty, $field),
+                $offset,
+                "offset of {}::{}",
+                stringify!($ty),
+                stringify!($field)
+            );
+        )*
+    };
+}
+
+#[test]
+fn aeffect() {
+    assert_layout!(AEffect {
+        size: by_width(192, 144),
+        align: PTR,
+        magic: 0,
+        dispatcher: PTR,
+        _process: 2 * PTR,
+        setParameter: 3 * PTR,
+        getParameter: 4 * PTR,
+        numPrograms: 5 * PTR,
+        numParams: 5 * PTR + 4,
+        numInputs: 5 * PTR + 8,
+        numOutputs: 5 * PTR + 12,
+        flags: 5 * PTR + 16,
+        reserved1: by_width(64, 40),
+        reserved2: by_width(72, 44),
+        initialDelay: by_width(80, 48),
+        _realQualities: by_width(84, 52),
+        _offQualities: by_width(88, 56),
+        _ioRatio: by_width(92, 60),
+        object: by_width(96, 64),
+        user: by_width(104, 68),
+        uniqueId: by_width(112, 72),
+        version: by_width(116, 76),
+        processReplacing: by_width(120, 80),
+        processReplacingF64: by_width(128, 84),
+        future: by_width(136, 88),
+    });
+}
+
+#[test]
+fn events() {
+    assert_layout!(Events {
+        size: 4 * PTR,
+        align: PTR,
+        num_events: 0,
+        _reserved: PTR,
+        events: 2 * PTR,
+    });
+
+    assert_layout!(Event {
+        size: 32,
+        align: 4,
+        event_type: 0,
+        byte_size: 4,
+        delta_frames: 8,
+        _flags: 12,
+        _reserved: 16,
+    });
+
+    assert_layout!(MidiEvent {
+        size: 32,
+        align: 4,
+        event_type: 0,
+        byte_size: 4,
+        delta_frames: 8,
+        flags: 12,
+        note_length: 16,
+        note_offset: 20,
+        midi_data: 24,
+        _midi_reserved: 27,
+        detune: 28,
+        note_off_velocity: 29,
+        _reserved1: 30,
+        _reserved2: 31,
+    });
+
+    assert_layout!(SysExEvent {
+        size: by_width(48, 32),
+        align: PTR,
+        event_type: 0,
+        byte_size: 4,
+        delta_frames: 8,
+        _flags: 12,
+        data_size: 16,
+        _reserved1: by_width(24, 20),
+        system_data: by_width(32, 24),
+        _reserved2: by_width(40, 28),
+    });
+}
+
+#[test]
+fn time_info() {
+    assert_layout!(TimeInfo {
+        size: 88,
+        align: 8,
+        sample_pos: 0,
+        sample_rate: 8,
+        nanoseconds: 16,
+        ppq_pos: 24,
+        tempo: 32,
+        bar_start_pos: 40,
+        cycle_start_pos: 48,
+        cycle_end_pos: 56,
+        time_sig_numerator: 64,
+        time_sig_denominator: 68,
+        smpte_offset: 72,
+        smpte_frame_rate: 76,
+        samples_to_next_clock: 80,
+        flags: 84,
+    });
+}
+
+#[test]
+fn channel_properties() {
+    assert_layout!(ChannelProperties {
+        size: 128,
+        align: 4,
+        name: 0,
+        flags: 64,
+        arrangement_type: 68,
+        short_name: 72,
+        future: 80,
+    });
+}
+
+#[test]
+fn parameter_properties() {
+    assert_layout!(ParameterProperties {
+        size: 152,
+        align: 4,
+        step_float: 0,
+        small_step_float: 4,
+        large_step_float: 8,
+        label: 12,
+        flags: 76,
+        min_integer: 80,
+        max_integer: 84,
+        step_integer: 88,
+        large_step_integer: 92,
+        short_label: 96,
+        display_index: 104,
+        category: 106,
+        num_parameters_in_category: 108,
+        _reserved: 110,
+        category_label: 112,
+        future: 136,
+    });
+}
+
+#[test]
+fn file_select() {
+    assert_layout!(FileType {
+        size: 408,
+        align: 1,
+        name: 0,
+        osx_type: 128,
+        win_type: 136,
+        nix_type: 144,
+        mime_type_1: 152,
+        mime_type_2: 280,
+    });
+
+    assert_layout!(FileSelect {
+        size: by_width(1216, 1184),
+        align: PTR,
+        command: 0,
+        select_type: 4,
+        mac_creator: 8,
+        num_types: 12,
+        file_types: 16,
+        title: 16 + PTR,
+        initial_path: by_width(1048, 1044),
+        return_path: by_width(1056, 1048),
+        size_return_path: by_width(1064, 1052),
+        return_multiple_paths: by_width(1072, 1056),
+        num_paths: by_width(1080, 1060),
+        reserved: by_width(1088, 1064),
+        future: by_width(1096, 1068),
+    });
+}
@@ -0,0 +1,8 @@
+//! Compile-fail tests for the `strict_threading` prototype.
+#![cfg(feature = "strict_threading")]
+
+#[test]
+fn process_thread_token() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
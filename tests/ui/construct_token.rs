@@ -0,0 +1,6 @@
+use vst::threading::ProcessThreadToken;
+
+fn main() {
+    // Only the crate can create a token, when it calls the plugin on the processing thread.
+    let _token = ProcessThreadToken::new();
+}
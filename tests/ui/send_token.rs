@@ -0,0 +1,30 @@
+#![allow(deprecated)]
+
+use std::thread;
+
+use vst::buffer::AudioBuffer;
+use vst::plugin::{HostCallback, Info, Plugin};
+use vst::threading::ProcessThreadToken;
+
+struct ThreadedPlugin;
+
+impl Plugin for ThreadedPlugin {
+    fn new(_host: HostCallback) -> Self {
+        ThreadedPlugin
+    }
+
+    fn get_info(&self) -> Info {
+        Default::default()
+    }
+
+    fn process_with_token(&mut self, _buffer: &mut AudioBuffer<f32>, token: &ProcessThreadToken) {
+        // The token must not leave the processing thread.
+        thread::scope(|scope| {
+            scope.spawn(|| process_only(token));
+        });
+    }
+}
+
+fn process_only(_token: &ProcessThreadToken) {}
+
+fn main() {}
@@ -0,0 +1,26 @@
+#![allow(deprecated)]
+
+use vst::buffer::AudioBuffer;
+use vst::plugin::{HostCallback, Info, Plugin};
+use vst::threading::ProcessThreadToken;
+
+struct StoringPlugin {
+    token: Option<&'static ProcessThreadToken>,
+}
+
+impl Plugin for StoringPlugin {
+    fn new(_host: HostCallback) -> Self {
+        StoringPlugin { token: None }
+    }
+
+    fn get_info(&self) -> Info {
+        Default::default()
+    }
+
+    fn process_with_token(&mut self, _buffer: &mut AudioBuffer<f32>, token: &ProcessThreadToken) {
+        // The token is only valid for the duration of the call.
+        self.token = Some(token);
+    }
+}
+
+fn main() {}